@@ -0,0 +1,251 @@
+//! Scale check marks so people can tell if their printer has resized a template
+use polygonical::point::Point;
+
+use crate::length::{Length, Unit};
+use crate::page::Page;
+use crate::text;
+use crate::{path, Element};
+
+/// Where to put the calibration block on the page
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Placement {
+    /// In the top left corner just inside the borders
+    TopLeft,
+    /// In the top right corner just inside the borders
+    TopRight,
+    /// In the bottom left corner just inside the borders
+    BottomLeft,
+    /// In the bottom right corner just inside the borders
+    BottomRight,
+    /// With its top left corner at this point
+    At(Point),
+}
+
+/// Space between the parts of the block
+const GAP: Length = Length {
+    value: 0.125,
+    unit: Unit::In,
+};
+
+/// Length of the inch ruler in inches
+const RULER_INCHES: u32 = 3;
+
+/// Length of the millimetre ruler in millimetres
+const RULER_MM: u32 = 70;
+
+/// The width and height in pixels of the block [`block`] draws
+pub fn block_size(dpi: i32) -> (f64, f64) {
+    let px = |l: Length| l.to_pixels(dpi).expect("calibration lengths are absolute");
+    (
+        px(Length::inches(1.0))
+            + px(GAP)
+            + px(Length::cm(2.0))
+            + px(GAP)
+            + px(Length::inches(RULER_INCHES as f64)),
+        px(Length::inches(1.0)) + font_size(dpi) * 1.5,
+    )
+}
+
+fn font_size(dpi: i32) -> f64 {
+    Length::pt(8.0)
+        .to_pixels(dpi)
+        .expect("calibration lengths are absolute")
+}
+
+/// Draw a block of scale checks at the exact physical size the page's dpi implies.
+///
+/// The block has a one inch square, a two centimetre square, a three inch ruler marked down to sixteenths, and a
+/// seventy millimetre ruler labelled every centimetre, with a line asking people to check them. See [`block_size`]
+/// for how much room it needs.
+///
+/// ```
+/// use esvg::calibration::{block, Placement};
+/// use esvg::create_document;
+/// use esvg::page::Page;
+///
+/// let page = Page::letter(96);
+/// let mut doc = create_document(&page);
+/// doc.add(&block(&page, Placement::BottomLeft));
+/// ```
+pub fn block(page: &Page, placement: Placement) -> Element {
+    let dpi = page.dpi;
+    let px = |l: Length| l.to_pixels(dpi).expect("calibration lengths are absolute");
+    let (width, height) = block_size(dpi);
+
+    let origin = match placement {
+        Placement::TopLeft => page.top_left(),
+        Placement::TopRight => Point::new(page.top_right().x - width, page.top_right().y),
+        Placement::BottomLeft => Point::new(page.bottom_left().x, page.bottom_left().y - height),
+        Placement::BottomRight => Point::new(
+            page.bottom_right().x - width,
+            page.bottom_right().y - height,
+        ),
+        Placement::At(p) => p,
+    };
+
+    let line_width = px(Length::pt(0.5));
+    let font_size = font_size(dpi);
+    let style = format!("font-family:sans-serif;font-size:{font_size}px;fill:black");
+    let centred = format!("{style};text-anchor:middle");
+
+    let mut group = Element::group();
+    group.set("class", "calibration");
+    group.set(
+        "transform",
+        format!("translate({}, {})", origin.x, origin.y),
+    );
+
+    let mut stroked = |data: &path::Data| {
+        let mut el = data.to_path();
+        el.set("stroke", "black");
+        el.set("stroke-width", line_width);
+        group.add(&el);
+    };
+
+    let inch = px(Length::inches(1.0));
+    let cm2 = px(Length::cm(2.0));
+    let gap = px(GAP);
+
+    // the squares
+    stroked(&square(Point::zero(), inch));
+    let cm_left = inch + gap;
+    stroked(&square(Point::new(cm_left, 0.0), cm2));
+
+    // the inch ruler hangs down from its top edge
+    let ruler_left = cm_left + cm2 + gap;
+    let mut ticks = path::Data::new();
+    ticks
+        .move_to(Point::new(ruler_left, 0.0))
+        .line_to(Point::new(ruler_left + inch * RULER_INCHES as f64, 0.0));
+    for i in 0..=(RULER_INCHES * 16) {
+        let tick = match i {
+            i if i % 16 == 0 => 0.3,
+            i if i % 8 == 0 => 0.2,
+            i if i % 4 == 0 => 0.15,
+            i if i % 2 == 0 => 0.1,
+            _ => 0.06,
+        };
+        let x = ruler_left + inch * i as f64 / 16.0;
+        ticks
+            .move_to(Point::new(x, 0.0))
+            .line_to(Point::new(x, inch * tick));
+    }
+
+    // the millimetre ruler stands up from its bottom edge
+    let mm = px(Length::mm(1.0));
+    ticks
+        .move_to(Point::new(ruler_left, inch))
+        .line_to(Point::new(ruler_left + mm * RULER_MM as f64, inch));
+    for i in 0..=RULER_MM {
+        let tick = match i {
+            i if i % 10 == 0 => 0.2,
+            i if i % 5 == 0 => 0.12,
+            _ => 0.06,
+        };
+        let x = ruler_left + mm * i as f64;
+        ticks
+            .move_to(Point::new(x, inch))
+            .line_to(Point::new(x, inch * (1.0 - tick)));
+    }
+    stroked(&ticks);
+
+    let mut label = |s: String, at: Point, style: &str| {
+        group.add(&text::create_text(s, at, style));
+    };
+
+    label(
+        "1 in".to_string(),
+        Point::new(inch / 2.0, inch / 2.0 + font_size / 3.0),
+        &centred,
+    );
+    label(
+        "2 cm".to_string(),
+        Point::new(cm_left + cm2 / 2.0, cm2 / 2.0 + font_size / 3.0),
+        &centred,
+    );
+    for i in 0..=RULER_INCHES {
+        label(
+            format!("{i}"),
+            Point::new(ruler_left + inch * i as f64, inch * 0.3 + font_size),
+            &centred,
+        );
+    }
+    label(
+        "in".to_string(),
+        Point::new(
+            ruler_left + inch * RULER_INCHES as f64 + font_size / 2.0,
+            font_size,
+        ),
+        &style,
+    );
+    for i in (0..=RULER_MM).step_by(10) {
+        label(
+            format!("{i}"),
+            Point::new(ruler_left + mm * i as f64, inch * 0.8 - font_size / 3.0),
+            &centred,
+        );
+    }
+    label(
+        "mm".to_string(),
+        Point::new(ruler_left + mm * RULER_MM as f64 + font_size / 2.0, inch),
+        &style,
+    );
+    label(
+        "Print at 100% scale, the squares should measure exactly 1 inch and 2 cm".to_string(),
+        Point::new(0.0, inch + font_size * 1.25),
+        &style,
+    );
+
+    group
+}
+
+fn square(corner: Point, size: f64) -> path::Data {
+    let mut data = path::Data::from_points(&[
+        corner,
+        Point::new(corner.x + size, corner.y),
+        Point::new(corner.x + size, corner.y + size),
+        Point::new(corner.x, corner.y + size),
+    ]);
+    data.close();
+    data
+}
+
+#[cfg(test)]
+mod tests {
+
+    use polygonical::point::Point;
+
+    use super::{block, block_size, Placement};
+    use crate::page::Page;
+
+    #[test]
+    fn physical_size() {
+        let page = Page::letter(300);
+        let el = block(&page, Placement::At(Point::zero()));
+        let squares: Vec<_> = el.elements().take(2).map(|e| e.get("d").unwrap()).collect();
+        assert_eq!(
+            squares[0],
+            "M0.000 0.000 L300.000 0.000 L300.000 300.000 L0.000 300.000 z"
+        );
+        assert_eq!(
+            squares[1],
+            "M337.500 0.000 L573.720 0.000 L573.720 236.220 L337.500 236.220 z"
+        );
+    }
+
+    #[test]
+    fn placement() {
+        let page = Page::letter(96);
+        let (w, h) = block_size(96);
+        assert_eq!(w, 96.0 + 12.0 + 75.59055118110237 + 12.0 + 288.0);
+
+        let el = block(&page, Placement::TopLeft);
+        assert_eq!(el.get("transform").unwrap(), "translate(48, 48)");
+
+        let el = block(&page, Placement::BottomRight);
+        assert_eq!(
+            el.get("transform").unwrap(),
+            format!("translate({}, {})", 816.0 - 48.0 - w, 1056.0 - 48.0 - h)
+        );
+    }
+}
@@ -1,25 +1,42 @@
 //! A collection of useful conversion functions
 use std::f64;
 use std::f64::consts::PI;
+use std::fmt;
 use std::str::FromStr;
 
 use crate::error;
+use crate::length::{self, Context, Length};
 
 /// convert value of inches to pixels given the dpi
-pub fn inches_to_pixels(value: f64, dpi: i32) -> i32 {
-    (value * (dpi as f64)).round() as i32
+pub fn inches_to_pixels(value: f64, dpi: i32) -> f64 {
+    value * (dpi as f64)
 }
 
 /// convert value of centimeters to pixels given the dpi
-pub fn cm_to_pixels(value: f64, dpi: i32) -> i32 {
+pub fn cm_to_pixels(value: f64, dpi: i32) -> f64 {
     inches_to_pixels(cm_to_inches(value), dpi)
 }
 
 /// convert value of millimeters to pixels given the dpi
-pub fn mm_to_pixels(value: f64, dpi: i32) -> i32 {
+pub fn mm_to_pixels(value: f64, dpi: i32) -> f64 {
     inches_to_pixels(mm_to_inches(value), dpi)
 }
 
+/// convert value of points (1/72 inch) to pixels given the dpi
+pub fn pt_to_pixels(value: f64, dpi: i32) -> f64 {
+    inches_to_pixels(value / 72.0, dpi)
+}
+
+/// convert value of picas (1/6 inch) to pixels given the dpi
+pub fn pc_to_pixels(value: f64, dpi: i32) -> f64 {
+    inches_to_pixels(value / 6.0, dpi)
+}
+
+/// convert value of feet to pixels given the dpi
+pub fn feet_to_pixels(value: f64, dpi: i32) -> f64 {
+    inches_to_pixels(value * 12.0, dpi)
+}
+
 /// Convert centimeters to inches
 pub fn cm_to_inches(value: f64) -> f64 {
     (value * 10.0) / 25.4
@@ -41,93 +58,58 @@ pub fn inches_to_cm(value: f64) -> f64 {
 }
 
 /// Convert a number of pixels to millimeters given the dpi
-pub fn pixels_to_mm(value: i32, dpi: i32) -> f64 {
-    inches_to_mm(value as f64 / dpi as f64)
+pub fn pixels_to_mm(value: f64, dpi: i32) -> f64 {
+    inches_to_mm(value / dpi as f64)
 }
 
 /// Convert a number of pixels to centimeters given the dpi
-pub fn pixels_to_cm(value: i32, dpi: i32) -> f64 {
-    inches_to_cm(value as f64 / dpi as f64)
+pub fn pixels_to_cm(value: f64, dpi: i32) -> f64 {
+    inches_to_cm(value / dpi as f64)
 }
 
 /// Convert a number of pixels to inches given the dpi
-pub fn pixels_to_inches(value: i32, dpi: i32) -> f64 {
-    value as f64 / dpi as f64
+pub fn pixels_to_inches(value: f64, dpi: i32) -> f64 {
+    value / dpi as f64
 }
 
-/// Parse a length. Handles unit suffixes
+/// Parse a length into pixels. Handles unit suffixes, see [`Length`] if you want to keep the unit.
+///
+/// Accepts any of the units in [`crate::length::Unit`], scientific notation ("1e3mm"), signs, whitespace before the
+/// unit, and fractions ("2 1/4in"). Em and ex use the font size of [`Context::new`], and percentages are an error as
+/// there is nothing for them to be a percentage of, see [`parse_length_in`] to provide those.
 ///
 /// If no suffix is provided it will assume inches. (Sorry this pains me too, but quilters mostly seem to be americans,
 /// and americans use inches for everything.)
 ///
+/// The result is not rounded, so no precision is lost until you decide to.
+///
 /// ```
 /// use esvg::convert::parse_length;
-/// assert_eq!(parse_length("27in", 96).unwrap(), 2592);
-/// assert_eq!(parse_length("2.5mm", 96).unwrap(), 9);
-/// assert_eq!(parse_length("2 4/16in", 96).unwrap(), 216);
+/// assert_eq!(parse_length("27in", 96).unwrap(), 2592.0);
+/// assert_eq!(parse_length("2.5mm", 96).unwrap(), 9.448818897637796);
+/// assert_eq!(parse_length("2 4/16in", 96).unwrap(), 216.0);
+/// assert_eq!(parse_length("36pt", 96).unwrap(), 48.0);
 /// ```
-pub fn parse_length(value: &str, dpi: i32) -> Result<i32, error::Error> {
-    let l = value.len();
-
-    match extract_unit(value)? {
-        "mm" => {
-            let mm = f64::from_str(&value[..l - 2])?;
-            Ok(mm_to_pixels(mm, dpi))
-        }
-        "cm" => {
-            let cm = f64::from_str(&value[..l - 2])?;
-            Ok(cm_to_pixels(cm, dpi))
-        }
-        "in" => {
-            // Inches are often provided in fractions so lets try and figure those out.
-            // examples: "1 1/2in", "5/8in", "3/16in", "7 1/4in", "1.544in", "1in"
-            parse_inches(value, dpi)
-        }
-        "px" => {
-            let px = i32::from_str(&value[..l - 2])?;
-            Ok(px)
-        }
-        _ => parse_inches(value, dpi),
-    }
+pub fn parse_length(value: &str, dpi: i32) -> Result<f64, error::Error> {
+    Length::from_str(value)?.resolve(&Context::new(dpi))
 }
 
-fn parse_inches(value: &str, dpi: i32) -> Result<i32, error::Error> {
-    let l = value.len();
-
-    let numeric_part: &str = if value.ends_with("in") {
-        &value[..l - 2]
-    } else {
-        value
-    };
-
-    let inches = if numeric_part.contains(' ') || numeric_part.contains('/') {
-        let (whole_inches, remainder) = if let Some(i) = numeric_part.find(' ') {
-            // some kind of whole number
-            (f64::from_str(numeric_part[..i].trim())?, &numeric_part[i..])
-        } else {
-            (0.0, numeric_part)
-        };
-
-        // fractional part
-        let (top, bottom) = if let Some(i) = remainder.find('/') {
-            (
-                f64::from_str(remainder[..i].trim())?,
-                f64::from_str(remainder[i + 1..].trim())?,
-            )
-        } else {
-            (1.0, 1.0)
-        };
-
-        whole_inches + (top / bottom)
-    } else {
-        f64::from_str(numeric_part)?
-    };
-    Ok(inches_to_pixels(inches, dpi))
+/// Parse a length into pixels, with percentages, em, and ex measured against the context.
+///
+/// ```
+/// use esvg::convert::parse_length_in;
+/// use esvg::length::Context;
+///
+/// let ctx = Context::new(96).with_reference(1000.0);
+/// assert_eq!(parse_length_in("10%", &ctx).unwrap(), 100.0);
+/// ```
+pub fn parse_length_in(value: &str, ctx: &Context) -> Result<f64, error::Error> {
+    Length::from_str(value)?.resolve(ctx)
 }
 
 /// Convert a pixel length into a specified unit.
-/// Supports "mm", "cm", "in", and "px" values for units
-pub fn px_to_length(value: i32, unit: &str, dpi: i32) -> Result<String, error::Error> {
+/// Supports "mm", "cm", "in", "pt", "pc", "ft", and "px" values for units
+pub fn px_to_length(value: f64, unit: &str, dpi: i32) -> Result<String, error::Error> {
     match unit {
         "mm" => {
             let mm = pixels_to_mm(value, dpi);
@@ -141,6 +123,18 @@ pub fn px_to_length(value: i32, unit: &str, dpi: i32) -> Result<String, error::E
             let inches = pixels_to_inches(value, dpi);
             Ok(format!("{inches:.2}in"))
         }
+        "pt" => {
+            let pt = pixels_to_inches(value, dpi) * 72.0;
+            Ok(format!("{pt:.2}pt"))
+        }
+        "pc" => {
+            let pc = pixels_to_inches(value, dpi) * 6.0;
+            Ok(format!("{pc:.2}pc"))
+        }
+        "ft" => {
+            let ft = pixels_to_inches(value, dpi) / 12.0;
+            Ok(format!("{ft:.2}ft"))
+        }
         "px" => Ok(format!("{value}px")),
         _ => {
             let inches = pixels_to_inches(value, dpi);
@@ -149,17 +143,121 @@ pub fn px_to_length(value: i32, unit: &str, dpi: i32) -> Result<String, error::E
     }
 }
 
-/// get the unit suffix from a string, if it has one.
-pub fn extract_unit(value: &str) -> Result<&str, error::Error> {
-    let l = value.len();
-    if l > 2 {
-        if value[l - 2..].chars().any(char::is_numeric) {
-            Ok("")
+/// A length in inches rounded to the nearest fraction, as read off a quilting ruler.
+///
+/// The `Display` implementation writes it in reduced mixed number form, e.g. `2 1/4in`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FractionalInches {
+    /// True if the length is below zero
+    pub negative: bool,
+    /// Whole number of inches
+    pub whole: u32,
+    /// Numerator of the fractional part, zero if the length is a whole number of inches
+    pub numerator: u32,
+    /// Denominator of the fractional part, always reduced. One if the length is a whole number of inches
+    pub denominator: u32,
+    /// How far the rounded value is from the real length, in inches. Never more than half of one over the maximum
+    /// denominator requested.
+    pub error: f64,
+}
+
+impl FractionalInches {
+    /// The value of the rounded length in inches
+    pub fn inches(&self) -> f64 {
+        let value = self.whole as f64 + (self.numerator as f64 / self.denominator as f64);
+        if self.negative {
+            -value
         } else {
-            Ok(&value[l - 2..])
+            value
         }
+    }
+}
+
+impl fmt::Display for FractionalInches {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.negative {
+            write!(formatter, "-")?;
+        }
+        match (self.whole, self.numerator) {
+            (w, 0) => write!(formatter, "{w}in"),
+            (0, n) => write!(formatter, "{n}/{}in", self.denominator),
+            (w, n) => write!(formatter, "{w} {n}/{}in", self.denominator),
+        }
+    }
+}
+
+/// Round a pixel length to the nearest multiple of 1/denominator of an inch, the way it would be read off a ruler.
+///
+/// Common choices for the denominator are 8, 16, or 32. The fraction is reduced so an eighth is never written as
+/// two sixteenths, but only multiples of 1/denominator are tried: with 10 a third comes out as 3/10, not 1/3.
+/// Lengths with more whole inches than fit in a `u32`, or that aren't finite, are an error.
+///
+/// ```
+/// use esvg::convert::px_to_fraction;
+/// let f = px_to_fraction(216.0, 96, 16).unwrap();
+/// assert_eq!(f.to_string(), "2 1/4in");
+/// assert_eq!(f.error, 0.0);
+///
+/// let f = px_to_fraction(100.0, 96, 8).unwrap();
+/// assert_eq!(f.to_string(), "1in");
+/// assert!(f.error <= 1.0 / 16.0);
+/// ```
+pub fn px_to_fraction(
+    value: f64,
+    dpi: i32,
+    denominator: u32,
+) -> Result<FractionalInches, error::Error> {
+    if denominator == 0 {
+        return Err(error::Error::InvalidDenominator(denominator));
+    }
+
+    let inches = pixels_to_inches(value, dpi);
+    if !inches.is_finite() {
+        return Err(error::Error::NotFinite(value));
+    }
+    let steps = (inches.abs() * denominator as f64).round() as u64;
+    let whole = u32::try_from(steps / denominator as u64)
+        .map_err(|_| error::Error::InvalidLength(format!("{value}px")))?;
+    let mut numerator = (steps % denominator as u64) as u32;
+    let mut denominator = denominator;
+
+    let divisor = gcd(numerator, denominator);
+    numerator /= divisor;
+    denominator /= divisor;
+
+    let mut result = FractionalInches {
+        negative: inches < 0.0 && steps != 0,
+        whole,
+        numerator,
+        denominator,
+        error: 0.0,
+    };
+    result.error = (inches - result.inches()).abs();
+
+    Ok(result)
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a
     } else {
-        Ok("")
+        gcd(b, a % b)
+    }
+}
+
+/// get the unit suffix from a string, if it has one.
+///
+/// ```
+/// use esvg::convert::extract_unit;
+/// assert_eq!(extract_unit("5pt").unwrap(), "pt");
+/// assert_eq!(extract_unit("10 %").unwrap(), "%");
+/// assert_eq!(extract_unit("2 1/4").unwrap(), "");
+/// assert!(extract_unit("mm").is_err());
+/// ```
+pub fn extract_unit(value: &str) -> Result<&str, error::Error> {
+    match length::tokenize(value) {
+        Some((_, unit)) => Ok(unit),
+        None => Err(error::Error::InvalidLength(value.to_string())),
     }
 }
 
@@ -229,7 +327,7 @@ pub const DEG_360: f64 = 360.0 * (PI / 180.0);
 #[cfg(test)]
 mod tests {
 
-    use crate::convert::parse_length;
+    use crate::convert::{parse_length, px_to_fraction, px_to_length};
 
     use super::parse_colour;
     #[test]
@@ -251,21 +349,79 @@ mod tests {
     #[test]
     pub fn parse_length_valid() {
         let value = parse_length("2.5", 96).unwrap();
-        assert_eq!(value, 240);
+        assert_eq!(value, 240.0);
 
         let value = parse_length("2.5cm", 96).unwrap();
-        assert_eq!(value, 94);
+        assert_eq!(value, 94.48818897637796);
 
         let value = parse_length("2.5mm", 96).unwrap();
-        assert_eq!(value, 9);
+        assert_eq!(value, 9.448818897637796);
 
         let value = parse_length("2 4/8in", 96).unwrap();
-        assert_eq!(value, 240);
+        assert_eq!(value, 240.0);
 
         let value = parse_length("1", 96).unwrap();
-        assert_eq!(value, 96);
+        assert_eq!(value, 96.0);
 
         let value = parse_length("5/10", 96).unwrap();
-        assert_eq!(value, 48);
+        assert_eq!(value, 48.0);
+
+        let value = parse_length("12.5px", 96).unwrap();
+        assert_eq!(value, 12.5);
+
+        let value = parse_length("-1e1mm ", 96).unwrap();
+        assert_eq!(value, -37.795275590551185);
+    }
+
+    #[test]
+    pub fn parse_length_extra_units() {
+        assert_eq!(parse_length("72pt", 96).unwrap(), 96.0);
+        assert_eq!(parse_length("3pc", 96).unwrap(), 48.0);
+        assert_eq!(parse_length("1.5ft", 96).unwrap(), 1728.0);
+        assert_eq!(
+            parse_length("4Q", 96).unwrap(),
+            parse_length("1mm", 96).unwrap()
+        );
+        // percentages need something to be a percentage of
+        assert!(parse_length("10%", 96).is_err());
+        assert_eq!(parse_length("1em", 96).unwrap(), 16.0);
+    }
+
+    #[test]
+    pub fn px_to_length_extra_units() {
+        assert_eq!(px_to_length(96.0, "pt", 96).unwrap(), "72.00pt");
+        assert_eq!(px_to_length(48.0, "pc", 96).unwrap(), "3.00pc");
+        assert_eq!(px_to_length(1728.0, "ft", 96).unwrap(), "1.50ft");
+    }
+
+    #[test]
+    pub fn px_to_fraction_valid() {
+        assert_eq!(px_to_fraction(18.0, 96, 32).unwrap().to_string(), "3/16in");
+        assert_eq!(
+            px_to_fraction(-216.0, 96, 16).unwrap().to_string(),
+            "-2 1/4in"
+        );
+        assert_eq!(px_to_fraction(192.0, 96, 8).unwrap().to_string(), "2in");
+        assert_eq!(px_to_fraction(1.0, 96, 8).unwrap().to_string(), "0in");
+
+        // 101px is 1.052in, the nearest 32nd is 1 1/16in which is off by 0.0104in
+        let f = px_to_fraction(101.0, 96, 32).unwrap();
+        assert_eq!(f.to_string(), "1 1/16in");
+        assert!((f.error - (1.0625 - 101.0 / 96.0)).abs() < 1e-12);
+        assert!(f.error <= 1.0 / 64.0);
+
+        assert!(px_to_fraction(10.0, 96, 0).is_err());
+
+        // only tenths are tried, so a third is rounded to one of them
+        assert_eq!(px_to_fraction(32.0, 96, 10).unwrap().to_string(), "3/10in");
+        assert_eq!(px_to_fraction(48.0, 96, 10).unwrap().to_string(), "1/2in");
+
+        // too many whole inches to count, rather than wrapping round
+        assert!(px_to_fraction(96.0 * 5e9, 96, 8).is_err());
+        assert_eq!(
+            px_to_fraction(96.0 * u32::MAX as f64, 96, 8).unwrap().whole,
+            u32::MAX
+        );
+        assert!(px_to_fraction(f64::NAN, 96, 8).is_err());
     }
 }
@@ -0,0 +1,896 @@
+//! Find what changed between two documents as a list of edits, and apply those edits to other documents
+//!
+//! ```
+//! use esvg::diff::diff;
+//! use esvg::read::parse_string;
+//!
+//! let before = parse_string(r#"<svg><rect id="logo" fill="red" /><text>Draft</text></svg>"#.to_string()).unwrap();
+//! let after = parse_string(r#"<svg><rect id="logo" fill="blue" /><text>Final</text></svg>"#.to_string()).unwrap();
+//!
+//! let patch = diff(&before, &after);
+//! assert_eq!(patch.to_string(), "set fill=\"blue\" on #logo\nchange /1/0 to \"Final\"\n");
+//!
+//! // the same edit on a document made from the same template
+//! let mut other = parse_string(r#"<svg><rect id="logo" fill="red" x="5" /><text>Draft</text></svg>"#.to_string()).unwrap();
+//! patch.apply(&mut other).unwrap();
+//! assert_eq!(other.find_by_id("logo").unwrap().get("fill").unwrap(), "blue");
+//! ```
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::Error;
+use crate::{Element, Node};
+
+/// Above this many pairs of children the careful matching is skipped and children are matched in order instead
+const MATCH_LIMIT: usize = 4_000_000;
+
+/// Where a node is, as the nearest element with an id above it and then child positions from there.
+///
+/// Written as `#id/2/0` for the first child of the third child of the element with id `id`, or `/2/0` counting from
+/// the root. Positions count every child including text, the way they are in [`Element::children`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Address {
+    pub id: Option<String>,
+    pub path: Vec<usize>,
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(id) = &self.id {
+            write!(formatter, "#{id}")?;
+        } else if self.path.is_empty() {
+            write!(formatter, "/")?;
+        }
+        for index in &self.path {
+            write!(formatter, "/{index}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Address {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidPatch(format!("bad address {value:?}"));
+        let (id, rest) = match value.strip_prefix('#') {
+            Some(rest) => match rest.split_once('/') {
+                Some((id, path)) => (Some(id.to_string()), path),
+                None => (Some(rest.to_string()), ""),
+            },
+            None => (None, value.strip_prefix('/').ok_or_else(invalid)?),
+        };
+        if id.as_deref() == Some("") {
+            return Err(invalid());
+        }
+        let path = rest
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .map(|s| s.parse().map_err(|_| invalid()))
+            .collect::<Result<_, _>>()?;
+        Ok(Address { id, path })
+    }
+}
+
+/// One edit to a document
+#[derive(Debug, Clone)]
+pub enum Operation {
+    /// Put a node into an element's children at index
+    Insert {
+        parent: Address,
+        index: usize,
+        node: Node,
+    },
+    Remove {
+        target: Address,
+    },
+    /// Take a node out of where it is and put it into an element's children at index. The parent is looked up after
+    /// the node has been taken out.
+    Move {
+        target: Address,
+        parent: Address,
+        index: usize,
+    },
+    SetAttribute {
+        target: Address,
+        name: String,
+        value: String,
+    },
+    RemoveAttribute {
+        target: Address,
+        name: String,
+    },
+    /// Replace the text of a text node or comment
+    SetText {
+        target: Address,
+        text: String,
+    },
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operation::Insert {
+                parent,
+                index,
+                node,
+            } => write!(
+                formatter,
+                "insert {} into {parent} at {index}",
+                describe(node)
+            ),
+            Operation::Remove { target } => write!(formatter, "remove {target}"),
+            Operation::Move {
+                target,
+                parent,
+                index,
+            } => write!(formatter, "move {target} into {parent} at {index}"),
+            Operation::SetAttribute {
+                target,
+                name,
+                value,
+            } => write!(formatter, "set {name}={value:?} on {target}"),
+            Operation::RemoveAttribute { target, name } => {
+                write!(formatter, "remove {name} from {target}")
+            }
+            Operation::SetText { target, text } => write!(formatter, "change {target} to {text:?}"),
+        }
+    }
+}
+
+/// A list of edits that turn one document into another, see [`diff`]
+#[derive(Debug, Clone, Default)]
+pub struct Patch {
+    pub operations: Vec<Operation>,
+}
+
+impl fmt::Display for Patch {
+    /// One operation per line, meant for people to read
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for operation in &self.operations {
+            writeln!(formatter, "{operation}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Patch {
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+
+    /// Apply the edits in order. If any of them can't be done the document is left as it was.
+    ///
+    /// Nodes are found by id where the patch has one, so a patch made from one document can be applied to another
+    /// one made from the same template as long as the parts that were edited line up.
+    pub fn apply(&self, doc: &mut Element) -> Result<(), Error> {
+        let mut result = doc.clone();
+        for operation in &self.operations {
+            apply(&mut result, operation)?;
+        }
+        *doc = result;
+        Ok(())
+    }
+
+    /// Write the patch as xml so it can be saved and read back with [`Patch::from_element`].
+    ///
+    /// ```
+    /// use esvg::diff::{diff, Patch};
+    /// use esvg::read::parse_string;
+    ///
+    /// let before = parse_string(r#"<svg><g id="a" /><circle r="1" /></svg>"#.to_string()).unwrap();
+    /// let after = parse_string(r#"<svg><g id="a"><circle r="2" /></g></svg>"#.to_string()).unwrap();
+    /// let saved = diff(&before, &after).to_element().to_pretty_string();
+    /// assert!(saved.starts_with("<patch>"));
+    ///
+    /// let patch = Patch::from_element(&parse_string(saved).unwrap()).unwrap();
+    /// let mut doc = before.clone();
+    /// patch.apply(&mut doc).unwrap();
+    /// assert_eq!(doc.to_string(), after.to_string());
+    /// ```
+    pub fn to_element(&self) -> Element {
+        let mut result = Element::new("patch");
+        for operation in &self.operations {
+            let mut el = match operation {
+                Operation::Insert {
+                    parent,
+                    index,
+                    node,
+                } => {
+                    let mut el = Element::new("insert");
+                    el.set("parent", parent.to_string()).set("index", *index);
+                    match node {
+                        Node::Element(_) => {}
+                        Node::Text(_) => _ = el.set("kind", "text"),
+                        Node::Comment(_) => _ = el.set("kind", "comment"),
+                    };
+                    el.add_node(match node {
+                        Node::Comment(c) => Node::Text(c.clone()),
+                        other => other.clone(),
+                    });
+                    el
+                }
+                Operation::Remove { .. } => Element::new("remove"),
+                Operation::Move { parent, index, .. } => {
+                    let mut el = Element::new("move");
+                    el.set("parent", parent.to_string()).set("index", *index);
+                    el
+                }
+                Operation::SetAttribute { name, value, .. } => {
+                    let mut el = Element::new("set-attribute");
+                    el.set("name", name.as_str()).set("value", value.as_str());
+                    el
+                }
+                Operation::RemoveAttribute { name, .. } => {
+                    let mut el = Element::new("remove-attribute");
+                    el.set("name", name.as_str());
+                    el
+                }
+                Operation::SetText { text, .. } => {
+                    let mut el = Element::new("set-text");
+                    el.add_node(Node::Text(text.clone()));
+                    el
+                }
+            };
+            if let Some(target) = target(operation) {
+                el.set("target", target.to_string());
+            }
+            result.add(&el);
+        }
+        result
+    }
+
+    /// Read a patch written by [`Patch::to_element`]
+    pub fn from_element(element: &Element) -> Result<Patch, Error> {
+        if element.name != "patch" {
+            return Err(Error::InvalidPatch(format!(
+                "expected <patch> not <{}>",
+                element.name
+            )));
+        }
+        let mut operations = vec![];
+        for el in element.elements() {
+            let attribute = |name: &str| {
+                el.get(name).ok_or_else(|| {
+                    Error::InvalidPatch(format!("<{}> is missing {name:?}", el.name))
+                })
+            };
+            let address = |name: &str| attribute(name)?.parse::<Address>();
+            let index = || {
+                attribute("index")?
+                    .parse::<usize>()
+                    .map_err(|_| Error::InvalidPatch(format!("bad index in <{}>", el.name)))
+            };
+            let text = || match el.children.first() {
+                Some(Node::Text(t)) => t.clone(),
+                _ => String::new(),
+            };
+
+            operations.push(match el.name.as_str() {
+                "insert" => Operation::Insert {
+                    parent: address("parent")?,
+                    index: index()?,
+                    node: match el.get("kind").as_deref() {
+                        Some("text") => Node::Text(text()),
+                        Some("comment") => Node::Comment(text()),
+                        _ => Node::Element(el.elements().next().cloned().ok_or_else(|| {
+                            Error::InvalidPatch("<insert> has nothing to insert".to_string())
+                        })?),
+                    },
+                },
+                "remove" => Operation::Remove {
+                    target: address("target")?,
+                },
+                "move" => Operation::Move {
+                    target: address("target")?,
+                    parent: address("parent")?,
+                    index: index()?,
+                },
+                "set-attribute" => Operation::SetAttribute {
+                    target: address("target")?,
+                    name: attribute("name")?,
+                    value: attribute("value")?,
+                },
+                "remove-attribute" => Operation::RemoveAttribute {
+                    target: address("target")?,
+                    name: attribute("name")?,
+                },
+                "set-text" => Operation::SetText {
+                    target: address("target")?,
+                    text: text(),
+                },
+                other => return Err(Error::InvalidPatch(format!("unknown operation <{other}>"))),
+            });
+        }
+        Ok(Patch { operations })
+    }
+}
+
+/// Work out the edits that turn old into new.
+///
+/// Elements with the same id in both documents are matched up wherever they are, so moving one into another group
+/// is a single move. Everything else is matched among its siblings by keeping as much as possible in the same
+/// order, and elements with the same name that don't match exactly are edited rather than replaced. The root
+/// elements are always matched with each other.
+///
+/// Applying the patch to old gives a document the same as new.
+pub fn diff(old: &Element, new: &Element) -> Patch {
+    let old_ids = unique_ids(old);
+    let new_ids = unique_ids(new);
+
+    let mut differ = Differ {
+        work: old.clone(),
+        shared: old_ids
+            .into_iter()
+            .filter(|(id, name)| new_ids.get(id) == Some(name))
+            .map(|(id, _)| id)
+            .collect(),
+        operations: vec![],
+    };
+    differ.element(vec![], new);
+    Patch {
+        operations: differ.operations,
+    }
+}
+
+/// Keeps a copy of the old document with the edits so far applied, so each new edit can be addressed against the
+/// document as it will be when the patch gets to it
+struct Differ {
+    work: Element,
+    /// Ids used once in both documents on the same sort of element, these are matched by id instead of by position
+    shared: HashSet<String>,
+    operations: Vec<Operation>,
+}
+
+impl Differ {
+    fn record(&mut self, operation: Operation) {
+        apply(&mut self.work, &operation).expect("diff made an operation it can't apply");
+        self.operations.push(operation);
+    }
+
+    fn address(&self, path: &[usize]) -> Address {
+        address(&self.work, path)
+    }
+
+    fn element(&mut self, path: Vec<usize>, new: &Element) {
+        let old = element_at(&self.work, &path).unwrap();
+        let mut names: Vec<String> = old.attributes.keys().cloned().collect();
+        names.extend(
+            new.attributes
+                .keys()
+                .filter(|k| !old.attributes.contains_key(*k))
+                .cloned(),
+        );
+        names.sort();
+        let changes: Vec<(String, Option<String>)> = names
+            .into_iter()
+            .map(|name| (new.get(&name), old.get(&name), name))
+            .filter(|(after, before, _)| after != before)
+            .map(|(after, _, name)| (name, after))
+            .collect();
+        // addressed one at a time as changing the id changes the address
+        for (name, value) in changes {
+            let target = self.address(&path);
+            self.record(match value {
+                Some(value) => Operation::SetAttribute {
+                    target,
+                    name,
+                    value,
+                },
+                None => Operation::RemoveAttribute { target, name },
+            });
+        }
+
+        self.children(path, new);
+    }
+
+    fn children(&mut self, path: Vec<usize>, new: &Element) {
+        let old = element_at(&self.work, &path).unwrap().children.clone();
+        let pairs = self.pair(&old, &new.children);
+        let at = |i: usize| {
+            let mut child = path.clone();
+            child.push(i);
+            child
+        };
+
+        // take out what isn't wanted, keeping anything with an id that is wanted somewhere else
+        let mut current: Vec<Option<usize>> = vec![];
+        for (k, node) in old.iter().enumerate().rev() {
+            let wanted = pairs.contains(&Some(k)) || self.shared_id(node).is_some();
+            if wanted {
+                current.insert(0, Some(k));
+            } else {
+                self.record(Operation::Remove {
+                    target: self.address(&at(k)),
+                });
+            }
+        }
+
+        // then put the children in order, moving in or inserting anything missing
+        let mut edit = vec![true; new.children.len()];
+        for (i, node) in new.children.iter().enumerate() {
+            if let Some(k) = pairs[i] {
+                let j = current.iter().position(|c| *c == Some(k)).unwrap();
+                if j != i {
+                    self.move_to(&at(j), &path, i);
+                    current.remove(j);
+                    current.insert(i, Some(k));
+                }
+                continue;
+            }
+
+            let elsewhere = self
+                .shared_id(node)
+                .and_then(|id| path_of(&self.work, &id))
+                .filter(|from| !path.starts_with(from));
+            match elsewhere {
+                Some(from) => self.move_to(&from, &path, i),
+                None => {
+                    let node = match node {
+                        Node::Element(e) if self.has_shared_ids(e) => {
+                            Node::Element(e.shallow_clone())
+                        }
+                        other => {
+                            edit[i] = false;
+                            other.clone()
+                        }
+                    };
+                    self.record(Operation::Insert {
+                        parent: self.address(&path),
+                        index: i,
+                        node,
+                    });
+                }
+            }
+            current.insert(i, None);
+        }
+
+        for (i, node) in new.children.iter().enumerate() {
+            if !edit[i] {
+                continue;
+            }
+            match node {
+                Node::Element(e) => self.element(at(i), e),
+                Node::Text(text) | Node::Comment(text) => {
+                    let same = match node_at(&self.work, &at(i)) {
+                        Some(Node::Text(t)) | Some(Node::Comment(t)) => t == text,
+                        _ => false,
+                    };
+                    if !same {
+                        self.record(Operation::SetText {
+                            target: self.address(&at(i)),
+                            text: text.clone(),
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    fn move_to(&mut self, from: &[usize], parent: &[usize], index: usize) {
+        let target = self.address(from);
+        // the parent is addressed after the node is taken out, which only changes the path if they share a parent
+        let mut parent = parent.to_vec();
+        if from.len() <= parent.len()
+            && from[..from.len() - 1] == parent[..from.len() - 1]
+            && from[from.len() - 1] < parent[from.len() - 1]
+        {
+            parent[from.len() - 1] -= 1;
+        }
+        let mut detached = self.work.clone();
+        take(&mut detached, from).unwrap();
+        self.record(Operation::Move {
+            target,
+            parent: address(&detached, &parent),
+            index,
+        });
+    }
+
+    fn shared_id(&self, node: &Node) -> Option<String> {
+        match node {
+            Node::Element(e) => e.get("id").filter(|id| self.shared.contains(id)),
+            _ => None,
+        }
+    }
+
+    fn has_shared_ids(&self, element: &Element) -> bool {
+        element.children.iter().any(|c| {
+            self.shared_id(c).is_some() || matches!(c, Node::Element(e) if self.has_shared_ids(e))
+        })
+    }
+
+    /// Which old child each new child is matched with, if any. Shared ids match by id and the rest keep as many
+    /// exactly equal children in order as they can, then match the same sort of node in the gaps between them.
+    fn pair(&self, old: &[Node], new: &[Node]) -> Vec<Option<usize>> {
+        let mut pairs = vec![None; new.len()];
+        for (i, node) in new.iter().enumerate() {
+            if let Some(id) = self.shared_id(node) {
+                pairs[i] = old
+                    .iter()
+                    .position(|o| self.shared_id(o).as_ref() == Some(&id));
+            }
+        }
+
+        let plain = |nodes: &[Node]| -> Vec<usize> {
+            (0..nodes.len())
+                .filter(|i| self.shared_id(&nodes[*i]).is_none())
+                .collect()
+        };
+        let old_plain = plain(old);
+        let new_plain = plain(new);
+        let old_text: Vec<String> = old_plain.iter().map(|i| old[*i].to_string()).collect();
+        let new_text: Vec<String> = new_plain.iter().map(|i| new[*i].to_string()).collect();
+
+        let mut anchors = if old_plain.len() * new_plain.len() <= MATCH_LIMIT {
+            longest_common(&old_text, &new_text)
+        } else {
+            vec![]
+        };
+        anchors.push((old_plain.len(), new_plain.len()));
+
+        let (mut o, mut n) = (0, 0);
+        for (anchor_o, anchor_n) in anchors {
+            let mut from = o;
+            for new_index in n..anchor_n {
+                let found = (from..anchor_o).find(|old_index| {
+                    kind(&old[old_plain[*old_index]]) == kind(&new[new_plain[new_index]])
+                });
+                if let Some(old_index) = found {
+                    pairs[new_plain[new_index]] = Some(old_plain[old_index]);
+                    from = old_index + 1;
+                }
+            }
+            if anchor_o < old_plain.len() {
+                pairs[new_plain[anchor_n]] = Some(old_plain[anchor_o]);
+            }
+            o = anchor_o + 1;
+            n = anchor_n + 1;
+        }
+        pairs
+    }
+}
+
+/// Positions of a longest run of equal items the two lists have in common, in order
+fn longest_common(a: &[String], b: &[String]) -> Vec<(usize, usize)> {
+    let width = b.len() + 1;
+    let mut lengths = vec![0u32; (a.len() + 1) * width];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lengths[i * width + j] = if a[i] == b[j] {
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut result = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            result.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[(i + 1) * width + j] >= lengths[i * width + j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    result
+}
+
+/// What sort of node this is, nodes can only be edited into another of the same sort
+fn kind(node: &Node) -> &str {
+    match node {
+        Node::Element(e) => e.name.as_str(),
+        Node::Text(_) => "#text",
+        Node::Comment(_) => "#comment",
+    }
+}
+
+fn describe(node: &Node) -> String {
+    match node {
+        Node::Element(e) if e.children.is_empty() => e.to_string(),
+        Node::Element(e) => format!("{} with {} children", e.shallow_clone(), e.children.len()),
+        Node::Text(t) => format!("text {t:?}"),
+        Node::Comment(c) => format!("comment {c:?}"),
+    }
+}
+
+/// Element names by id for ids that are only used once, anything with a repeated id is matched as if it didn't
+/// have one
+fn unique_ids(element: &Element) -> HashMap<String, String> {
+    fn walk(element: &Element, seen: &mut HashMap<String, String>, repeated: &mut HashSet<String>) {
+        if let Some(id) = element.get("id") {
+            if seen.insert(id.clone(), element.name.clone()).is_some() {
+                repeated.insert(id);
+            }
+        }
+        for child in element.elements() {
+            walk(child, seen, repeated);
+        }
+    }
+    let mut seen = HashMap::new();
+    let mut repeated = HashSet::new();
+    walk(element, &mut seen, &mut repeated);
+    seen.retain(|id, _| !repeated.contains(id));
+    seen
+}
+
+fn target(operation: &Operation) -> Option<&Address> {
+    match operation {
+        Operation::Insert { .. } => None,
+        Operation::Remove { target }
+        | Operation::Move { target, .. }
+        | Operation::SetAttribute { target, .. }
+        | Operation::RemoveAttribute { target, .. }
+        | Operation::SetText { target, .. } => Some(target),
+    }
+}
+
+/// The address of the node at path from the root, anchored on the closest element above it with an id that only
+/// appears once
+fn address(root: &Element, path: &[usize]) -> Address {
+    let mut anchor = (None, 0);
+    let mut element = root;
+    for depth in 0..=path.len() {
+        if let Some(id) = element.get("id") {
+            if root
+                .find_by_id(&id)
+                .is_some_and(|e| std::ptr::eq(e, element))
+            {
+                anchor = (Some(id), depth);
+            }
+        }
+        match path.get(depth).and_then(|i| element.children.get(*i)) {
+            Some(Node::Element(e)) => element = e,
+            _ => break,
+        }
+    }
+    Address {
+        id: anchor.0,
+        path: path[anchor.1..].to_vec(),
+    }
+}
+
+/// The path from the root to the element with an id
+fn path_of(element: &Element, id: &str) -> Option<Vec<usize>> {
+    if element.get("id").as_deref() == Some(id) {
+        return Some(vec![]);
+    }
+    element
+        .children
+        .iter()
+        .enumerate()
+        .find_map(|(i, c)| match c {
+            Node::Element(e) => path_of(e, id).map(|mut path| {
+                path.insert(0, i);
+                path
+            }),
+            _ => None,
+        })
+}
+
+fn resolve(root: &Element, address: &Address) -> Result<Vec<usize>, Error> {
+    let mut path = match &address.id {
+        Some(id) => path_of(root, id)
+            .ok_or_else(|| Error::PatchFailed(format!("nothing has the id {id:?}")))?,
+        None => vec![],
+    };
+    path.extend(&address.path);
+    if path.is_empty() || node_at(root, &path).is_some() {
+        Ok(path)
+    } else {
+        Err(Error::PatchFailed(format!("nothing at {address}")))
+    }
+}
+
+fn node_at<'a>(root: &'a Element, path: &[usize]) -> Option<&'a Node> {
+    let (last, parent) = path.split_last()?;
+    element_at(root, parent)?.children.get(*last)
+}
+
+fn element_at<'a>(root: &'a Element, path: &[usize]) -> Option<&'a Element> {
+    path.iter()
+        .try_fold(root, |el, i| match el.children.get(*i) {
+            Some(Node::Element(e)) => Some(e),
+            _ => None,
+        })
+}
+
+fn element_at_mut<'a>(root: &'a mut Element, path: &[usize]) -> Option<&'a mut Element> {
+    path.iter()
+        .try_fold(root, |el, i| match el.children.get_mut(*i) {
+            Some(Node::Element(e)) => Some(e),
+            _ => None,
+        })
+}
+
+/// Take the node at path out of the document
+fn take(root: &mut Element, path: &[usize]) -> Option<Node> {
+    let (last, parent) = path.split_last()?;
+    let parent = element_at_mut(root, parent)?;
+    (*last < parent.children.len()).then(|| parent.children.remove(*last))
+}
+
+fn apply(doc: &mut Element, operation: &Operation) -> Result<(), Error> {
+    let failed = || Error::PatchFailed(operation.to_string());
+    let insert = |doc: &mut Element, parent: &Address, index: usize, node: Node| {
+        let path = resolve(doc, parent)?;
+        let parent = element_at_mut(doc, &path).ok_or_else(failed)?;
+        if index > parent.children.len() {
+            return Err(failed());
+        }
+        parent.children.insert(index, node);
+        Ok(())
+    };
+
+    match operation {
+        Operation::Insert {
+            parent,
+            index,
+            node,
+        } => insert(doc, parent, *index, node.clone()),
+        Operation::Remove { target } => {
+            let path = resolve(doc, target)?;
+            take(doc, &path).map(|_| ()).ok_or_else(failed)
+        }
+        Operation::Move {
+            target,
+            parent,
+            index,
+        } => {
+            let path = resolve(doc, target)?;
+            let node = take(doc, &path).ok_or_else(failed)?;
+            insert(doc, parent, *index, node)
+        }
+        Operation::SetAttribute {
+            target,
+            name,
+            value,
+        } => {
+            let path = resolve(doc, target)?;
+            element_at_mut(doc, &path)
+                .ok_or_else(failed)?
+                .set(name.as_str(), value.as_str());
+            Ok(())
+        }
+        Operation::RemoveAttribute { target, name } => {
+            let path = resolve(doc, target)?;
+            element_at_mut(doc, &path)
+                .ok_or_else(failed)?
+                .remove(name.as_str());
+            Ok(())
+        }
+        Operation::SetText { target, text } => {
+            let path = resolve(doc, target)?;
+            let (last, parent) = path.split_last().ok_or_else(failed)?;
+            match element_at_mut(doc, parent).and_then(|p| p.children.get_mut(*last)) {
+                Some(Node::Text(t)) | Some(Node::Comment(t)) => {
+                    *t = text.clone();
+                    Ok(())
+                }
+                _ => Err(failed()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{diff, Address, Operation, Patch};
+    use crate::read::parse_string;
+    use crate::Element;
+
+    fn svg(text: &str) -> Element {
+        parse_string(text.to_string()).unwrap()
+    }
+
+    /// Diff two documents and check the patch turns one into the other
+    fn round_trip(before: &str, after: &str) -> Patch {
+        let (before, after) = (svg(before), svg(after));
+        let patch = diff(&before, &after);
+        let mut doc = before.clone();
+        patch.apply(&mut doc).unwrap();
+        assert_eq!(doc.to_string(), after.to_string(), "patch:\n{patch}");
+        patch
+    }
+
+    #[test]
+    fn addresses() {
+        for text in ["/", "/0/3", "#logo", "#logo/1/0"] {
+            assert_eq!(text.parse::<Address>().unwrap().to_string(), text);
+        }
+        assert_eq!(
+            "#a/2".parse::<Address>().unwrap(),
+            Address {
+                id: Some("a".to_string()),
+                path: vec![2]
+            }
+        );
+        assert!("logo".parse::<Address>().is_err());
+        assert!("/x".parse::<Address>().is_err());
+    }
+
+    #[test]
+    fn small_edits() {
+        let patch = round_trip(
+            r#"<svg><rect /><circle r="1" /><circle r="2" /><text>old</text></svg>"#,
+            r#"<svg><rect /><line /><circle r="2" /><text>new</text><g /></svg>"#,
+        );
+        let lines: Vec<String> = patch.operations.iter().map(|o| o.to_string()).collect();
+        assert_eq!(
+            lines,
+            [
+                "remove /1",
+                "insert <line /> into / at 1",
+                "insert <g /> into / at 4",
+                "change /3/0 to \"new\"",
+            ]
+        );
+
+        // a changed element is edited in place rather than replaced
+        let patch = round_trip(
+            r#"<svg><g><rect x="1" y="2" /></g></svg>"#,
+            r#"<svg><g><rect x="3" /></g></svg>"#,
+        );
+        assert_eq!(
+            patch.to_string(),
+            "set x=\"3\" on /0/0\nremove y from /0/0\n"
+        );
+        assert!(diff(&svg("<svg><g /></svg>"), &svg("<svg><g /></svg>")).is_empty());
+    }
+
+    #[test]
+    fn moves_by_id() {
+        let patch = round_trip(
+            r#"<svg><g id="a"><rect id="r" /><circle /></g><g id="b"><line /></g></svg>"#,
+            r#"<svg><g id="b"><line /><rect id="r" width="2" /></g><g id="a"><circle /></g></svg>"#,
+        );
+        assert_eq!(
+            patch.to_string(),
+            "move #b into / at 0\nmove #r into #b at 1\nset width=\"2\" on #r\n"
+        );
+        assert!(matches!(patch.operations[0], Operation::Move { .. }));
+
+        // moving things in and out of new groups
+        round_trip(
+            r#"<svg><rect id="r" /><circle id="c" /><text>hi</text></svg>"#,
+            r#"<svg><g><g><circle id="c" /></g><text>hi</text></g><rect id="r" /></svg>"#,
+        );
+        round_trip(
+            r#"<svg><g id="outer"><g id="inner"><rect /></g></g></svg>"#,
+            r#"<svg><g id="inner"><g id="outer"><rect /></g></g></svg>"#,
+        );
+    }
+
+    #[test]
+    fn patches_other_documents() {
+        let patch = diff(
+            &svg(r#"<svg><g id="title"><text>Draft</text></g><rect id="box" /></svg>"#),
+            &svg(r#"<svg><g id="title"><text>Final</text></g><rect id="box" fill="red" /></svg>"#),
+        );
+        let saved = patch.to_element().to_pretty_string();
+        let patch = Patch::from_element(&svg(&saved)).unwrap();
+
+        let mut other = svg(
+            r#"<svg><circle /><rect id="box" /><g id="title" class="big"><text>Draft</text></g></svg>"#,
+        );
+        patch.apply(&mut other).unwrap();
+        assert_eq!(
+            other.to_string(),
+            svg(r#"<svg><circle /><rect id="box" fill="red" /><g id="title" class="big"><text>Final</text></g></svg>"#)
+                .to_string()
+        );
+
+        // nothing is changed when part of a patch doesn't fit
+        let mut unrelated = svg(r#"<svg><rect id="box" /></svg>"#);
+        let before = unrelated.to_string();
+        assert!(patch.apply(&mut unrelated).is_err());
+        assert_eq!(unrelated.to_string(), before);
+        assert!(Patch::from_element(&svg("<patch><explode /></patch>")).is_err());
+    }
+}
@@ -14,6 +14,35 @@ pub enum Error {
     /// When trying to parse a paper name and it doesn't make sense or it is not implemented yet
     #[error("Unknown paper: {0:?}")]
     UnknownPaper(String),
+    /// When a page spec can't be understood, contains the spec and the reason
+    #[error("Invalid page spec {0:?}: {1}")]
+    InvalidPageSpec(String, String),
+    /// When rounding to a fraction and the requested denominator can't be used, such as zero.
+    #[error("Invalid denominator: {0}")]
+    InvalidDenominator(u32),
+    /// When a length has a unit suffix that isn't recognised
+    #[error("Unknown unit: {0:?}")]
+    UnknownUnit(String),
+    /// When a string could not be parsed as a length, contains the input
+    #[error("Invalid length: {0:?}")]
+    InvalidLength(String),
+    /// When a relative length like a percentage is used where there is nothing for it to be relative to, contains
+    /// the length
+    #[error("Nothing to measure {0} against")]
+    RelativeLength(String),
+    /// When an element is missing an attribute that is needed, contains the attribute name
+    #[error("Missing attribute: {0:?}")]
+    MissingAttribute(String),
+    /// When an attribute has a value that doesn't make sense, contains the attribute name and value
+    #[error("Invalid value for attribute {0:?}: {1:?}")]
+    InvalidAttribute(String, String),
+    /// When tiling and the overlap is negative or doesn't leave any room on the page
+    #[error("Invalid overlap: {0}")]
+    InvalidOverlap(String),
+    /// When a machine's speed or acceleration is negative or not a number, or a speed is zero, contains which one and
+    /// its value
+    #[error("Invalid machine {0}: {1}")]
+    InvalidMachine(String, String),
     #[error("A style tag is badly formed")]
     MalformedStyle,
     #[error("Could not parse an integer: {0:?}")]
@@ -31,11 +60,38 @@ pub enum Error {
     /// Attempted to parse something that wasn't a valid xml document
     #[error("Tried to parse document but it was empty")]
     EmptyDocument,
+    /// When a file is bigger than the parse options allow, contains the limit in bytes
+    #[error("File is larger than {0} bytes")]
+    FileTooLarge(usize),
+    /// When a document has more elements than the parse options allow, contains the limit
+    #[error("Document has more than {0} elements")]
+    TooManyElements(usize),
+    /// When elements are nested deeper than the parse options allow, contains the limit
+    #[error("Elements are nested more than {0} deep")]
+    TooDeep(usize),
+    /// When an element has more attributes than the parse options allow, contains the element name and the limit
+    #[error("Element {0:?} has more than {1} attributes")]
+    TooManyAttributes(String, usize),
+    /// When an attribute value is longer than the parse options allow, contains the attribute name and the limit
+    #[error("Attribute {0:?} is longer than {1} bytes")]
+    AttributeTooLong(String, usize),
+    /// When a text node is longer than the parse options allow, contains the limit in bytes
+    #[error("Text is longer than {0} bytes")]
+    TextTooLong(usize),
     #[error("A utf-8 encoding error: {0:?}")]
     UTF8Error(str::Utf8Error),
     /// A problem trying to parse a hex colour, likely the value is too short
     #[error("Invalid colour '{0:?}'")]
     ColourError(String),
+    /// When a number that is infinite or not a number would be written into a document
+    #[error("Can't write a number that isn't finite: {0}")]
+    NotFinite(f64),
+    /// When a patch can't be read back, contains the reason
+    #[error("Invalid patch: {0}")]
+    InvalidPatch(String),
+    /// When a patch doesn't fit the document it is applied to, contains the operation that couldn't be done
+    #[error("Could not apply patch: {0}")]
+    PatchFailed(String),
 }
 
 impl From<ParseIntError> for Error {
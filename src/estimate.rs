@@ -0,0 +1,355 @@
+//! Estimate how long a plot will take and how far the pen will go
+use std::fmt;
+use std::time::Duration;
+
+use polygonical::point::Point;
+
+use crate::convert;
+use crate::error::Error;
+use crate::export;
+use crate::length::{Context, Length};
+use crate::path;
+use crate::Element;
+
+/// How fast a plotter moves, the numbers the estimate is worked out from
+#[derive(Debug, Clone, PartialEq)]
+pub struct Machine {
+    /// Top speed with the pen down, per second
+    pub draw_speed: Length,
+    /// Top speed with the pen up, per second
+    pub travel_speed: Length,
+    /// How quickly the machine gets up to speed and slows down again, per second per second. Zero leaves speeding up
+    /// and slowing down out.
+    pub acceleration: Length,
+    /// How long it takes to lift the pen and put it down again
+    pub pen_lift: Duration,
+}
+
+impl Default for Machine {
+    /// Roughly an AxiDraw at its default settings
+    fn default() -> Self {
+        Machine {
+            draw_speed: Length::mm(50.0),
+            travel_speed: Length::mm(150.0),
+            acceleration: Length::mm(1000.0),
+            pen_lift: Duration::from_millis(300),
+        }
+    }
+}
+
+/// How much plotting there is in some or all of a document
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Estimate {
+    /// How far the pen is dragged along the paper, in mm
+    pub draw_distance: Length,
+    /// How far the pen moves in the air between lines, in mm
+    pub travel_distance: Length,
+    /// How many times the pen is lifted
+    pub pen_lifts: usize,
+    /// How long it should all take
+    pub time: Duration,
+}
+
+impl fmt::Display for Estimate {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let seconds = self.time.as_secs();
+        write!(
+            formatter,
+            "{:.2} m drawn, {:.2} m travel, {} pen lifts, {}h {:02}m {:02}s",
+            self.draw_distance.value / 1000.0,
+            self.travel_distance.value / 1000.0,
+            self.pen_lifts,
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    }
+}
+
+/// The estimate for a whole document, and split up by layer and stroke colour
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Report {
+    pub total: Estimate,
+    /// Layers by label in the order they are drawn, lines outside of any layer are only in the total
+    pub layers: Vec<(String, Estimate)>,
+    /// Stroke colours in the order they are first drawn
+    pub colours: Vec<(String, Estimate)>,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(formatter, "total: {}", self.total)?;
+        for (name, estimate) in &self.layers {
+            writeln!(formatter, "layer {name}: {estimate}")?;
+        }
+        for (colour, estimate) in &self.colours {
+            writeln!(formatter, "colour {colour}: {estimate}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Turns sharper than this bring the machine to a stop before carrying on
+const CORNER: f64 = 30.0;
+
+/// Work out how long a document will take to plot, drawing every stroked line in document order starting and ending
+/// at the top left corner.
+///
+/// Each move speeds up and slows down at the machine's acceleration, and drawing stops at sharp corners but carries
+/// on round curves. Real plotters plan their moves in more detail so treat the time as a guide, it is usually close
+/// enough to tell a twenty minute plot from a six hour one. Speeds have to be absolute lengths above zero, and the
+/// acceleration can't be negative.
+///
+/// ```
+/// use esvg::estimate::{estimate, Machine};
+/// use esvg::read::parse_string;
+/// use std::time::Duration;
+///
+/// let doc = parse_string(
+///     r#"<svg><line x1="96" y1="0" x2="96" y2="96" stroke="black" /></svg>"#.to_string(),
+/// )
+/// .unwrap();
+/// let report = estimate(&doc, 96, &Machine::default()).unwrap();
+/// assert_eq!(report.total.draw_distance.value, 25.4);
+/// assert!((report.total.travel_distance.value - 25.4 - 35.921).abs() < 0.001);
+/// assert_eq!(report.total.pen_lifts, 1);
+/// assert!(report.total.time > Duration::from_secs(1));
+/// ```
+pub fn estimate(doc: &Element, dpi: i32, machine: &Machine) -> Result<Report, Error> {
+    let ctx = Context::new(dpi);
+    let rate = |name: &str, length: Length, least: f64| {
+        let px = length.resolve(&ctx)?;
+        if !px.is_finite() || px < least {
+            return Err(Error::InvalidMachine(name.to_string(), length.to_string()));
+        }
+        Ok(px)
+    };
+    let profile = Profile {
+        draw: rate("draw speed", machine.draw_speed, f64::MIN_POSITIVE)?,
+        travel: rate("travel speed", machine.travel_speed, f64::MIN_POSITIVE)?,
+        acceleration: rate("acceleration", machine.acceleration, 0.0)?,
+        lift: machine.pen_lift.as_secs_f64(),
+    };
+
+    let mut total = Tally::default();
+    let mut layers: Vec<(String, Tally)> = vec![];
+    let mut colours: Vec<(String, Tally)> = vec![];
+    let mut at = Point::zero();
+    for line in export::lines(doc, 0.1)? {
+        let mut tally = Tally::default();
+        let travel = path::distance(at, line.points[0]);
+        tally.travel += travel;
+        tally.seconds += profile.move_time(travel, profile.travel);
+        tally.lifts += 1;
+        tally.seconds += profile.lift;
+        for run in runs(&line.points) {
+            let length: f64 = run.windows(2).map(|w| path::distance(w[0], w[1])).sum();
+            tally.draw += length;
+            tally.seconds += profile.move_time(length, profile.draw);
+        }
+        at = *line.points.last().unwrap();
+
+        total.add(&tally);
+        if let Some(layer) = line.layer {
+            add_to(&mut layers, layer, &tally);
+        }
+        add_to(&mut colours, line.colour, &tally);
+    }
+    let home = path::distance(at, Point::zero());
+    total.travel += home;
+    total.seconds += profile.move_time(home, profile.travel);
+
+    let estimate = |t: &Tally| t.estimate(dpi);
+    Ok(Report {
+        total: estimate(&total),
+        layers: layers
+            .iter()
+            .map(|(n, t)| (n.clone(), estimate(t)))
+            .collect(),
+        colours: colours
+            .iter()
+            .map(|(n, t)| (n.clone(), estimate(t)))
+            .collect(),
+    })
+}
+
+/// The machine's numbers in pixels and seconds
+struct Profile {
+    draw: f64,
+    travel: f64,
+    acceleration: f64,
+    lift: f64,
+}
+
+impl Profile {
+    /// Time to move a distance from a stop to a stop, speeding up to the top speed if there is room
+    fn move_time(&self, distance: f64, speed: f64) -> f64 {
+        if distance <= 0.0 || speed <= 0.0 {
+            return 0.0;
+        }
+        if self.acceleration <= 0.0 {
+            return distance / speed;
+        }
+        // distance used getting up to speed and back down again
+        let ramps = speed * speed / self.acceleration;
+        if distance >= ramps {
+            distance / speed + speed / self.acceleration
+        } else {
+            2.0 * (distance / self.acceleration).sqrt()
+        }
+    }
+}
+
+/// Running totals in pixels and seconds
+#[derive(Debug, Clone, Default)]
+struct Tally {
+    draw: f64,
+    travel: f64,
+    lifts: usize,
+    seconds: f64,
+}
+
+impl Tally {
+    fn add(&mut self, other: &Tally) {
+        self.draw += other.draw;
+        self.travel += other.travel;
+        self.lifts += other.lifts;
+        self.seconds += other.seconds;
+    }
+
+    fn estimate(&self, dpi: i32) -> Estimate {
+        Estimate {
+            draw_distance: Length::mm(convert::pixels_to_mm(self.draw, dpi)),
+            travel_distance: Length::mm(convert::pixels_to_mm(self.travel, dpi)),
+            pen_lifts: self.lifts,
+            time: Duration::from_secs_f64(self.seconds),
+        }
+    }
+}
+
+fn add_to(groups: &mut Vec<(String, Tally)>, name: String, tally: &Tally) {
+    match groups.iter_mut().find(|(n, _)| *n == name) {
+        Some((_, t)) => t.add(tally),
+        None => groups.push((name, tally.clone())),
+    }
+}
+
+/// Split a line at its sharp corners, where the machine has to stop
+fn runs(points: &[Point]) -> Vec<&[Point]> {
+    let mut result = vec![];
+    let mut start = 0;
+    for i in 1..points.len().saturating_sub(1) {
+        let (a, b, c) = (points[i - 1], points[i], points[i + 1]);
+        let turn = ((b.x - a.x) * (c.y - b.y) - (b.y - a.y) * (c.x - b.x))
+            .atan2((b.x - a.x) * (c.x - b.x) + (b.y - a.y) * (c.y - b.y));
+        if turn.abs() > CORNER.to_radians() {
+            result.push(&points[start..=i]);
+            start = i;
+        }
+    }
+    result.push(&points[start..]);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{estimate, runs, Machine, Profile};
+    use crate::length::Length;
+    use crate::read::parse_string;
+    use polygonical::point::Point;
+    use std::time::Duration;
+
+    #[test]
+    fn move_times() {
+        let profile = Profile {
+            draw: 10.0,
+            travel: 20.0,
+            acceleration: 100.0,
+            lift: 0.5,
+        };
+        // one second at full speed plus a tenth each speeding up and slowing down, which covers half a unit
+        assert!((profile.move_time(11.0, 10.0) - 1.2).abs() < 1e-9);
+        // never gets to full speed
+        assert!((profile.move_time(0.25, 10.0) - 0.1).abs() < 1e-9);
+        assert_eq!(profile.move_time(0.0, 10.0), 0.0);
+    }
+
+    #[test]
+    fn corners() {
+        let square = [
+            Point::new(0.0, 0.0),
+            Point::new(10.0, 0.0),
+            Point::new(10.0, 10.0),
+            Point::new(0.0, 10.0),
+            Point::new(0.0, 0.0),
+        ];
+        assert_eq!(runs(&square).len(), 4);
+
+        let gentle = [
+            Point::new(0.0, 0.0),
+            Point::new(10.0, 1.0),
+            Point::new(20.0, 3.0),
+        ];
+        assert_eq!(runs(&gentle).len(), 1);
+    }
+
+    #[test]
+    fn layers_and_colours() {
+        let doc = parse_string(
+            r##"<svg xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape">
+  <g inkscape:groupmode="layer" inkscape:label="outline">
+    <rect width="96" height="96" stroke="black" />
+  </g>
+  <g inkscape:groupmode="layer" inkscape:label="detail" stroke="red">
+    <line x1="0" y1="0" x2="96" y2="0" />
+    <line x1="0" y1="48" x2="96" y2="48" stroke="#000000" />
+  </g>
+  <rect width="10" height="10" fill="black" />
+</svg>"##
+                .to_string(),
+        )
+        .unwrap();
+        let machine = Machine {
+            draw_speed: Length::inches(2.0),
+            pen_lift: Duration::from_secs(1),
+            acceleration: Length::zero(),
+            ..Default::default()
+        };
+        let report = estimate(&doc, 96, &machine).unwrap();
+        assert_eq!(report.total.pen_lifts, 3);
+        assert_eq!(report.total.draw_distance.value, 25.4 * 6.0);
+
+        let names: Vec<&str> = report.layers.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(names, ["outline", "detail"]);
+        let colours: Vec<&str> = report.colours.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(colours, ["#000000", "#ff0000"]);
+        assert_eq!(report.colours[0].1.pen_lifts, 2);
+
+        // the outline is 4 inches at 2 inches a second, plus a pen lift
+        assert_eq!(report.layers[0].1.time, Duration::from_secs(3));
+        assert!(report.to_string().contains("layer detail: "));
+
+        // speeds that can't be measured or make no sense are errors, not nonsense times
+        for bad in [
+            Machine {
+                draw_speed: Length::percent(50.0),
+                ..Default::default()
+            },
+            Machine {
+                travel_speed: Length::zero(),
+                ..Default::default()
+            },
+            Machine {
+                draw_speed: Length::mm(f64::NAN),
+                ..Default::default()
+            },
+            Machine {
+                acceleration: Length::mm(-10.0),
+                ..Default::default()
+            },
+        ] {
+            assert!(estimate(&doc, 96, &bad).is_err());
+        }
+    }
+}
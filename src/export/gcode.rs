@@ -0,0 +1,506 @@
+//! G-code for pen plotters, laser cutters, and drag knives running GRBL or similar firmware
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::Write;
+
+use polygonical::point::Point;
+
+use crate::error::Error;
+use crate::export::{Origin, Placement};
+use crate::flatten;
+use crate::layer;
+use crate::page::Page;
+use crate::path::{self, Arc, Segment};
+use crate::pens::{normalise_colour, stroke_colour};
+use crate::transform::Transform;
+use crate::Element;
+
+/// How to drive the machine
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    /// Where on the page the machine's 0,0 is
+    pub origin: Origin,
+    /// Most machines count y upwards from the origin while svg counts down, turn this off if yours doesn't
+    pub y_up: bool,
+    /// Flip the drawing left to right
+    pub mirror: bool,
+    /// Lines of G-code to lift the pen or turn the laser off
+    pub pen_up: String,
+    /// Lines of G-code to put the pen down or turn the laser on
+    pub pen_down: String,
+    /// Drawing speed in mm per minute
+    pub feed_rate: f64,
+    /// Drawing speeds for particular layers or stroke colours, layer labels are checked first
+    pub feed_rates: Vec<(String, f64)>,
+    /// Draw circular arcs with `G2` and `G3` instead of lots of short lines. Elliptical arcs are always split into
+    /// lines.
+    pub arcs: bool,
+    /// How far in mm a drag knife's blade trails behind its centre, zero for tools that cut where they are
+    pub knife_offset: f64,
+    /// Curves are drawn as lines no further than this many pixels from the real curve
+    pub curve_tolerance: f64,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            origin: Origin::BottomLeft,
+            y_up: true,
+            mirror: false,
+            pen_up: "G0 Z5".to_string(),
+            pen_down: "G0 Z0".to_string(),
+            feed_rate: 1000.0,
+            feed_rates: vec![],
+            arcs: false,
+            knife_offset: 0.0,
+            curve_tolerance: 0.1,
+        }
+    }
+}
+
+impl Options {
+    /// Settings for a plotter that lifts its pen with a servo, the values are the spindle speeds that put the
+    /// servo up and down
+    pub fn servo(up: u32, down: u32) -> Options {
+        Options {
+            pen_up: format!("M3 S{up}"),
+            pen_down: format!("M3 S{down}"),
+            ..Default::default()
+        }
+    }
+
+    /// Settings for a laser, which is turned on at a power level to cut and off to move
+    pub fn laser(power: u32) -> Options {
+        Options {
+            pen_up: "M5".to_string(),
+            pen_down: format!("M3 S{power}"),
+            ..Default::default()
+        }
+    }
+}
+
+/// One move with the pen down, in machine millimetres
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Cut {
+    Line(Point),
+    Arc {
+        to: Point,
+        centre: Point,
+        clockwise: bool,
+    },
+}
+
+/// Everything drawn between putting the pen down and lifting it again
+#[derive(Debug, Clone, PartialEq)]
+struct Stroke {
+    start: Point,
+    cuts: Vec<Cut>,
+    feed: f64,
+}
+
+/// The G-code to draw every stroked line in a document, in millimetres.
+///
+/// Transforms and curves are flattened to straight moves, apart from circular arcs when [`Options::arcs`] is on.
+/// Shapes without a stroke are left out. The machine starts and finishes at its origin with the pen up.
+///
+/// ```
+/// use esvg::export::gcode::{to_string, Options};
+/// use esvg::page::Page;
+/// use esvg::read::parse_string;
+///
+/// let doc = parse_string(
+///     r#"<svg><line x1="0" y1="0" x2="96" y2="0" stroke="black" /></svg>"#.to_string(),
+/// )
+/// .unwrap();
+/// let gcode = to_string(&doc, &Page::A6(96), &Options::servo(30, 90)).unwrap();
+/// assert_eq!(
+///     gcode,
+///     "G21\nG90\nM3 S30\nG0 X0.000 Y148.000\nM3 S90\nG1 X25.400 Y148.000 F1000\nM3 S30\nG0 X0 Y0\nM2\n"
+/// );
+/// ```
+pub fn to_string(doc: &Element, page: &Page, options: &Options) -> Result<String, Error> {
+    let placement = Placement::new(page, options.origin, options.y_up, options.mirror);
+    let feed_rates: Vec<(String, f64)> = options
+        .feed_rates
+        .iter()
+        .map(|(name, rate)| (name.clone(), *rate))
+        .chain(
+            options
+                .feed_rates
+                .iter()
+                .filter_map(|(name, rate)| Some((normalise_colour(name)?, *rate))),
+        )
+        .collect();
+
+    let mut strokes = vec![];
+    flatten::walk_segments(doc, &mut |chain, transform, segments| {
+        let mut ancestry = vec![doc];
+        ancestry.extend_from_slice(chain);
+        let Some(colour) = stroke_colour(&ancestry) else {
+            return;
+        };
+        let label = chain
+            .iter()
+            .rev()
+            .find(|el| layer::is_layer(el))
+            .map(|el| layer::label(el));
+        let feed = feed_rates
+            .iter()
+            .find(|(name, _)| Some(name) == label.as_ref())
+            .or_else(|| feed_rates.iter().find(|(name, _)| *name == colour))
+            .map(|(_, rate)| *rate)
+            .unwrap_or(options.feed_rate);
+
+        let scale = transform.scale_factor();
+        let tolerance = if scale > 0.0 {
+            options.curve_tolerance / scale
+        } else {
+            options.curve_tolerance
+        };
+        if options.arcs && options.knife_offset <= 0.0 {
+            strokes.extend(with_arcs(&segments, transform, &placement, tolerance, feed));
+        } else {
+            // a line needs somewhere to go, lone points would put the pen down without cutting
+            for line in path::flatten(&segments, tolerance)
+                .into_iter()
+                .filter(|l| l.len() > 1)
+            {
+                let points: Vec<Point> = line
+                    .into_iter()
+                    .map(|p| placement.mm(transform.apply(p)))
+                    .collect();
+                let stroke = if options.knife_offset > 0.0 {
+                    knife(&points, options.knife_offset, feed)
+                } else {
+                    Stroke {
+                        start: points[0],
+                        cuts: points[1..].iter().map(|p| Cut::Line(*p)).collect(),
+                        feed,
+                    }
+                };
+                if !stroke.cuts.is_empty() {
+                    strokes.push(stroke);
+                }
+            }
+        }
+    })?;
+
+    let mut out = String::from("G21\nG90\n");
+    writeln!(out, "{}", options.pen_up).unwrap();
+    for stroke in strokes {
+        writeln!(out, "G0 X{} Y{}", mm(stroke.start.x), mm(stroke.start.y)).unwrap();
+        writeln!(out, "{}", options.pen_down).unwrap();
+        for (i, cut) in stroke.cuts.iter().enumerate() {
+            let feed = if i == 0 {
+                format!(" F{}", stroke.feed)
+            } else {
+                String::new()
+            };
+            match cut {
+                Cut::Line(p) => writeln!(out, "G1 X{} Y{}{feed}", mm(p.x), mm(p.y)),
+                Cut::Arc {
+                    to,
+                    centre,
+                    clockwise,
+                } => {
+                    let start = match i {
+                        0 => stroke.start,
+                        _ => end(&stroke.cuts[i - 1]),
+                    };
+                    writeln!(
+                        out,
+                        "{} X{} Y{} I{} J{}{feed}",
+                        if *clockwise { "G2" } else { "G3" },
+                        mm(to.x),
+                        mm(to.y),
+                        mm(centre.x - start.x),
+                        mm(centre.y - start.y)
+                    )
+                }
+            }
+            .unwrap();
+        }
+        writeln!(out, "{}", options.pen_up).unwrap();
+    }
+    out.push_str("G0 X0 Y0\nM2\n");
+    Ok(out)
+}
+
+/// Write the G-code for a document to a file at the given path, see [`to_string`]
+pub fn save(path: &str, doc: &Element, page: &Page, options: &Options) -> Result<(), Error> {
+    let mut f = File::create(path)?;
+    write!(f, "{}", to_string(doc, page, options)?)?;
+    Ok(())
+}
+
+/// A distance in mm to three decimal places, never negative zero
+fn mm(value: f64) -> String {
+    format!("{:.3}", (value * 1000.0).round() / 1000.0 + 0.0)
+}
+
+fn end(cut: &Cut) -> Point {
+    match cut {
+        Cut::Line(p) => *p,
+        Cut::Arc { to, .. } => *to,
+    }
+}
+
+/// Strokes for a shape's segments keeping circular arcs whole
+fn with_arcs(
+    segments: &[Segment],
+    transform: &Transform,
+    placement: &Placement,
+    tolerance: f64,
+    feed: f64,
+) -> Vec<Stroke> {
+    let place = |p: Point| placement.mm(transform.apply(p));
+    let determinant = transform.a * transform.d - transform.b * transform.c;
+    // circles stay circles if the transform only moves, turns, flips, and scales evenly
+    let similar = ((transform.a - transform.d).abs() < 1e-9
+        && (transform.b + transform.c).abs() < 1e-9)
+        || ((transform.a + transform.d).abs() < 1e-9 && (transform.b - transform.c).abs() < 1e-9);
+
+    let mut result = vec![];
+    let mut stroke: Option<Stroke> = None;
+    let mut current = Point::zero();
+    let mut start = Point::zero();
+    let finish = |stroke: &mut Option<Stroke>, result: &mut Vec<Stroke>| {
+        if let Some(s) = stroke.take().filter(|s| !s.cuts.is_empty()) {
+            result.push(s);
+        }
+    };
+
+    for segment in segments {
+        if let Segment::MoveTo(p) = segment {
+            finish(&mut stroke, &mut result);
+            current = *p;
+            start = *p;
+            continue;
+        }
+        let stroke = stroke.get_or_insert_with(|| Stroke {
+            start: place(current),
+            cuts: vec![],
+            feed,
+        });
+
+        let arc = match *segment {
+            Segment::ArcTo {
+                rx,
+                ry,
+                rotation,
+                large,
+                sweep,
+                to,
+            } if similar => Arc::from_endpoints(current, to, rx, ry, rotation, large, sweep)
+                .filter(|arc| (arc.rx - arc.ry).abs() <= 1e-9 * arc.rx.max(arc.ry))
+                .map(|arc| (arc, to)),
+            _ => None,
+        };
+        match (segment, arc) {
+            (_, Some((arc, to))) => {
+                // positive sweeps go anticlockwise in the page's own y down coordinates
+                let anticlockwise =
+                    ((arc.sweep > 0.0) != (determinant < 0.0)) != placement.reverses();
+                stroke.cuts.push(Cut::Arc {
+                    to: place(to),
+                    centre: place(arc.center),
+                    clockwise: !anticlockwise,
+                });
+                current = to;
+            }
+            (Segment::LineTo(p), None) => {
+                stroke.cuts.push(Cut::Line(place(*p)));
+                current = *p;
+            }
+            (Segment::Close, None) => {
+                stroke.cuts.push(Cut::Line(place(start)));
+                current = start;
+            }
+            (segment, None) => {
+                let lines = path::flatten(&[Segment::MoveTo(current), *segment], tolerance);
+                for p in lines.into_iter().flatten().skip(1) {
+                    stroke.cuts.push(Cut::Line(place(p)));
+                }
+                current = match *segment {
+                    Segment::CubicTo(_, _, p) | Segment::QuadTo(_, p) => p,
+                    Segment::ArcTo { to, .. } => to,
+                    _ => current,
+                };
+            }
+        }
+    }
+    finish(&mut stroke, &mut result);
+    result
+}
+
+/// A drag knife's blade trails behind the centre of the tool, so the centre has to run ahead of the line by the
+/// offset and swing round each corner to turn the blade before the next cut.
+fn knife(points: &[Point], offset: f64, feed: f64) -> Stroke {
+    let mut points: Vec<Point> = points.to_vec();
+    points.dedup_by(|a, b| path::distance(*a, *b) < 1e-9);
+    if points.len() < 2 {
+        return Stroke {
+            start: points[0],
+            cuts: vec![],
+            feed,
+        };
+    }
+
+    let direction = |i: usize| {
+        let (a, b) = (points[i], points[i + 1]);
+        let length = path::distance(a, b);
+        Point::new((b.x - a.x) / length, (b.y - a.y) / length)
+    };
+    let ahead = |p: Point, d: Point| Point::new(p.x + d.x * offset, p.y + d.y * offset);
+
+    let mut cuts = vec![];
+    let swivel = |cuts: &mut Vec<Cut>, corner: Point, from: Point, to: Point| {
+        let cross = from.x * to.y - from.y * to.x;
+        let dot = from.x * to.x + from.y * to.y;
+        // small turns don't need the blade swung round
+        if cross.atan2(dot).abs() > 5f64.to_radians() {
+            cuts.push(Cut::Arc {
+                to: ahead(corner, to),
+                centre: corner,
+                clockwise: cross < 0.0,
+            });
+        }
+    };
+
+    let start = ahead(points[0], direction(0));
+    for i in 0..points.len() - 1 {
+        let d = direction(i);
+        if i > 0 {
+            swivel(&mut cuts, points[i], direction(i - 1), d);
+        }
+        cuts.push(Cut::Line(ahead(points[i + 1], d)));
+    }
+
+    // turn the blade back to the start of a closed shape so the first corner is cut cleanly
+    let last = points.len() - 2;
+    if path::distance(points[0], points[last + 1]) < 1e-9 {
+        swivel(&mut cuts, points[0], direction(last), direction(0));
+    }
+
+    Stroke { start, cuts, feed }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{to_string, Options};
+    use crate::export::Origin;
+    use crate::page::Page;
+    use crate::read::parse_string;
+
+    fn top_left() -> Options {
+        Options {
+            origin: Origin::TopLeft,
+            y_up: false,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn feed_rates() {
+        let doc = parse_string(
+            r##"<svg xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape">
+  <g inkscape:groupmode="layer" inkscape:label="slow">
+    <line x1="0" y1="0" x2="96" y2="0" stroke="blue" />
+  </g>
+  <line x1="0" y1="96" x2="96" y2="96" stroke="#00f" />
+  <line x1="0" y1="192" x2="96" y2="192" stroke="black" />
+</svg>"##
+                .to_string(),
+        )
+        .unwrap();
+        let options = Options {
+            feed_rates: vec![("slow".to_string(), 100.0), ("blue".to_string(), 500.0)],
+            ..top_left()
+        };
+        let gcode = to_string(&doc, &Page::A6(96), &options).unwrap();
+        assert!(gcode.contains("G1 X25.400 Y0.000 F100\n"));
+        assert!(gcode.contains("G1 X25.400 Y25.400 F500\n"));
+        assert!(gcode.contains("G1 X25.400 Y50.800 F1000\n"));
+    }
+
+    #[test]
+    fn arcs() {
+        let doc = parse_string(
+            r#"<svg><circle cx="96" cy="96" r="48" stroke="black" transform="rotate(30, 96, 96)" /></svg>"#
+                .to_string(),
+        )
+        .unwrap();
+        let options = Options {
+            arcs: true,
+            ..top_left()
+        };
+        let gcode = to_string(&doc, &Page::A6(96), &options).unwrap();
+        // two halves, clockwise on the page which is anticlockwise with y counting down
+        assert_eq!(gcode.matches("G3 ").count(), 2);
+        assert_eq!(gcode.matches("G1 ").count(), 1);
+
+        // the same circle the right way up for the machine goes clockwise
+        let gcode = to_string(
+            &doc,
+            &Page::A6(96),
+            &Options {
+                arcs: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(gcode.matches("G2 ").count(), 2);
+
+        // squashed circles are split into lines
+        let doc = parse_string(
+            r#"<svg><circle cx="96" cy="96" r="48" stroke="black" transform="scale(1, 2)" /></svg>"#.to_string(),
+        )
+        .unwrap();
+        let gcode = to_string(&doc, &Page::A6(96), &options).unwrap();
+        assert!(!gcode.contains("G3 "));
+        assert!(gcode.matches("G1 ").count() > 10);
+    }
+
+    #[test]
+    fn drag_knife() {
+        let doc = parse_string(
+            r#"<svg><rect width="96" height="96" stroke="black" /></svg>"#.to_string(),
+        )
+        .unwrap();
+        let options = Options {
+            knife_offset: 0.5,
+            ..top_left()
+        };
+        let gcode = to_string(&doc, &Page::A6(96), &options).unwrap();
+        assert!(gcode.contains("G0 X0.500 Y0.000\n"));
+        assert!(gcode.contains("G1 X25.900 Y0.000 F1000\n"));
+        // swing round the corner about the corner itself
+        assert!(gcode.contains("G3 X25.400 Y0.500 I-0.500 J0.000\n"));
+        // three corners and back round to the start
+        assert_eq!(gcode.matches("G3 ").count(), 4);
+    }
+
+    #[test]
+    fn lone_points() {
+        let doc = parse_string(
+            r#"<svg><path d="M10 10" stroke="black" /><path d="M20 20 M30 30" stroke="black" /></svg>"#
+                .to_string(),
+        )
+        .unwrap();
+        for options in [
+            top_left(),
+            Options {
+                arcs: true,
+                ..top_left()
+            },
+            Options {
+                knife_offset: 0.5,
+                ..top_left()
+            },
+        ] {
+            let gcode = to_string(&doc, &Page::A6(96), &options).unwrap();
+            assert_eq!(gcode, "G21\nG90\nG0 Z5\nG0 X0 Y0\nM2\n");
+        }
+    }
+}
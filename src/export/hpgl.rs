@@ -0,0 +1,201 @@
+//! HPGL, the language of vintage pen plotters such as the HP 7475A
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::Write;
+
+use crate::error::Error;
+use crate::export::{lines, Origin, Placement};
+use crate::page::Page;
+use crate::pens::normalise_colour;
+use crate::Element;
+
+/// HPGL plotter units in a millimetre
+pub const UNITS_PER_MM: f64 = 40.0;
+
+/// How to lay the drawing out for the plotter
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    /// Where on the page the plotter's 0,0 is, normally the bottom left corner
+    pub origin: Origin,
+    /// Plotters count y upwards from the origin while svg counts down, turn this off if yours doesn't
+    pub y_up: bool,
+    /// Flip the drawing left to right, for drawing on the back of transparent film
+    pub mirror: bool,
+    /// The colour of the pen in each slot of the carousel, starting at pen 1. Colours not listed are given the
+    /// following pen numbers in the order they are found. A pen with no colour, such as `none`, is an error.
+    pub pens: Vec<String>,
+    /// Pen speed in cm/s, or None to leave the plotter's default
+    pub velocity: Option<f64>,
+    /// Curves are drawn as lines no further than this many pixels from the real curve
+    pub curve_tolerance: f64,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            origin: Origin::BottomLeft,
+            y_up: true,
+            mirror: false,
+            pens: vec![],
+            velocity: None,
+            curve_tolerance: 0.1,
+        }
+    }
+}
+
+/// The HPGL to plot every stroked line in a document, one pen at a time.
+///
+/// Transforms and curves are flattened into straight lines, and shapes without a stroke are left out as a pen
+/// plotter can't fill them. Lines are drawn in document order for each pen, with the pens in number order.
+///
+/// ```
+/// use esvg::export::hpgl::{to_string, Options};
+/// use esvg::page::Page;
+/// use esvg::read::parse_string;
+///
+/// let doc = parse_string(
+///     r#"<svg><line x1="0" y1="0" x2="96" y2="0" stroke="black" /></svg>"#.to_string(),
+/// )
+/// .unwrap();
+/// let page = Page::A6(96);
+/// let options = Options {
+///     velocity: Some(10.0),
+///     ..Default::default()
+/// };
+/// let hpgl = to_string(&doc, &page, &options).unwrap();
+/// assert_eq!(hpgl, "IN;\nVS10;\nSP1;\nPU0,5920;\nPD1016,5920;\nPU;\nSP0;\n");
+/// ```
+pub fn to_string(doc: &Element, page: &Page, options: &Options) -> Result<String, Error> {
+    let placement = Placement::new(page, options.origin, options.y_up, options.mirror);
+
+    let mut pens: Vec<String> = options
+        .pens
+        .iter()
+        .map(|p| normalise_colour(p).ok_or_else(|| Error::ColourError(p.clone())))
+        .collect::<Result<_, _>>()?;
+    let mut drawing: Vec<(usize, Vec<(i64, i64)>)> = vec![];
+    for line in lines(doc, options.curve_tolerance)? {
+        let pen = match pens.iter().position(|p| *p == line.colour) {
+            Some(i) => i + 1,
+            None => {
+                pens.push(line.colour.clone());
+                pens.len()
+            }
+        };
+
+        let mut points: Vec<(i64, i64)> = vec![];
+        for p in line.points {
+            let p = placement.mm(p);
+            let p = (
+                (p.x * UNITS_PER_MM).round() as i64,
+                (p.y * UNITS_PER_MM).round() as i64,
+            );
+            if points.last() != Some(&p) {
+                points.push(p);
+            }
+        }
+        drawing.push((pen, points));
+    }
+    // stable so each pen keeps the drawing order
+    drawing.sort_by_key(|(pen, _)| *pen);
+
+    let mut out = String::from("IN;\n");
+    if let Some(v) = options.velocity {
+        writeln!(out, "VS{v};").unwrap();
+    }
+    let mut current = 0;
+    for (pen, points) in drawing {
+        if pen != current {
+            writeln!(out, "SP{pen};").unwrap();
+            current = pen;
+        }
+        let (x, y) = points[0];
+        writeln!(out, "PU{x},{y};").unwrap();
+        // a line shorter than a plotter unit is still drawn as a dot
+        let rest = if points.len() > 1 {
+            &points[1..]
+        } else {
+            &points[..]
+        };
+        let coordinates: Vec<String> = rest.iter().map(|(x, y)| format!("{x},{y}")).collect();
+        writeln!(out, "PD{};", coordinates.join(",")).unwrap();
+    }
+    out.push_str("PU;\nSP0;\n");
+    Ok(out)
+}
+
+/// Write the HPGL for a document to a file at the given path, see [`to_string`]
+pub fn save(path: &str, doc: &Element, page: &Page, options: &Options) -> Result<(), Error> {
+    let mut f = File::create(path)?;
+    write!(f, "{}", to_string(doc, page, options)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{to_string, Options};
+    use crate::export::Origin;
+    use crate::page::Page;
+    use crate::read::parse_string;
+
+    const DRAWING: &str = r##"<svg viewBox="0 0 396.85 559.37">
+  <rect x="48" y="48" width="96" height="48" stroke="#000" fill="none" />
+  <g stroke="red" transform="translate(96, 192)">
+    <circle cx="0" cy="0" r="24" />
+    <line x1="-48" y1="0" x2="48" y2="0" stroke="blue" />
+  </g>
+  <polyline points="48,300 96,340 144,300" stroke="black" />
+  <rect x="200" y="200" width="50" height="50" fill="black" />
+</svg>"##;
+
+    #[test]
+    fn golden() {
+        let doc = parse_string(DRAWING.to_string()).unwrap();
+        let options = Options {
+            pens: vec!["blue".to_string()],
+            velocity: Some(20.0),
+            curve_tolerance: 1.0,
+            ..Default::default()
+        };
+        let hpgl = to_string(&doc, &Page::A6(96), &options).unwrap();
+        assert_eq!(hpgl, include_str!("testdata/drawing.hpgl"));
+    }
+
+    #[test]
+    fn placement() {
+        let doc = parse_string(
+            r#"<svg><line x1="0" y1="0" x2="96" y2="96" stroke="black" /></svg>"#.to_string(),
+        )
+        .unwrap();
+        let page = Page::A6(96);
+
+        let top_left = Options {
+            origin: Origin::TopLeft,
+            y_up: false,
+            ..Default::default()
+        };
+        let hpgl = to_string(&doc, &page, &top_left).unwrap();
+        assert!(hpgl.contains("PU0,0;\nPD1016,1016;"));
+
+        let centred = Options {
+            origin: Origin::Centre,
+            mirror: true,
+            ..Default::default()
+        };
+        let hpgl = to_string(&doc, &page, &centred).unwrap();
+        assert!(hpgl.contains("PU2100,2960;\nPD1084,1944;"));
+    }
+
+    #[test]
+    fn pens_without_a_colour() {
+        let doc = parse_string(DRAWING.to_string()).unwrap();
+        for colour in ["none", "", "transparent"] {
+            let options = Options {
+                pens: vec!["blue".to_string(), colour.to_string()],
+                ..Default::default()
+            };
+            assert!(to_string(&doc, &Page::A6(96), &options).is_err());
+        }
+    }
+}
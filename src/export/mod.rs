@@ -0,0 +1,104 @@
+//! Write documents in the languages plotters and cutters speak
+use polygonical::point::Point;
+
+use crate::error::Error;
+use crate::flatten;
+use crate::layer;
+use crate::page::Page;
+use crate::pens;
+use crate::Element;
+
+pub mod gcode;
+pub mod hpgl;
+
+/// The corner or middle of the page the machine counts from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Origin {
+    TopLeft,
+    BottomLeft,
+    Centre,
+}
+
+impl Origin {
+    /// Where the origin is on the page in pixels
+    fn point(&self, page: &Page) -> Point {
+        match self {
+            Origin::TopLeft => Point::zero(),
+            Origin::BottomLeft => Point::new(0.0, page.height),
+            Origin::Centre => Point::new(page.width / 2.0, page.height / 2.0),
+        }
+    }
+}
+
+/// Turns page pixels into machine positions in millimetres
+#[derive(Debug, Clone)]
+pub(crate) struct Placement {
+    origin: Point,
+    y_up: bool,
+    mirror: bool,
+    dpi: i32,
+}
+
+impl Placement {
+    pub(crate) fn new(page: &Page, origin: Origin, y_up: bool, mirror: bool) -> Self {
+        Placement {
+            origin: origin.point(page),
+            y_up,
+            mirror,
+            dpi: page.dpi,
+        }
+    }
+
+    /// The position of a point on the page in millimetres from the machine's origin
+    pub(crate) fn mm(&self, p: Point) -> Point {
+        let scale = 25.4 / self.dpi as f64;
+        let x = (p.x - self.origin.x) * scale;
+        let y = (p.y - self.origin.y) * scale;
+        Point::new(
+            if self.mirror { -x } else { x },
+            if self.y_up { -y } else { y },
+        )
+    }
+
+    /// true if the machine's axes are a mirror image of the page's, which counts y downwards, so angles turn the
+    /// other way
+    pub(crate) fn reverses(&self) -> bool {
+        self.y_up != self.mirror
+    }
+}
+
+/// A line to draw in page pixels, the colour of its stroke, and the label of the layer it is in
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Line {
+    pub(crate) points: Vec<Point>,
+    pub(crate) colour: String,
+    pub(crate) layer: Option<String>,
+}
+
+/// Every stroked line in a document in drawing order, with curves turned into lines within tolerance pixels.
+/// Shapes without a stroke aren't drawn.
+pub(crate) fn lines(doc: &Element, tolerance: f64) -> Result<Vec<Line>, Error> {
+    let mut result = vec![];
+    flatten::walk(doc, tolerance, &mut |chain, lines| {
+        let mut ancestry = vec![doc];
+        ancestry.extend_from_slice(chain);
+        if let Some(colour) = pens::stroke_colour(&ancestry) {
+            let layer = chain
+                .iter()
+                .rev()
+                .find(|el| layer::is_layer(el))
+                .map(|el| layer::label(el));
+            result.extend(
+                lines
+                    .into_iter()
+                    .filter(|l| l.len() > 1)
+                    .map(|points| Line {
+                        points,
+                        colour: colour.clone(),
+                        layer: layer.clone(),
+                    }),
+            );
+        }
+    })?;
+    Ok(result)
+}
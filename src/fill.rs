@@ -0,0 +1,755 @@
+//! Fill shapes with lines, as plotters can only draw strokes
+use std::collections::HashMap;
+
+use polygonical::point::Point;
+use polygonical::polygon::Polygon;
+
+use crate::error::Error;
+use crate::flatten;
+use crate::layer;
+use crate::length::Length;
+use crate::page::Page;
+use crate::path;
+use crate::transform::Transform;
+use crate::{Element, Node};
+
+/// How to decide what is inside a shape whose outlines cross or sit inside each other
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FillRule {
+    /// Inside if the outlines wind round the point in one direction more than the other, the svg default
+    NonZero,
+    /// Inside if a line out from the point crosses an odd number of outlines, so every other ring is a hole
+    EvenOdd,
+}
+
+/// An area to fill, made from closed rings. Holes are rings inside others, which ones count as holes depends on
+/// the fill rule.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Region {
+    pub rings: Vec<Vec<Point>>,
+    pub rule: FillRule,
+}
+
+/// The ways a region can be filled
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pattern {
+    /// Parallel lines at an angle in degrees
+    Hatch { angle: f64 },
+    /// Two sets of parallel lines at right angles to each other
+    CrossHatch { angle: f64 },
+    /// Parallel lines joined at alternate ends so the pen stays down
+    ZigZag { angle: f64 },
+    /// Outlines shrinking in towards the middle of the shape, traced rather than offset exactly, see
+    /// [`approximate_concentric`]
+    Concentric,
+}
+
+impl Region {
+    pub fn new(rings: Vec<Vec<Point>>, rule: FillRule) -> Self {
+        Region { rings, rule }
+    }
+
+    /// The area inside path data, every sub path is closed whether it says so or not.
+    /// Curves are turned into lines within tolerance.
+    pub fn from_data(data: &path::Data, rule: FillRule, tolerance: f64) -> Result<Region, Error> {
+        Ok(Region::new(
+            path::flatten(&data.segments()?, tolerance),
+            rule,
+        ))
+    }
+
+    /// true if the point is inside the region
+    pub fn contains(&self, p: Point) -> bool {
+        self.spans(p.y).iter().any(|(a, b)| *a <= p.x && p.x <= *b)
+    }
+
+    /// The parts of a horizontal line through y that are inside the region, left to right
+    fn spans(&self, y: f64) -> Vec<(f64, f64)> {
+        let mut crossings = vec![];
+        for ring in &self.rings {
+            for (i, p) in ring.iter().enumerate() {
+                let q = ring[(i + 1) % ring.len()];
+                if (p.y <= y && y < q.y) || (q.y <= y && y < p.y) {
+                    let x = p.x + (y - p.y) / (q.y - p.y) * (q.x - p.x);
+                    crossings.push((x, if p.y < q.y { 1 } else { -1 }));
+                }
+            }
+        }
+        crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut result = vec![];
+        let mut winding = 0;
+        let mut start = 0.0;
+        for (i, (x, direction)) in crossings.into_iter().enumerate() {
+            let inside_before = match self.rule {
+                FillRule::NonZero => winding != 0,
+                FillRule::EvenOdd => i % 2 == 1,
+            };
+            winding += direction;
+            let inside_after = match self.rule {
+                FillRule::NonZero => winding != 0,
+                FillRule::EvenOdd => i % 2 == 0,
+            };
+            if !inside_before && inside_after {
+                start = x;
+            } else if inside_before && !inside_after && x > start {
+                result.push((start, x));
+            }
+        }
+        result
+    }
+
+    /// The region turned around the origin by an angle in radians
+    fn rotated(&self, angle: f64) -> Region {
+        Region::new(
+            self.rings
+                .iter()
+                .map(|r| r.iter().map(|p| p.rotate(angle)).collect())
+                .collect(),
+            self.rule,
+        )
+    }
+
+    /// The top left and bottom right corners of a box around the region, if it has any points
+    pub fn bounds(&self) -> Option<(Point, Point)> {
+        let points = self.rings.iter().flatten();
+        let mut result: Option<(Point, Point)> = None;
+        for p in points {
+            result = Some(match result {
+                Some((min, max)) => (min.min(p), max.max(p)),
+                None => (*p, *p),
+            });
+        }
+        result
+    }
+
+    /// Distance from a point to the nearest outline
+    fn edge_distance(&self, p: Point) -> f64 {
+        let mut best = f64::MAX;
+        for ring in &self.rings {
+            for (i, a) in ring.iter().enumerate() {
+                let b = ring[(i + 1) % ring.len()];
+                let dx = b.x - a.x;
+                let dy = b.y - a.y;
+                let length_squared = dx * dx + dy * dy;
+                let t = if length_squared > 0.0 {
+                    (((p.x - a.x) * dx + (p.y - a.y) * dy) / length_squared).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                best = best.min((a.x + dx * t - p.x).hypot(a.y + dy * t - p.y));
+            }
+        }
+        best
+    }
+}
+
+impl From<&Polygon> for Region {
+    fn from(polygon: &Polygon) -> Self {
+        Region::new(vec![polygon.points.clone()], FillRule::NonZero)
+    }
+}
+
+/// The display area of the page, inside the borders
+impl From<&Page> for Region {
+    fn from(page: &Page) -> Self {
+        Region::new(
+            vec![vec![
+                page.top_left(),
+                page.top_right(),
+                page.bottom_right(),
+                page.bottom_left(),
+            ]],
+            FillRule::NonZero,
+        )
+    }
+}
+
+/// Fill a region with a pattern of lines spacing apart
+pub fn fill(region: &Region, pattern: Pattern, spacing: f64) -> path::Data {
+    match pattern {
+        Pattern::Hatch { angle } => hatch(region, angle, spacing),
+        Pattern::CrossHatch { angle } => cross_hatch(region, angle, spacing),
+        Pattern::ZigZag { angle } => zigzag(region, angle, spacing),
+        Pattern::Concentric => approximate_concentric(region, spacing),
+    }
+}
+
+/// Parallel lines across a region at an angle in degrees, clipped to its outline.
+///
+/// Lines sit on a grid lined up with the origin so neighbouring shapes hatched the same way line up, and every
+/// other line is drawn backwards to save pen travel.
+///
+/// ```
+/// use esvg::fill::{hatch, Region};
+/// use polygonical::point::Point;
+/// use polygonical::polygon::Polygon;
+///
+/// let square = Polygon::new(vec![
+///     Point::new(0.0, 0.0),
+///     Point::new(10.0, 0.0),
+///     Point::new(10.0, 10.0),
+///     Point::new(0.0, 10.0),
+/// ]);
+/// let lines = hatch(&Region::from(&square), 0.0, 5.0);
+/// assert_eq!(lines.build(), "M0.000 2.500 L10.000 2.500 M10.000 7.500 L0.000 7.500");
+/// ```
+pub fn hatch(region: &Region, angle: f64, spacing: f64) -> path::Data {
+    let mut data = path::Data::new();
+    let angle = angle.to_radians();
+    for (row, (y, spans)) in scan(region, angle, spacing).into_iter().enumerate() {
+        let backwards = row % 2 == 1;
+        let mut spans = spans;
+        if backwards {
+            spans.reverse();
+        }
+        for (a, b) in spans {
+            let (a, b) = if backwards { (b, a) } else { (a, b) };
+            data.move_to(Point::new(a, y).rotate(angle))
+                .line_to(Point::new(b, y).rotate(angle));
+        }
+    }
+    data
+}
+
+/// Hatching at an angle and again at right angles to it
+pub fn cross_hatch(region: &Region, angle: f64, spacing: f64) -> path::Data {
+    let mut data = hatch(region, angle, spacing);
+    data.append(&hatch(region, angle + 90.0, spacing));
+    data
+}
+
+/// Hatching where each line is joined to the next at alternate ends, so a whole region can often be filled
+/// without lifting the pen. Where the shape splits the lines carry on in separate zig-zags.
+pub fn zigzag(region: &Region, angle: f64, spacing: f64) -> path::Data {
+    struct Chain {
+        points: Vec<Point>,
+        last: (f64, f64),
+        row: usize,
+        forward: bool,
+    }
+
+    let rotation = angle.to_radians();
+    let mut chains: Vec<Chain> = vec![];
+    for (row, (y, spans)) in scan(region, rotation, spacing).into_iter().enumerate() {
+        for (a, b) in spans {
+            let follows = chains.iter_mut().find(|c| {
+                c.row + 1 == row && c.last.0 < b && a < c.last.1 && c.points.last().is_some()
+            });
+            match follows {
+                Some(chain) => {
+                    chain.forward = !chain.forward;
+                    let (from, to) = if chain.forward { (a, b) } else { (b, a) };
+                    chain.points.push(Point::new(from, y));
+                    chain.points.push(Point::new(to, y));
+                    chain.last = (a, b);
+                    chain.row = row;
+                }
+                None => chains.push(Chain {
+                    points: vec![Point::new(a, y), Point::new(b, y)],
+                    last: (a, b),
+                    row,
+                    forward: true,
+                }),
+            }
+        }
+    }
+
+    let mut data = path::Data::new();
+    for chain in chains {
+        let points: Vec<Point> = chain.points.iter().map(|p| p.rotate(rotation)).collect();
+        data.append(&path::Data::from_points(&points));
+    }
+    data
+}
+
+/// The spans of the region on each hatch line, in a frame turned so the lines are horizontal
+fn scan(region: &Region, angle: f64, spacing: f64) -> Vec<(f64, Vec<(f64, f64)>)> {
+    let turned = region.rotated(-angle);
+    let Some((min, max)) = turned.bounds() else {
+        return vec![];
+    };
+    if spacing <= 0.0 {
+        return vec![];
+    }
+
+    let mut result = vec![];
+    let mut k = (min.y / spacing - 0.5).floor();
+    loop {
+        let y = (k + 0.5) * spacing;
+        if y > max.y {
+            break;
+        }
+        if y >= min.y {
+            let spans = turned.spans(y);
+            if !spans.is_empty() {
+                result.push((y, spans));
+            }
+        }
+        k += 1.0;
+    }
+    result
+}
+
+/// Rings following the outline of a region, each spacing further in, starting half a spacing from the edge.
+/// Holes get their own rings growing outwards, and rings split where the shape gets narrow.
+///
+/// This is an approximation: the outlines aren't offset, instead the distance to the edge is sampled on a grid a
+/// quarter of the spacing apart and the rings are traced through it with marching squares. Rings can be off by a
+/// small part of a grid cell and sharp corners get cut off within a cell. Every grid point measures its distance to
+/// every edge, so the time taken grows with the area over the spacing squared times the number of edges, keep the
+/// spacing sensible for big shapes.
+///
+/// ```
+/// use esvg::fill::{approximate_concentric, Region};
+/// use polygonical::point::Point;
+/// use polygonical::polygon::Polygon;
+///
+/// let square = Polygon::new(vec![
+///     Point::new(0.0, 0.0),
+///     Point::new(20.0, 0.0),
+///     Point::new(20.0, 20.0),
+///     Point::new(0.0, 20.0),
+/// ]);
+/// let rings = approximate_concentric(&Region::from(&square), 4.0);
+/// // rings at 2, 6 and 10 in from the edge, the last of which is a point
+/// assert_eq!(rings.build().matches('M').count(), 2);
+/// ```
+pub fn approximate_concentric(region: &Region, spacing: f64) -> path::Data {
+    let mut data = path::Data::new();
+    let Some((min, max)) = region.bounds() else {
+        return data;
+    };
+    if spacing <= 0.0 {
+        return data;
+    }
+
+    // sample the distance to the edge on a grid with a border of outside cells so every contour closes
+    let cell = spacing / 4.0;
+    let origin = Point::new(min.x - cell, min.y - cell);
+    let nx = ((max.x - min.x) / cell).ceil() as usize + 3;
+    let ny = ((max.y - min.y) / cell).ceil() as usize + 3;
+    let position =
+        |i: usize, j: usize| Point::new(origin.x + i as f64 * cell, origin.y + j as f64 * cell);
+
+    let mut field = vec![0.0; nx * ny];
+    let mut deepest: f64 = 0.0;
+    for j in 0..ny {
+        // what is inside only changes along a row where it crosses an outline
+        let spans = region.spans(position(0, j).y);
+        for i in 0..nx {
+            let p = position(i, j);
+            let d = region.edge_distance(p);
+            let inside = spans.iter().any(|(a, b)| *a <= p.x && p.x <= *b);
+            let d = if inside { d } else { -d };
+            deepest = deepest.max(d);
+            field[j * nx + i] = d;
+        }
+    }
+
+    let mut level = spacing / 2.0;
+    while level < deepest {
+        for ring in contours(&field, nx, ny, level, &position) {
+            let closed = ring.len() > 2 && ring.first() == ring.last();
+            let mut d = path::Data::from_points(&ring[..ring.len() - usize::from(closed)]);
+            if closed {
+                d.close();
+            }
+            data.append(&d);
+        }
+        level += spacing;
+    }
+    data
+}
+
+/// An edge of the sampling grid, the horizontal or vertical one leaving a grid point
+type GridEdge = (usize, usize, bool);
+
+/// Marching squares, the lines where the field crosses the level joined up into polylines
+fn contours<F>(field: &[f64], nx: usize, ny: usize, level: f64, position: &F) -> Vec<Vec<Point>>
+where
+    F: Fn(usize, usize) -> Point,
+{
+    let value = |i: usize, j: usize| field[j * nx + i];
+    let mut crossing: HashMap<GridEdge, Point> = HashMap::new();
+    let mut links: HashMap<GridEdge, Vec<GridEdge>> = HashMap::new();
+
+    let mut point_on = |edge: GridEdge| -> Point {
+        *crossing.entry(edge).or_insert_with(|| {
+            let (i, j, horizontal) = edge;
+            let (i2, j2) = if horizontal { (i + 1, j) } else { (i, j + 1) };
+            let (a, b) = (value(i, j), value(i2, j2));
+            let t = (level - a) / (b - a);
+            let (p, q) = (position(i, j), position(i2, j2));
+            Point::new(p.x + (q.x - p.x) * t, p.y + (q.y - p.y) * t)
+        })
+    };
+
+    for j in 0..ny - 1 {
+        for i in 0..nx - 1 {
+            let corners = [
+                value(i, j),
+                value(i + 1, j),
+                value(i + 1, j + 1),
+                value(i, j + 1),
+            ];
+            let inside = corners.map(|v| v >= level);
+            // bottom, right, top, left, so edge n runs between corner n and the next one round
+            let edges: [GridEdge; 4] = [
+                (i, j, true),
+                (i + 1, j, false),
+                (i, j + 1, true),
+                (i, j, false),
+            ];
+            let crossed: Vec<usize> = (0..4)
+                .filter(|n| inside[*n] != inside[(n + 1) % 4])
+                .collect();
+
+            let pairs = match crossed.len() {
+                2 => vec![(crossed[0], crossed[1])],
+                4 => {
+                    // a saddle, cut off the corners that don't match the middle of the cell
+                    let centre = corners.iter().sum::<f64>() / 4.0 >= level;
+                    (0..4)
+                        .filter(|c| inside[*c] != centre)
+                        .map(|c| ((c + 3) % 4, c))
+                        .collect()
+                }
+                _ => vec![],
+            };
+            for (a, b) in pairs {
+                let (a, b) = (edges[a], edges[b]);
+                point_on(a);
+                point_on(b);
+                links.entry(a).or_default().push(b);
+                links.entry(b).or_default().push(a);
+            }
+        }
+    }
+
+    let mut result = vec![];
+    let mut keys: Vec<GridEdge> = links.keys().copied().collect();
+    keys.sort();
+    let mut visited: HashMap<GridEdge, bool> = HashMap::new();
+    for start in keys {
+        if visited.contains_key(&start) {
+            continue;
+        }
+        let mut line = vec![start];
+        visited.insert(start, true);
+        let mut at = start;
+        loop {
+            let next = links[&at]
+                .iter()
+                .find(|k| !visited.contains_key(k))
+                .copied();
+            match next {
+                Some(n) => {
+                    visited.insert(n, true);
+                    line.push(n);
+                    at = n;
+                }
+                None => {
+                    if links[&at].contains(&start) && line.len() > 2 {
+                        line.push(start);
+                    }
+                    break;
+                }
+            }
+        }
+        if line.len() > 1 {
+            result.push(line.iter().map(|k| crossing[k]).collect());
+        }
+    }
+    result
+}
+
+/// Replace the fill of every filled shape in a document with lines in the fill colour, for plotting.
+///
+/// The fill, fill rule, and colour are worked out with inheritance, and shapes filled with a gradient or pattern are
+/// filled in black. The lines are added as a path straight after each shape with the same transform, and the shape's
+/// own fill is turned off. Spacing is kept the same on the page however the shapes are scaled, while the angle is in
+/// each shape's own coordinates. Returns how many shapes were filled.
+///
+/// ```
+/// use esvg::fill::{document, Pattern};
+/// use esvg::length::Length;
+/// use esvg::read::parse_string;
+///
+/// let mut doc = parse_string(
+///     r##"<svg><g fill="#ff0000"><rect width="10" height="10" /></g><rect width="5" height="5" fill="none" /></svg>"##
+///         .to_string(),
+/// )
+/// .unwrap();
+/// let count = document(&mut doc, Pattern::Hatch { angle: 45.0 }, Length::px(2.0), 96).unwrap();
+/// assert_eq!(count, 1);
+/// ```
+pub fn document(
+    doc: &mut Element,
+    pattern: Pattern,
+    spacing: Length,
+    dpi: i32,
+) -> Result<usize, Error> {
+    let spacing = spacing.to_pixels(dpi)?;
+    let root = FillPaint {
+        fill: "black".to_string(),
+        rule: FillRule::NonZero,
+        color: "black".to_string(),
+    };
+    let mut count = 0;
+    fill_children(
+        doc,
+        &root,
+        &Transform::identity(),
+        pattern,
+        spacing,
+        &mut count,
+    )?;
+    Ok(count)
+}
+
+/// Elements that can have a fill
+const FILLABLE: [&str; 6] = ["path", "rect", "circle", "ellipse", "polygon", "polyline"];
+
+#[derive(Debug, Clone)]
+struct FillPaint {
+    fill: String,
+    rule: FillRule,
+    color: String,
+}
+
+impl FillPaint {
+    fn of(el: &Element, parent: &FillPaint) -> FillPaint {
+        let own = |name: &str| el.property(name).filter(|v| v != "inherit");
+        let color = own("color").unwrap_or_else(|| parent.color.clone());
+        let fill = match own("fill") {
+            Some(f) if f.eq_ignore_ascii_case("currentcolor") => color.clone(),
+            Some(f) => f,
+            None => parent.fill.clone(),
+        };
+        let rule = match own("fill-rule").as_deref() {
+            Some("evenodd") => FillRule::EvenOdd,
+            Some("nonzero") => FillRule::NonZero,
+            _ => parent.rule,
+        };
+        FillPaint { fill, rule, color }
+    }
+}
+
+fn fill_children(
+    el: &mut Element,
+    parent: &FillPaint,
+    transform: &Transform,
+    pattern: Pattern,
+    spacing: f64,
+    count: &mut usize,
+) -> Result<(), Error> {
+    let mut children = Vec::with_capacity(el.children.len());
+    for child in std::mem::take(&mut el.children) {
+        let Node::Element(mut child) = child else {
+            children.push(child);
+            continue;
+        };
+        if flatten::NOT_DRAWN.contains(&child.name.as_str()) || !layer::is_visible(&child) {
+            children.push(Node::Element(child));
+            continue;
+        }
+
+        let paint = FillPaint::of(&child, parent);
+        let transform = match child.get("transform") {
+            Some(t) => t.parse::<Transform>()?.then(transform),
+            None => *transform,
+        };
+        fill_children(&mut child, &paint, &transform, pattern, spacing, count)?;
+
+        let filled = FILLABLE.contains(&child.name.as_str()) && paint.fill != "none";
+        let lines = if filled {
+            let scale = transform.scale_factor();
+            let local = if scale > 0.0 {
+                spacing / scale
+            } else {
+                spacing
+            };
+            let region = Region::new(flatten::shape(&child, local / 10.0)?, paint.rule);
+            Some(fill(&region, pattern, local))
+        } else {
+            None
+        };
+
+        let own_transform = child.get("transform");
+        if lines.is_some() {
+            child.set("fill", "none");
+            if child.property("fill").as_deref() != Some("none") {
+                child.set_style("fill", "none");
+            }
+        }
+        children.push(Node::Element(child));
+
+        if let Some(lines) = lines {
+            *count += 1;
+            let colour = if paint.fill.starts_with("url(") {
+                "black".to_string()
+            } else {
+                paint.fill.clone()
+            };
+            let mut hatching = lines.to_path();
+            hatching.set("class", "fill-lines");
+            hatching.set("stroke", colour);
+            if let Some(t) = own_transform {
+                hatching.set("transform", t);
+            }
+            children.push(Node::Element(hatching));
+        }
+    }
+    el.children = children;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{
+        approximate_concentric, cross_hatch, document, hatch, zigzag, FillRule, Pattern, Region,
+    };
+    use crate::length::Length;
+    use crate::path;
+    use crate::read::parse_string;
+    use polygonical::point::Point;
+
+    fn square(x: f64, y: f64, size: f64) -> Vec<Point> {
+        vec![
+            Point::new(x, y),
+            Point::new(x + size, y),
+            Point::new(x + size, y + size),
+            Point::new(x, y + size),
+        ]
+    }
+
+    fn with_hole(rule: FillRule) -> Region {
+        let mut hole = square(5.0, 5.0, 10.0);
+        if rule == FillRule::NonZero {
+            // holes wind the other way for non zero
+            hole.reverse();
+        }
+        Region::new(vec![square(0.0, 0.0, 20.0), hole], rule)
+    }
+
+    #[test]
+    fn regions() {
+        for rule in [FillRule::NonZero, FillRule::EvenOdd] {
+            let region = with_hole(rule);
+            assert!(region.contains(Point::new(2.0, 2.0)));
+            assert!(!region.contains(Point::new(10.0, 10.0)));
+            assert!(!region.contains(Point::new(30.0, 10.0)));
+        }
+
+        // the same winding twice is still filled with non zero
+        let doubled = Region::new(
+            vec![square(0.0, 0.0, 20.0), square(5.0, 5.0, 10.0)],
+            FillRule::NonZero,
+        );
+        assert!(doubled.contains(Point::new(10.0, 10.0)));
+
+        let data = path::Data::from_points(&square(0.0, 0.0, 10.0));
+        let region = Region::from_data(&data, FillRule::NonZero, 0.1).unwrap();
+        assert!(region.contains(Point::new(5.0, 5.0)));
+
+        // detail finer than the written path is kept
+        let data = path::Data::from_points(&square(0.0, 0.0, 0.0004));
+        let region = Region::from_data(&data, FillRule::NonZero, 0.1).unwrap();
+        assert!(region.contains(Point::new(0.0002, 0.0002)));
+    }
+
+    #[test]
+    fn hatching_with_holes() {
+        let lines = hatch(&with_hole(FillRule::EvenOdd), 0.0, 8.0);
+        assert_eq!(
+            lines.build(),
+            "M0.000 4.000 L20.000 4.000 M20.000 12.000 L15.000 12.000 M5.000 12.000 L0.000 12.000"
+        );
+
+        // lines at 3.5, 10.5, and 17.5 each way, the middle ones split by the hole
+        let crossed = cross_hatch(&with_hole(FillRule::NonZero), 0.0, 7.0).build();
+        assert_eq!(crossed.matches('M').count(), 8);
+
+        let turned = hatch(
+            &Region::new(vec![square(0.0, 0.0, 10.0)], FillRule::NonZero),
+            90.0,
+            5.0,
+        );
+        assert_eq!(
+            turned.build(),
+            "M7.500 0.000 L7.500 10.000 M2.500 10.000 L2.500 0.000"
+        );
+    }
+
+    #[test]
+    fn zigzags() {
+        let lines = zigzag(
+            &Region::new(vec![square(0.0, 0.0, 10.0)], FillRule::NonZero),
+            0.0,
+            2.0,
+        );
+        assert_eq!(
+            lines.build(),
+            "M0.000 1.000 L10.000 1.000 L10.000 3.000 L0.000 3.000 L0.000 5.000 L10.000 5.000 L10.000 7.000 L0.000 7.000 L0.000 9.000 L10.000 9.000"
+        );
+
+        // the hole splits it, one run goes down the left and carries on underneath, the other stops on the right
+        let split = zigzag(&with_hole(FillRule::EvenOdd), 0.0, 2.0).build();
+        assert_eq!(split.matches('M').count(), 2);
+    }
+
+    #[test]
+    fn concentric_rings() {
+        let rings = approximate_concentric(&with_hole(FillRule::EvenOdd), 2.0).build();
+        // the band is five wide so it fits rings at 1 and 3 in from each side, which meet in the middle
+        assert!(rings.matches('M').count() >= 2);
+        for ring in rings.split('M').filter(|s| !s.is_empty()) {
+            assert!(ring.trim_end().ends_with('z'));
+        }
+
+        // the traced rings stay within a grid cell of where they should be
+        let square = Region::new(vec![square(0.0, 0.0, 20.0)], FillRule::NonZero);
+        let rings = approximate_concentric(&square, 4.0).build();
+        let traced = path::flatten(&path::parse(&rings).unwrap(), 0.1);
+        assert_eq!(traced.len(), 2);
+        for (ring, level) in traced.iter().zip([2.0, 6.0]) {
+            for p in ring {
+                assert!((square.edge_distance(*p) - level).abs() <= 1.0, "{p:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn fill_document() {
+        let mut doc = parse_string(
+            r#"<svg>
+  <g fill="blue" transform="scale(2)">
+    <circle cx="10" cy="10" r="5" style="fill:currentColor" color="green" transform="translate(1, 1)" />
+    <rect width="5" height="5" fill="none" stroke="black" />
+    <text>not filled</text>
+  </g>
+  <defs><rect id="r" width="5" height="5" /></defs>
+  <path d="M0 0 L10 0 L10 10 z" />
+</svg>"#
+                .to_string(),
+        )
+        .unwrap();
+        let count = document(&mut doc, Pattern::Hatch { angle: 0.0 }, Length::px(2.0), 96).unwrap();
+        assert_eq!(count, 2);
+
+        let text = doc.to_pretty_string();
+        assert!(text.contains("style=\"fill:none\""));
+        let group = doc.elements().next().unwrap();
+        let hatching = group.elements().nth(1).unwrap();
+        assert_eq!(hatching.get("class").unwrap(), "fill-lines");
+        assert_eq!(hatching.get("stroke").unwrap(), "green");
+        assert_eq!(hatching.get("transform").unwrap(), "translate(1, 1)");
+        // spacing is 2 on the page so 1 inside the scaled group
+        assert!(hatching.get("d").unwrap().contains(" 5.500 "));
+
+        let last = doc.elements().last().unwrap();
+        assert_eq!(last.get("stroke").unwrap(), "black");
+    }
+}
@@ -0,0 +1,316 @@
+//! Turn the shapes in a document into straight lines, which is what plotters, estimators, and exporters work with
+use polygonical::point::Point;
+
+use crate::error::Error;
+use crate::layer;
+use crate::length::{Context, CSS_DPI};
+use crate::page::{number_list, svg_length};
+use crate::path;
+use crate::transform::Transform;
+use crate::Element;
+
+/// Elements whose children are only used by reference and are never drawn where they are
+pub(crate) const NOT_DRAWN: [&str; 9] = [
+    "defs",
+    "clipPath",
+    "mask",
+    "marker",
+    "symbol",
+    "pattern",
+    "linearGradient",
+    "radialGradient",
+    "metadata",
+];
+
+/// All the lines drawn in a document or group, in the user space of that element.
+///
+/// Transforms on the children are applied, hidden elements and the contents of definitions are skipped, and text is
+/// left out as it needs a font to draw. Curves are split into lines within tolerance of the real curve, measured in
+/// the user space of the element. Closed shapes end with their first point again.
+///
+/// ```
+/// use esvg::flatten::polylines;
+/// use esvg::read::parse_string;
+///
+/// let doc = parse_string(
+///     r#"<svg><g transform="translate(10, 0)"><rect x="0" y="0" width="5" height="5" /></g></svg>"#.to_string(),
+/// )
+/// .unwrap();
+/// let lines = polylines(&doc, 0.1).unwrap();
+/// assert_eq!(lines[0][0].x, 10.0);
+/// assert_eq!(lines[0].len(), 5);
+/// ```
+pub fn polylines(el: &Element, tolerance: f64) -> Result<Vec<Vec<Point>>, Error> {
+    let mut result = vec![];
+    walk(el, tolerance, &mut |_, lines| result.extend(lines))?;
+    Ok(result)
+}
+
+/// Call a function with every visible shape under an element and its lines in the user space of the element, in
+/// the order they are drawn. See [`polylines`].
+///
+/// The function is also given the elements between this one and the shape, starting with a child of this element
+/// and ending with the shape itself, so inherited styles can be worked out.
+pub fn walk<'a, F>(el: &'a Element, tolerance: f64, f: &mut F) -> Result<(), Error>
+where
+    F: FnMut(&[&'a Element], Vec<Vec<Point>>),
+{
+    walk_segments(el, &mut |ancestors, transform, segments| {
+        let scale = transform.scale_factor();
+        let local_tolerance = if scale > 0.0 {
+            tolerance / scale
+        } else {
+            tolerance
+        };
+        let lines = path::flatten(&segments, local_tolerance);
+        if lines.is_empty() {
+            return;
+        }
+        let lines = if transform.is_identity() {
+            lines
+        } else {
+            lines
+                .into_iter()
+                .map(|l| l.into_iter().map(|p| transform.apply(p)).collect())
+                .collect()
+        };
+        f(ancestors, lines);
+    })
+}
+
+/// Like [`walk`] but the function gets each shape's path segments in its own user space, along with the transform
+/// from there to the user space of this element, for when curves need to be kept as they are.
+pub fn walk_segments<'a, F>(el: &'a Element, f: &mut F) -> Result<(), Error>
+where
+    F: FnMut(&[&'a Element], &Transform, Vec<path::Segment>),
+{
+    let mut ancestors = vec![];
+    for child in el.elements() {
+        visit(child, &Transform::identity(), &mut ancestors, f)?;
+    }
+    Ok(())
+}
+
+fn visit<'a, F>(
+    el: &'a Element,
+    parent: &Transform,
+    ancestors: &mut Vec<&'a Element>,
+    f: &mut F,
+) -> Result<(), Error>
+where
+    F: FnMut(&[&'a Element], &Transform, Vec<path::Segment>),
+{
+    if NOT_DRAWN.contains(&el.name.as_str()) || !layer::is_visible(el) {
+        return Ok(());
+    }
+
+    let transform = match el.get("transform") {
+        Some(t) => t.parse::<Transform>()?.then(parent),
+        None => *parent,
+    };
+
+    ancestors.push(el);
+    let segments = segments(el)?;
+    if !segments.is_empty() {
+        f(ancestors, &transform, segments);
+    }
+
+    for child in el.elements() {
+        visit(child, &transform, ancestors, f)?;
+    }
+    ancestors.pop();
+    Ok(())
+}
+
+/// The lines of a single shape element in its own user space, ignoring its transform and children.
+/// Elements that aren't shapes give no lines.
+pub fn shape(el: &Element, tolerance: f64) -> Result<Vec<Vec<Point>>, Error> {
+    Ok(path::flatten(&segments(el)?, tolerance))
+}
+
+/// The outline of a single shape element as path segments in its own user space, see [`shape`]
+pub fn segments(el: &Element) -> Result<Vec<path::Segment>, Error> {
+    Ok(match el.name.as_str() {
+        "path" => match el.get("d") {
+            Some(d) => path::parse(&d)?,
+            None => vec![],
+        },
+        "line" => vec![
+            path::Segment::MoveTo(Point::new(number(el, "x1")?, number(el, "y1")?)),
+            path::Segment::LineTo(Point::new(number(el, "x2")?, number(el, "y2")?)),
+        ],
+        "polyline" | "polygon" => {
+            let values = match el.get("points") {
+                Some(p) => number_list(&p)
+                    .map_err(|_| Error::InvalidAttribute("points".to_string(), p.clone()))?,
+                None => vec![],
+            };
+            let mut segments: Vec<path::Segment> = values
+                .chunks_exact(2)
+                .enumerate()
+                .map(|(i, p)| {
+                    let p = Point::new(p[0], p[1]);
+                    if i == 0 {
+                        path::Segment::MoveTo(p)
+                    } else {
+                        path::Segment::LineTo(p)
+                    }
+                })
+                .collect();
+            if el.name == "polygon" && !segments.is_empty() {
+                segments.push(path::Segment::Close);
+            }
+            segments
+        }
+        "rect" => rect(el)?,
+        "circle" => {
+            let r = number(el, "r")?;
+            ellipse(number(el, "cx")?, number(el, "cy")?, r, r)
+        }
+        "ellipse" => ellipse(
+            number(el, "cx")?,
+            number(el, "cy")?,
+            number(el, "rx")?,
+            number(el, "ry")?,
+        ),
+        _ => vec![],
+    })
+}
+
+/// A length attribute in user units, missing attributes are zero
+fn number(el: &Element, name: &str) -> Result<f64, Error> {
+    match el.get(name) {
+        Some(v) => {
+            let length =
+                svg_length(&v).map_err(|_| Error::InvalidAttribute(name.to_string(), v.clone()))?;
+            // the viewport percentages refer to isn't known here, so they are taken as zero and the shape left out
+            Ok(length.resolve(&Context::new(CSS_DPI)).unwrap_or(0.0))
+        }
+        None => Ok(0.0),
+    }
+}
+
+fn rect(el: &Element) -> Result<Vec<path::Segment>, Error> {
+    use path::Segment::*;
+
+    let (x, y) = (number(el, "x")?, number(el, "y")?);
+    let (w, h) = (number(el, "width")?, number(el, "height")?);
+    if w <= 0.0 || h <= 0.0 {
+        return Ok(vec![]);
+    }
+
+    // a missing radius takes the value of the other one
+    let (rx, ry) = match (el.get("rx"), el.get("ry")) {
+        (None, None) => (0.0, 0.0),
+        (Some(_), None) => (number(el, "rx")?, number(el, "rx")?),
+        (None, Some(_)) => (number(el, "ry")?, number(el, "ry")?),
+        (Some(_), Some(_)) => (number(el, "rx")?, number(el, "ry")?),
+    };
+    let rx = rx.clamp(0.0, w / 2.0);
+    let ry = ry.clamp(0.0, h / 2.0);
+
+    if rx == 0.0 || ry == 0.0 {
+        return Ok(vec![
+            MoveTo(Point::new(x, y)),
+            LineTo(Point::new(x + w, y)),
+            LineTo(Point::new(x + w, y + h)),
+            LineTo(Point::new(x, y + h)),
+            Close,
+        ]);
+    }
+
+    let corner = |to: Point| ArcTo {
+        rx,
+        ry,
+        rotation: 0.0,
+        large: false,
+        sweep: true,
+        to,
+    };
+    Ok(vec![
+        MoveTo(Point::new(x + rx, y)),
+        LineTo(Point::new(x + w - rx, y)),
+        corner(Point::new(x + w, y + ry)),
+        LineTo(Point::new(x + w, y + h - ry)),
+        corner(Point::new(x + w - rx, y + h)),
+        LineTo(Point::new(x + rx, y + h)),
+        corner(Point::new(x, y + h - ry)),
+        LineTo(Point::new(x, y + ry)),
+        corner(Point::new(x + rx, y)),
+        Close,
+    ])
+}
+
+fn ellipse(cx: f64, cy: f64, rx: f64, ry: f64) -> Vec<path::Segment> {
+    use path::Segment::*;
+
+    if rx <= 0.0 || ry <= 0.0 {
+        return vec![];
+    }
+    let half = |to: Point| ArcTo {
+        rx,
+        ry,
+        rotation: 0.0,
+        large: false,
+        sweep: true,
+        to,
+    };
+    vec![
+        MoveTo(Point::new(cx + rx, cy)),
+        half(Point::new(cx - rx, cy)),
+        half(Point::new(cx + rx, cy)),
+        Close,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{polylines, shape};
+    use crate::read::parse_string;
+    use crate::Element;
+    use polygonical::point::Point;
+
+    #[test]
+    fn shapes() {
+        let mut circle = Element::new("circle");
+        circle.set("cx", 10).set("cy", 10).set("r", 5);
+        let lines = shape(&circle, 0.01).unwrap();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].first(), lines[0].last());
+        assert!(lines[0]
+            .iter()
+            .all(|p| ((p.x - 10.0).hypot(p.y - 10.0) - 5.0).abs() < 1e-9));
+
+        let mut rect = Element::new("rect");
+        rect.set("width", 10).set("height", 4).set("rx", 1);
+        let lines = shape(&rect, 0.1).unwrap();
+        assert!(lines[0].len() > 9);
+
+        let mut polygon = Element::new("polygon");
+        polygon.set("points", "0,0 10,0 10,10");
+        assert_eq!(shape(&polygon, 0.1).unwrap()[0].len(), 4);
+
+        let mut bad = Element::new("line");
+        bad.set("x1", "wide");
+        assert!(shape(&bad, 0.1).is_err());
+        assert!(shape(&Element::new("text"), 0.1).unwrap().is_empty());
+    }
+
+    #[test]
+    fn document() {
+        let doc = parse_string(
+            r#"<svg>
+  <defs><path d="M0 0 L1 1" /></defs>
+  <g transform="scale(2)">
+    <line x1="1" y1="1" x2="2" y2="2" transform="translate(1, 0)" />
+    <path d="M0 0 L5 5" style="display:none" />
+  </g>
+</svg>"#
+                .to_string(),
+        )
+        .unwrap();
+        let lines = polylines(&doc, 0.1).unwrap();
+        assert_eq!(lines, [[Point::new(4.0, 2.0), Point::new(6.0, 4.0)]]);
+    }
+}
@@ -0,0 +1,331 @@
+//! Layers that Inkscape and plotter tools understand, groups marked with `inkscape:groupmode="layer"`
+use crate::page::Page;
+use crate::{create_document, Element, Node};
+
+/// Namespace of the inkscape attributes
+pub const INKSCAPE_NAMESPACE: &str = "http://www.inkscape.org/namespaces/inkscape";
+/// Namespace of the sodipodi attributes
+pub const SODIPODI_NAMESPACE: &str = "http://sodipodi.sourceforge.net/DTD/sodipodi-0.dtd";
+
+/// A layer, which is a group with a few extra attributes.
+///
+/// ```
+/// use esvg::layer::{self, Layer};
+/// use esvg::page::Page;
+/// use esvg::{create_document, shapes};
+///
+/// let page = Page::A4(96);
+/// let mut doc = create_document(&page);
+///
+/// let mut pen = Layer::new("red pen");
+/// pen.add(&shapes::circle(page.center(), 50));
+/// layer::add_layer(&mut doc, &pen);
+///
+/// assert_eq!(layer::find_layer(&doc, "red pen").unwrap().label(), "red pen");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Layer {
+    group: Element,
+}
+
+impl Layer {
+    /// Create a new visible and unlocked layer
+    pub fn new(label: &str) -> Self {
+        let mut group = Element::group();
+        group.set("inkscape:groupmode", "layer");
+        group.set("inkscape:label", label);
+        Layer { group }
+    }
+
+    /// Treat an element as a layer, if it is one
+    pub fn from_element(element: &Element) -> Option<Layer> {
+        is_layer(element).then(|| Layer {
+            group: element.clone(),
+        })
+    }
+
+    /// The name of the layer, or its id if it doesn't have one
+    pub fn label(&self) -> String {
+        label(&self.group)
+    }
+
+    /// Rename the layer
+    pub fn set_label(&mut self, label: &str) -> &mut Self {
+        self.group.set("inkscape:label", label);
+        self
+    }
+
+    /// false if the layer is hidden with `display:none`
+    pub fn is_visible(&self) -> bool {
+        is_visible(&self.group)
+    }
+
+    /// Show or hide the layer
+    pub fn set_visible(&mut self, visible: bool) -> &mut Self {
+        self.group
+            .set_style("display", if visible { "inline" } else { "none" });
+        self
+    }
+
+    /// true if the layer is locked in Inkscape so it can't be edited by accident
+    pub fn is_locked(&self) -> bool {
+        self.group.get("sodipodi:insensitive").as_deref() == Some("true")
+    }
+
+    /// Lock or unlock the layer
+    pub fn set_locked(&mut self, locked: bool) -> &mut Self {
+        if locked {
+            self.group.set("sodipodi:insensitive", "true");
+        } else {
+            self.group.remove("sodipodi:insensitive");
+        }
+        self
+    }
+
+    /// Add something to draw on this layer
+    pub fn add(&mut self, child: &Element) -> &mut Self {
+        self.group.add(child);
+        self
+    }
+
+    /// The group element behind this layer
+    pub fn element(&self) -> &Element {
+        &self.group
+    }
+
+    /// The group element behind this layer, for changing it directly
+    pub fn element_mut(&mut self) -> &mut Element {
+        &mut self.group
+    }
+
+    /// Turn this layer into its group element
+    pub fn into_element(self) -> Element {
+        self.group
+    }
+}
+
+/// true if the element is a group marked as a layer
+pub fn is_layer(element: &Element) -> bool {
+    element.name == "g" && element.get("inkscape:groupmode").as_deref() == Some("layer")
+}
+
+/// false if the element is hidden with `display:none` in its style or a display attribute, the style wins if it has
+/// both. Visibility isn't checked as children can make themselves visible again.
+pub fn is_visible(element: &Element) -> bool {
+    element.property("display").as_deref() != Some("none")
+}
+
+pub(crate) fn label(element: &Element) -> String {
+    element
+        .get("inkscape:label")
+        .or_else(|| element.get("id"))
+        .unwrap_or_default()
+}
+
+/// Add a layer to the top of a document, declaring the inkscape namespaces if needed
+pub fn add_layer(doc: &mut Element, layer: &Layer) {
+    if doc.get("xmlns:inkscape").is_none() {
+        doc.set("xmlns:inkscape", INKSCAPE_NAMESPACE);
+    }
+    if layer.is_locked() && doc.get("xmlns:sodipodi").is_none() {
+        doc.set("xmlns:sodipodi", SODIPODI_NAMESPACE);
+    }
+    doc.add(layer.element());
+}
+
+/// All the top level layers of a document from the bottom up, the order they are drawn in.
+/// Sub layers are left inside their parents.
+pub fn layers(doc: &Element) -> Vec<Layer> {
+    doc.elements().filter_map(Layer::from_element).collect()
+}
+
+/// Find a layer by its label, searching sub layers as well
+pub fn find_layer(doc: &Element, label: &str) -> Option<Layer> {
+    find_element(doc, label).and_then(Layer::from_element)
+}
+
+fn find_element<'a>(el: &'a Element, name: &str) -> Option<&'a Element> {
+    for child in el.elements() {
+        if is_layer(child) {
+            if label(child) == name {
+                return Some(child);
+            }
+            if let Some(found) = find_element(child, name) {
+                return Some(found);
+            }
+        }
+    }
+    None
+}
+
+/// Find a layer by its label so it can be changed in place, searching sub layers as well
+pub fn find_layer_mut<'a>(doc: &'a mut Element, name: &str) -> Option<&'a mut Element> {
+    for child in doc.elements_mut() {
+        if is_layer(child) {
+            if label(child) == name {
+                return Some(child);
+            }
+            if let Some(found) = find_layer_mut(child, name) {
+                return Some(found);
+            }
+        }
+    }
+    None
+}
+
+/// Remove a top level layer from a document by its label, returning it if it was found
+pub fn remove_layer(doc: &mut Element, name: &str) -> Option<Layer> {
+    let index = doc
+        .children
+        .iter()
+        .position(|c| matches!(c, Node::Element(e) if is_layer(e) && label(e) == name))?;
+
+    match doc.children.remove(index) {
+        Node::Element(e) => Some(Layer { group: e }),
+        _ => None,
+    }
+}
+
+/// Move a top level layer so it is at the given position in the list of layers, zero is the bottom.
+/// Returns false if there is no layer with that label.
+///
+/// ```
+/// use esvg::layer::{self, Layer};
+///
+/// let mut doc = esvg::Element::new("svg");
+/// for name in ["a", "b", "c"] {
+///     layer::add_layer(&mut doc, &Layer::new(name));
+/// }
+/// layer::move_layer(&mut doc, "c", 0);
+///
+/// let order: Vec<_> = layer::layers(&doc).iter().map(|l| l.label()).collect();
+/// assert_eq!(order, ["c", "a", "b"]);
+/// ```
+pub fn move_layer(doc: &mut Element, name: &str, position: usize) -> bool {
+    let Some(layer) = remove_layer(doc, name) else {
+        return false;
+    };
+
+    let index = doc
+        .children
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| matches!(c, Node::Element(e) if is_layer(e)))
+        .map(|(i, _)| i)
+        .nth(position)
+        .unwrap_or(doc.children.len());
+
+    doc.children
+        .insert(index, Node::Element(layer.into_element()));
+    true
+}
+
+/// Make one document per top level layer, each the size of the page.
+///
+/// Every document gets a copy of the `defs` from the original so gradients, markers, and the like still work.
+/// Hidden layers are included, check [`Layer::is_visible`] on the result if you don't want them.
+pub fn split_layers(doc: &Element, page: &Page) -> Vec<(Layer, Element)> {
+    let defs: Vec<&Element> = doc.elements().filter(|e| e.name == "defs").collect();
+
+    layers(doc)
+        .into_iter()
+        .map(|layer| {
+            let mut result = create_document(page);
+            result.set("xmlns:inkscape", INKSCAPE_NAMESPACE);
+            if layer.is_locked() {
+                result.set("xmlns:sodipodi", SODIPODI_NAMESPACE);
+            }
+            for d in &defs {
+                result.add(d);
+            }
+            result.add(layer.element());
+            (layer, result)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{find_layer, find_layer_mut, is_visible, layers, split_layers, Layer};
+    use crate::page::Page;
+    use crate::read::parse_string;
+    use crate::Element;
+
+    const INKSCAPE: &str = r#"<svg xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape" xmlns:sodipodi="http://sodipodi.sourceforge.net/DTD/sodipodi-0.dtd" viewBox="0 0 100 100">
+  <defs><linearGradient id="fade" /></defs>
+  <g inkscape:groupmode="layer" id="layer1" inkscape:label="Black" style="display:inline">
+    <path d="M0 0 L10 10" />
+    <g inkscape:groupmode="layer" id="layer3" inkscape:label="Details">
+      <circle cx="5" cy="5" r="1" />
+    </g>
+  </g>
+  <g inkscape:groupmode="layer" id="layer2" inkscape:label="Red" style="display:none" sodipodi:insensitive="true">
+    <path d="M10 0 L0 10" />
+  </g>
+  <g id="not-a-layer" />
+</svg>"#;
+
+    #[test]
+    fn read_inkscape_layers() {
+        let doc = parse_string(INKSCAPE.to_string()).unwrap();
+        let found = layers(&doc);
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].label(), "Black");
+        assert!(found[0].is_visible());
+        assert!(!found[0].is_locked());
+        assert_eq!(found[1].label(), "Red");
+        assert!(!found[1].is_visible());
+        assert!(found[1].is_locked());
+
+        assert!(find_layer(&doc, "Details").is_some());
+        assert!(find_layer(&doc, "not-a-layer").is_none());
+    }
+
+    #[test]
+    fn change_layers() {
+        let mut doc = parse_string(INKSCAPE.to_string()).unwrap();
+        let red = find_layer_mut(&mut doc, "Red").unwrap();
+        red.set_style("display", "inline");
+        red.remove("sodipodi:insensitive");
+
+        let red = find_layer(&doc, "Red").unwrap();
+        assert!(red.is_visible());
+        assert!(!red.is_locked());
+
+        let mut layer = Layer::new("blue");
+        layer.set_visible(false).set_locked(true);
+        assert_eq!(
+            layer.element().to_string(),
+            "<g inkscape:groupmode=\"layer\" inkscape:label=\"blue\" sodipodi:insensitive=\"true\" style=\"display:none\" />"
+        );
+        layer.set_visible(true).set_locked(false);
+        assert_eq!(layer.element().get("style").unwrap(), "display:inline");
+
+        let mut group = Element::new("g");
+        group.set("style", "fill:red; display : none ");
+        assert!(!is_visible(&group));
+        group.set("style", "display:inline").set("display", "none");
+        assert!(is_visible(&group));
+    }
+
+    #[test]
+    fn split() {
+        let doc = parse_string(INKSCAPE.to_string()).unwrap();
+        let page = Page::A4(96);
+        let docs = split_layers(&doc, &page);
+        assert_eq!(docs.len(), 2);
+
+        let (layer, black) = &docs[0];
+        assert_eq!(layer.label(), "Black");
+        let names: Vec<&str> = black.elements().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["defs", "g"]);
+        assert_eq!(
+            black.get("viewBox"),
+            crate::create_document(&page).get("viewBox")
+        );
+
+        let empty = split_layers(&Element::new("svg"), &page);
+        assert!(empty.is_empty());
+    }
+}
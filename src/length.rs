@@ -0,0 +1,611 @@
+//! Lengths with units attached, so values can be kept exact until they are needed in pixels
+use std::fmt;
+use std::ops::{Div, Mul, Neg};
+use std::str::FromStr;
+
+use crate::convert;
+use crate::error::Error;
+use crate::value::Value;
+
+/// The reference pixel density CSS uses. Pixel lengths are converted with this when mixed with other units in
+/// arithmetic, as there is no page to take a dpi from.
+pub const CSS_DPI: i32 = 96;
+
+/// Units a length can be measured in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Unit {
+    /// Pixels, the size of which depends on the dpi of the page
+    Px,
+    /// Inches
+    In,
+    /// Feet
+    Ft,
+    /// Centimetres
+    Cm,
+    /// Millimetres
+    Mm,
+    /// Quarter millimetres
+    Q,
+    /// Points, 1/72 of an inch
+    Pt,
+    /// Picas, 1/6 of an inch
+    Pc,
+    /// Percentage of a reference length, such as the width of the page
+    Percent,
+    /// Multiples of the font size
+    Em,
+    /// Multiples of the height of a lower case x, taken as half the font size
+    Ex,
+}
+
+impl Unit {
+    /// All the units that can be parsed
+    pub const ALL: [Unit; 11] = [
+        Unit::Px,
+        Unit::In,
+        Unit::Ft,
+        Unit::Cm,
+        Unit::Mm,
+        Unit::Q,
+        Unit::Pt,
+        Unit::Pc,
+        Unit::Percent,
+        Unit::Em,
+        Unit::Ex,
+    ];
+
+    /// The suffix used for this unit when written as a string
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            Unit::Px => "px",
+            Unit::In => "in",
+            Unit::Ft => "ft",
+            Unit::Cm => "cm",
+            Unit::Mm => "mm",
+            Unit::Q => "Q",
+            Unit::Pt => "pt",
+            Unit::Pc => "pc",
+            Unit::Percent => "%",
+            Unit::Em => "em",
+            Unit::Ex => "ex",
+        }
+    }
+
+    /// How many of this unit fit in an inch at the given dpi.
+    /// Returns None for relative units as they depend on a [`Context`]
+    pub fn per_inch(&self, dpi: i32) -> Option<f64> {
+        match self {
+            Unit::Px => Some(dpi as f64),
+            Unit::In => Some(1.0),
+            Unit::Ft => Some(1.0 / 12.0),
+            Unit::Cm => Some(2.54),
+            Unit::Mm => Some(25.4),
+            Unit::Q => Some(25.4 * 4.0),
+            Unit::Pt => Some(72.0),
+            Unit::Pc => Some(6.0),
+            Unit::Percent | Unit::Em | Unit::Ex => None,
+        }
+    }
+
+    /// true if the size of this unit depends on something other than the dpi
+    pub fn is_relative(&self) -> bool {
+        self.per_inch(CSS_DPI).is_none()
+    }
+}
+
+/// What relative lengths are measured against when converting them to pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Context {
+    /// Pixels per inch
+    pub dpi: i32,
+    /// The length in pixels that 100% refers to, if there is one
+    pub reference: Option<f64>,
+    /// The font size in pixels that 1em refers to
+    pub font_size: f64,
+}
+
+impl Context {
+    /// A context for the given dpi with a 12pt font size and nothing for percentages to refer to
+    pub fn new(dpi: i32) -> Self {
+        Context {
+            dpi,
+            reference: None,
+            font_size: dpi as f64 / 6.0,
+        }
+    }
+
+    /// Set the length in pixels that percentages are relative to
+    pub fn with_reference(mut self, reference: f64) -> Self {
+        self.reference = Some(reference);
+        self
+    }
+
+    /// Set the font size in pixels that em and ex are relative to
+    pub fn with_font_size(mut self, font_size: f64) -> Self {
+        self.font_size = font_size;
+        self
+    }
+
+    /// How many pixels one of the unit is in this context, percentages are an error without a reference length
+    pub fn unit_size(&self, unit: Unit) -> Result<f64, Error> {
+        Ok(match unit {
+            Unit::Percent => match self.reference {
+                Some(reference) => reference / 100.0,
+                None => return Err(Error::RelativeLength(unit.to_string())),
+            },
+            Unit::Em => self.font_size,
+            Unit::Ex => self.font_size / 2.0,
+            _ => self.dpi as f64 / unit.per_inch(self.dpi).unwrap_or(1.0),
+        })
+    }
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "{}", self.suffix())
+    }
+}
+
+impl FromStr for Unit {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Q" | "q" => Ok(Unit::Q),
+            _ => Unit::ALL
+                .into_iter()
+                .find(|u| u.suffix().eq_ignore_ascii_case(s))
+                .ok_or_else(|| Error::UnknownUnit(s.to_string())),
+        }
+    }
+}
+
+/// A length with a unit.
+///
+/// ```
+/// use esvg::length::{Length, Unit};
+///
+/// let l: Length = "2.5mm".parse().unwrap();
+/// assert_eq!(l, Length::new(2.5, Unit::Mm));
+/// assert_eq!(l.to_pixels(96).unwrap(), 9.448818897637796);
+/// assert_eq!((l * 2.0).to_string(), "5mm");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Length {
+    pub value: f64,
+    pub unit: Unit,
+}
+
+impl Length {
+    /// Create a new length
+    pub fn new(value: f64, unit: Unit) -> Self {
+        Length { value, unit }
+    }
+
+    /// A length in pixels
+    pub fn px(value: f64) -> Self {
+        Length::new(value, Unit::Px)
+    }
+
+    /// A length in inches
+    pub fn inches(value: f64) -> Self {
+        Length::new(value, Unit::In)
+    }
+
+    /// A length in feet
+    pub fn feet(value: f64) -> Self {
+        Length::new(value, Unit::Ft)
+    }
+
+    /// A length in centimetres
+    pub fn cm(value: f64) -> Self {
+        Length::new(value, Unit::Cm)
+    }
+
+    /// A length in millimetres
+    pub fn mm(value: f64) -> Self {
+        Length::new(value, Unit::Mm)
+    }
+
+    /// A length in points
+    pub fn pt(value: f64) -> Self {
+        Length::new(value, Unit::Pt)
+    }
+
+    /// A length in picas
+    pub fn pc(value: f64) -> Self {
+        Length::new(value, Unit::Pc)
+    }
+
+    /// A length of nothing
+    pub fn zero() -> Self {
+        Length::px(0.0)
+    }
+
+    /// A percentage of some reference length
+    pub fn percent(value: f64) -> Self {
+        Length::new(value, Unit::Percent)
+    }
+
+    /// A multiple of the font size
+    pub fn em(value: f64) -> Self {
+        Length::new(value, Unit::Em)
+    }
+
+    /// The length in inches, pixels are converted with the given dpi.
+    /// Relative units are measured against the defaults of [`Context::new`], see [`Length::to_pixels`]
+    pub fn to_inches(&self, dpi: i32) -> Result<f64, Error> {
+        Ok(match self.unit.per_inch(dpi) {
+            Some(per_inch) => self.value / per_inch,
+            None => convert::pixels_to_inches(self.to_pixels(dpi)?, dpi),
+        })
+    }
+
+    /// The length in pixels at the given dpi. No rounding is done.
+    ///
+    /// This is for absolute lengths. Em and ex are measured against the font size of [`Context::new`], and
+    /// percentages have nothing to refer to so are an error, use [`Length::resolve`] to give them one.
+    ///
+    /// ```
+    /// use esvg::length::Length;
+    ///
+    /// assert_eq!(Length::inches(1.0).to_pixels(96).unwrap(), 96.0);
+    /// assert!(Length::percent(10.0).to_pixels(96).is_err());
+    /// ```
+    pub fn to_pixels(&self, dpi: i32) -> Result<f64, Error> {
+        self.resolve(&Context::new(dpi))
+    }
+
+    /// The length in pixels, with relative units measured against the context.
+    ///
+    /// ```
+    /// use esvg::length::{Context, Length};
+    ///
+    /// let ctx = Context::new(96).with_reference(400.0).with_font_size(20.0);
+    /// assert_eq!(Length::percent(25.0).resolve(&ctx).unwrap(), 100.0);
+    /// assert_eq!(Length::em(1.5).resolve(&ctx).unwrap(), 30.0);
+    /// assert_eq!(Length::inches(1.0).resolve(&ctx).unwrap(), 96.0);
+    /// assert!(Length::percent(25.0).resolve(&Context::new(96)).is_err());
+    /// ```
+    pub fn resolve(&self, ctx: &Context) -> Result<f64, Error> {
+        Ok(match self.unit.per_inch(ctx.dpi) {
+            _ if self.unit == Unit::Px => self.value,
+            Some(per_inch) => convert::inches_to_pixels(self.value / per_inch, ctx.dpi),
+            None => {
+                let size = ctx
+                    .unit_size(self.unit)
+                    .map_err(|_| Error::RelativeLength(self.to_string()))?;
+                self.value * size
+            }
+        })
+    }
+
+    /// Convert this length to another unit, pixels are converted with the given dpi.
+    /// Relative units are measured against the defaults of [`Context::new`], so percentages are an error
+    pub fn to_unit(&self, unit: Unit, dpi: i32) -> Result<Length, Error> {
+        self.to_unit_in(unit, &Context::new(dpi))
+    }
+
+    /// Convert this length to another unit, with relative units measured against the context.
+    pub fn to_unit_in(&self, unit: Unit, ctx: &Context) -> Result<Length, Error> {
+        if unit == self.unit {
+            return Ok(*self);
+        }
+        let size = ctx.unit_size(unit)?;
+        if size == 0.0 {
+            return Err(Error::RelativeLength(Length::new(1.0, unit).to_string()));
+        }
+        Ok(Length::new(self.resolve(ctx)? / size, unit))
+    }
+
+    /// Add two lengths, giving the result in the unit of the left hand side. Pixels are converted at [`CSS_DPI`].
+    /// Relative units can only be added to the same unit, as there is nothing to convert them with.
+    ///
+    /// ```
+    /// use esvg::length::Length;
+    ///
+    /// assert_eq!(Length::inches(1.0).checked_add(Length::mm(25.4)).unwrap(), Length::inches(2.0));
+    /// assert_eq!(Length::percent(10.0).checked_add(Length::percent(5.0)).unwrap(), Length::percent(15.0));
+    /// assert!(Length::percent(10.0).checked_add(Length::mm(5.0)).is_err());
+    /// ```
+    pub fn checked_add(self, rhs: Length) -> Result<Length, Error> {
+        Ok(Length::new(self.value + rhs.in_unit_of(&self)?, self.unit))
+    }
+
+    /// Subtract a length, see [`Length::checked_add`]
+    pub fn checked_sub(self, rhs: Length) -> Result<Length, Error> {
+        Ok(Length::new(self.value - rhs.in_unit_of(&self)?, self.unit))
+    }
+
+    /// The value of this length in the unit of another, for arithmetic
+    fn in_unit_of(&self, other: &Length) -> Result<f64, Error> {
+        if self.unit == other.unit {
+            return Ok(self.value);
+        }
+        if self.unit.is_relative() || other.unit.is_relative() {
+            return Err(Error::RelativeLength(format!("{self} and {other}")));
+        }
+        Ok(self.to_unit(other.unit, CSS_DPI)?.value)
+    }
+}
+
+impl Default for Length {
+    fn default() -> Self {
+        Length::zero()
+    }
+}
+
+impl fmt::Display for Length {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "{}{}", self.value, self.unit)
+    }
+}
+
+/// Parse a length, see [`convert::parse_length`] for the formats accepted.
+impl FromStr for Length {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (value, unit) = tokenize(s).ok_or_else(|| Error::InvalidLength(s.to_string()))?;
+        let unit = match unit {
+            "" => Unit::In,
+            u => Unit::from_str(u).map_err(|_| Error::InvalidLength(s.to_string()))?,
+        };
+
+        Ok(Length::new(value, unit))
+    }
+}
+
+/// Split a length into its numeric value and its unit suffix.
+///
+/// The grammar is roughly `sign? (number | number? whole fraction) space? unit?` where number can use scientific
+/// notation and fraction is `digits/digits`, so "-1.5e2mm", "2 1/4in", "3 in" and "10%" are all fine.
+/// Returns None if the input isn't a length.
+pub(crate) fn tokenize(input: &str) -> Option<(f64, &str)> {
+    let s = input.trim();
+    let bytes = s.as_bytes();
+    let mut i = 0;
+
+    let negative = match bytes.first() {
+        Some(b'-') => {
+            i += 1;
+            true
+        }
+        Some(b'+') => {
+            i += 1;
+            false
+        }
+        _ => false,
+    };
+
+    let (mut value, end) = number(s, i)?;
+    i = end;
+
+    // A fraction, either straight after the number ("5/8") or after a whole number ("2 1/4")
+    if bytes.get(i) == Some(&b'/') {
+        let (bottom, end) = digits(s, i + 1)?;
+        value /= bottom;
+        i = end;
+    } else {
+        let after_space = skip_space(bytes, i);
+        if after_space > i && bytes.get(after_space).is_some_and(u8::is_ascii_digit) {
+            let (top, end) = digits(s, after_space)?;
+            if bytes.get(end) != Some(&b'/') {
+                return None;
+            }
+            let (bottom, end) = digits(s, end + 1)?;
+            value += top / bottom;
+            i = end;
+        }
+    }
+
+    let unit = s[i..].trim_start();
+    let valid_unit = unit == "%" || unit.bytes().all(|b| b.is_ascii_alphabetic());
+    if !valid_unit || !value.is_finite() {
+        return None;
+    }
+
+    Some((if negative { -value } else { value }, unit))
+}
+
+/// A decimal number with an optional exponent starting at i, returns the value and where it finished
+fn number(s: &str, start: usize) -> Option<(f64, usize)> {
+    let bytes = s.as_bytes();
+    let mut i = start;
+    while bytes.get(i).is_some_and(u8::is_ascii_digit) {
+        i += 1;
+    }
+    if bytes.get(i) == Some(&b'.') {
+        i += 1;
+        while bytes.get(i).is_some_and(u8::is_ascii_digit) {
+            i += 1;
+        }
+    }
+    if i == start || &s[start..i] == "." {
+        return None;
+    }
+
+    // only treat an e as an exponent if digits follow, otherwise it is the start of em or ex
+    if matches!(bytes.get(i), Some(b'e') | Some(b'E')) {
+        let mut j = i + 1;
+        if matches!(bytes.get(j), Some(b'+') | Some(b'-')) {
+            j += 1;
+        }
+        if bytes.get(j).is_some_and(u8::is_ascii_digit) {
+            while bytes.get(j).is_some_and(u8::is_ascii_digit) {
+                j += 1;
+            }
+            i = j;
+        }
+    }
+
+    f64::from_str(&s[start..i]).ok().map(|v| (v, i))
+}
+
+/// An unsigned run of digits starting at i, returns the value and where it finished
+fn digits(s: &str, start: usize) -> Option<(f64, usize)> {
+    let bytes = s.as_bytes();
+    let mut i = start;
+    while bytes.get(i).is_some_and(u8::is_ascii_digit) {
+        i += 1;
+    }
+    if i == start {
+        return None;
+    }
+    f64::from_str(&s[start..i]).ok().map(|v| (v, i))
+}
+
+fn skip_space(bytes: &[u8], mut i: usize) -> usize {
+    while bytes.get(i).is_some_and(u8::is_ascii_whitespace) {
+        i += 1;
+    }
+    i
+}
+
+/// SVG has no feet unit so those are written out as inches. Relative units are written as is.
+impl From<Length> for Value {
+    fn from(other: Length) -> Self {
+        match other.unit {
+            Unit::Ft => Length::inches(other.value * 12.0).to_string().into(),
+            _ => other.to_string().into(),
+        }
+    }
+}
+
+impl Mul<f64> for Length {
+    type Output = Length;
+
+    fn mul(self, rhs: f64) -> Length {
+        Length::new(self.value * rhs, self.unit)
+    }
+}
+
+impl Div<f64> for Length {
+    type Output = Length;
+
+    fn div(self, rhs: f64) -> Length {
+        Length::new(self.value / rhs, self.unit)
+    }
+}
+
+impl Neg for Length {
+    type Output = Length;
+
+    fn neg(self) -> Length {
+        Length::new(-self.value, self.unit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::str::FromStr;
+
+    use super::{Context, Length, Unit};
+    use crate::error::Error;
+    use crate::value::Value;
+
+    #[test]
+    fn parse_units() {
+        assert_eq!(Length::from_str("3pt").unwrap(), Length::pt(3.0));
+        assert_eq!(Length::from_str("10Q").unwrap(), Length::new(10.0, Unit::Q));
+        assert_eq!(Length::from_str("2 1/4in").unwrap(), Length::inches(2.25));
+        assert_eq!(Length::from_str("7").unwrap(), Length::inches(7.0));
+        assert!(Length::from_str("7furlongs").is_err());
+    }
+
+    #[test]
+    fn parse_grammar() {
+        assert_eq!(Length::from_str("5pt").unwrap(), Length::pt(5.0));
+        assert_eq!(Length::from_str("1e3mm").unwrap(), Length::mm(1000.0));
+        assert_eq!(Length::from_str("1.5E-1cm").unwrap(), Length::cm(0.15));
+        assert_eq!(Length::from_str("10%").unwrap(), Length::percent(10.0));
+        assert_eq!(Length::from_str("-2.5in").unwrap(), Length::inches(-2.5));
+        assert_eq!(Length::from_str("3 in").unwrap(), Length::inches(3.0));
+        assert_eq!(Length::from_str("2mm ").unwrap(), Length::mm(2.0));
+        assert_eq!(Length::from_str(" +.5em").unwrap(), Length::em(0.5));
+        assert_eq!(Length::from_str("2ex").unwrap(), Length::new(2.0, Unit::Ex));
+        assert_eq!(Length::from_str("-2 1/4in").unwrap(), Length::inches(-2.25));
+        assert_eq!(Length::from_str("5/8").unwrap(), Length::inches(0.625));
+        assert_eq!(Length::from_str("2 4/16 in").unwrap(), Length::inches(2.25));
+    }
+
+    #[test]
+    fn parse_errors() {
+        for bad in [
+            "", "mm", ".mm", "1.2.3mm", "2 3mm", "1/mm", "1e", "--1in", "5 % 2", "1e400mm",
+        ] {
+            match Length::from_str(bad) {
+                Err(Error::InvalidLength(s)) => assert_eq!(s, bad),
+                other => panic!("expected an invalid length for {bad:?} got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn arithmetic() {
+        let add = |a: Length, b: Length| a.checked_add(b).unwrap();
+        assert_eq!(
+            add(Length::inches(1.0), Length::mm(25.4)),
+            Length::inches(2.0)
+        );
+        assert_eq!(
+            Length::mm(30.0).checked_sub(Length::cm(1.0)).unwrap(),
+            Length::mm(20.0)
+        );
+        assert_eq!(
+            add(Length::inches(1.0), Length::px(48.0)),
+            Length::inches(1.5)
+        );
+        assert_eq!(-Length::pt(3.0) / 2.0, Length::pt(-1.5));
+        assert_eq!(add(Length::em(1.0), Length::em(0.5)), Length::em(1.5));
+        assert!(matches!(
+            Length::mm(2.0).checked_sub(Length::percent(10.0)),
+            Err(Error::RelativeLength(l)) if l == "10% and 2mm"
+        ));
+    }
+
+    #[test]
+    fn relative_without_reference() {
+        let ctx = Context::new(96);
+        assert!(matches!(
+            Length::percent(10.0).resolve(&ctx),
+            Err(Error::RelativeLength(l)) if l == "10%"
+        ));
+        assert!(Length::mm(5.0).to_unit(Unit::Percent, 96).is_err());
+        assert!(Length::mm(5.0)
+            .to_unit_in(Unit::Percent, &ctx.with_reference(0.0))
+            .is_err());
+        assert_eq!(
+            Length::px(48.0)
+                .to_unit_in(Unit::Percent, &ctx.with_reference(96.0))
+                .unwrap(),
+            Length::percent(50.0)
+        );
+        assert!(matches!(
+            Length::percent(10.0).to_pixels(96),
+            Err(Error::RelativeLength(l)) if l == "10%"
+        ));
+    }
+
+    #[test]
+    fn conversion() {
+        assert_eq!(
+            Length::inches(1.0).to_unit(Unit::Pt, 96).unwrap(),
+            Length::pt(72.0)
+        );
+        assert_eq!(
+            Length::px(150.0).to_unit(Unit::In, 300).unwrap(),
+            Length::inches(0.5)
+        );
+        assert_eq!(Length::mm(2.5).to_pixels(300).unwrap(), 29.52755905511811);
+    }
+
+    #[test]
+    fn into_value() {
+        let v: Value = Length::mm(2.5).into();
+        assert_eq!(v.to_string_bare(), "2.5mm");
+        let v: Value = Length::feet(2.0).into();
+        assert_eq!(v.to_string_bare(), "24in");
+    }
+}
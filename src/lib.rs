@@ -5,13 +5,32 @@ use std::fmt;
 use std::fs::File;
 use std::io::prelude::*;
 
+pub mod calibration;
 pub mod convert;
+pub mod diff;
 pub mod error;
+pub mod estimate;
+pub mod export;
+pub mod fill;
+pub mod flatten;
+pub mod layer;
+pub mod length;
+pub mod marks;
+pub mod optimise;
 pub mod page;
 pub mod path;
+pub mod pens;
+pub mod plot;
+pub mod raster;
 pub mod read;
+pub mod sanitize;
 pub mod shapes;
+pub mod simplify;
+pub mod stipple;
+pub mod testing;
 pub mod text;
+pub mod tile;
+pub mod transform;
 pub mod value;
 
 use crate::error::Error;
@@ -19,40 +38,65 @@ use crate::page::Page;
 
 /// Create a new document with the width, height, and view box setup for the provided page.
 pub fn create_document(paper: &Page) -> Element {
+    sized_document(0.0, 0.0, paper.width, paper.height, paper.dpi)
+}
+
+/// An empty document with a view box of the given size in pixels starting at x, y, and a physical size to match
+pub(crate) fn sized_document(x: f64, y: f64, width: f64, height: f64, dpi: i32) -> Element {
     let mut el = Element::new("svg");
     el.set("xmlns", "http://www.w3.org/2000/svg");
     el.set("xmlns:xlink", "http://www.w3.org/1999/xlink");
-    el.set(
-        "viewBox",
-        format!("0, 0, {}, {}", paper.width, paper.height),
-    );
-    el.set(
-        "width",
-        format!("{}mm", convert::pixels_to_mm(paper.width, paper.dpi)),
-    );
-    el.set(
-        "height",
-        format!("{}mm", convert::pixels_to_mm(paper.height, paper.dpi)),
-    );
+    el.set("viewBox", format!("{x}, {y}, {width}, {height}"));
+    // a millionth of a millimetre is plenty, and keeps 210mm from coming back as 210.00000000000003mm
+    let mm =
+        |px: f64| value::NumberFormat::decimals(6).format_unchecked(convert::pixels_to_mm(px, dpi));
+    el.set("width", format!("{}mm", mm(width)));
+    el.set("height", format!("{}mm", mm(height)));
 
     el
 }
 
-/// Write the provided document to a file at the given path.
+/// Write the provided document to a file at the given path. Numbers that aren't finite are an error rather than
+/// ending up in the file.
 pub fn save(path: &str, doc: &Element) -> Result<(), Error> {
+    let text = doc.pretty_fmt_with(None, true)?;
+    let mut f = File::create(path)?;
+    write!(f, "{text}")?;
+    Ok(())
+}
+
+/// Write the provided document to a file at the given path, with every number in the same format. See
+/// [`Element::to_pretty_string_with`].
+pub fn save_with(path: &str, doc: &Element, format: &value::NumberFormat) -> Result<(), Error> {
+    let text = doc.to_pretty_string_with(format)?;
     let mut f = File::create(path)?;
-    write!(f, "{}", doc.to_pretty_string())?;
+    write!(f, "{text}")?;
     Ok(())
 }
 
-/// Read an svg from the given path.
+/// Read an svg from the given path, with the default [`read::ParseOptions`].
 pub fn read(path: &str) -> Result<Element, Error> {
-    let mut f = File::open(path)?;
+    read_with(path, &read::ParseOptions::default())
+}
+
+/// Read an svg from the given path, with limits on how big it can be. Files over the size limit are turned away
+/// without being read in.
+pub fn read_with(path: &str, options: &read::ParseOptions) -> Result<Element, Error> {
+    let f = File::open(path)?;
 
     let mut buffer = String::new();
-    f.read_to_string(&mut buffer)?;
+    match options.max_file_size {
+        // the file could still grow, or not know its size, so only read one byte past the limit
+        Some(limit) => {
+            if f.metadata()?.len() > limit as u64 {
+                return Err(Error::FileTooLarge(limit));
+            }
+            f.take(limit as u64 + 1).read_to_string(&mut buffer)?;
+        }
+        None => _ = (&f).read_to_string(&mut buffer)?,
+    }
 
-    read::parse_string(buffer)
+    read::parse_string_with(buffer, options)
 }
 
 /// Defines a node in the xml tree
@@ -66,7 +110,7 @@ pub enum Node {
 impl fmt::Display for Node {
     fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Node::Text(s) => write!(formatter, "{s}"),
+            Node::Text(s) => write!(formatter, "{}", value::escape(s, false)),
             Node::Element(e) => write!(formatter, "{e}"),
             Node::Comment(c) => write!(formatter, "<!-- {c} -->"),
         }
@@ -115,6 +159,22 @@ impl Element {
         Node::Element(self.clone())
     }
 
+    /// Iterate over the children of this element that are elements, skipping text and comments
+    pub fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(|c| match c {
+            Node::Element(e) => Some(e),
+            _ => None,
+        })
+    }
+
+    /// Iterate mutably over the children of this element that are elements, skipping text and comments
+    pub fn elements_mut(&mut self) -> impl Iterator<Item = &mut Element> {
+        self.children.iter_mut().filter_map(|c| match c {
+            Node::Element(e) => Some(e),
+            _ => None,
+        })
+    }
+
     /// Add a child element to this element
     pub fn add(&mut self, child: &Element) {
         self.children.push(Node::Element(child.clone()));
@@ -144,6 +204,56 @@ impl Element {
         self.attributes.get(&key.into()).map(|s| s.to_string_bare())
     }
 
+    /// Remove an attribute from this element, returning its value if it had one
+    pub fn remove<K>(&mut self, key: K) -> Option<String>
+    where
+        K: Into<String>,
+    {
+        self.attributes
+            .remove(&key.into())
+            .map(|v| v.to_string_bare())
+    }
+
+    /// Set a style property, replacing it if it is already in the style attribute
+    ///
+    /// ```
+    /// let mut el = esvg::Element::new("g");
+    /// el.add_style("display", "none").add_style("fill", "red");
+    /// el.set_style("display", "inline");
+    /// assert_eq!(el.get("style").unwrap(), "display:inline;fill:red");
+    /// ```
+    pub fn set_style<K, V>(&mut self, key: K, value: V) -> &mut Self
+    where
+        K: Into<String>,
+        V: Into<value::Value>,
+    {
+        let key = key.into();
+        let value = value.into().to_string_bare();
+        let existing = self.get("style").unwrap_or_default();
+
+        let mut found = false;
+        let mut parts = vec![];
+        for part in existing.split(';').filter(|p| !p.trim().is_empty()) {
+            match part.split_once(':') {
+                Some((k, _)) if k.trim() == key => {
+                    if !found {
+                        parts.push(format!("{key}:{value}"));
+                        found = true;
+                    }
+                }
+                _ => parts.push(part.to_string()),
+            }
+        }
+        if !found {
+            parts.push(format!("{key}:{value}"));
+        }
+
+        self.attributes
+            .insert("style".into(), parts.join(";").into());
+
+        self
+    }
+
     pub fn add_style<K, V>(&mut self, key: K, value: V) -> &mut Self
     where
         K: Into<String>,
@@ -166,11 +276,36 @@ impl Element {
         self
     }
 
+    /// A presentation property like `stroke` or `fill`, from the style attribute if it is set there, otherwise the
+    /// attribute of the same name. Inheritance isn't taken into account.
+    ///
+    /// ```
+    /// let mut el = esvg::Element::new("path");
+    /// el.set("stroke", "red").set("style", "fill: blue; stroke:green");
+    /// assert_eq!(el.property("stroke").unwrap(), "green");
+    /// assert_eq!(el.property("fill").unwrap(), "blue");
+    /// assert!(el.property("opacity").is_none());
+    /// ```
+    pub fn property(&self, name: &str) -> Option<String> {
+        let styled = self
+            .style_map()
+            .ok()
+            .and_then(|s| s.into_iter().find(|(k, _)| k.trim() == name))
+            .map(|(_, v)| v);
+        styled
+            .or_else(|| self.get(name))
+            .map(|v| v.trim().to_string())
+    }
+
     pub fn style_map(&self) -> Result<HashMap<String, String>, Error> {
         let mut result = HashMap::new();
 
         if let Some(v) = self.attributes.get("style") {
             for e in v.to_string_bare().split(';') {
+                // a trailing semicolon leaves nothing after it
+                if e.trim().is_empty() {
+                    continue;
+                }
                 if let Some((key, value)) = e.split_once(':') {
                     result.insert(key.to_string(), value.to_string());
                 } else {
@@ -182,6 +317,23 @@ impl Element {
         Ok(result)
     }
 
+    /// Find the element with an id, looking at this element and everything under it
+    ///
+    /// ```
+    /// let mut doc = esvg::Element::new("svg");
+    /// let mut group = esvg::Element::group();
+    /// group.add(esvg::Element::new("rect").set("id", "box"));
+    /// doc.add(&group);
+    /// assert_eq!(doc.find_by_id("box").unwrap().name, "rect");
+    /// assert!(doc.find_by_id("circle").is_none());
+    /// ```
+    pub fn find_by_id(&self, id: &str) -> Option<&Element> {
+        if self.get("id").as_deref() == Some(id) {
+            return Some(self);
+        }
+        self.elements().find_map(|e| e.find_by_id(id))
+    }
+
     /// Create a copy of this element with out its children
     pub fn shallow_clone(&self) -> Element {
         let mut result = Element::new(self.name.as_str());
@@ -191,31 +343,72 @@ impl Element {
         result
     }
 
-    /// Create a nicely formatted string
+    /// Create a nicely formatted string. Numbers that aren't finite are written as they are, use
+    /// [`to_pretty_string_with`](Element::to_pretty_string_with) or [`save`] to have them be an error.
     pub fn to_pretty_string(&self) -> String {
+        self.pretty_fmt_with(None, false)
+            .expect("only number formats can fail")
+    }
+
+    /// Create a nicely formatted string with every number written in the same format. This covers numbers set as
+    /// floats and the numbers in path data, transforms, and other attributes that hold numbers. Numbers that aren't
+    /// finite are an error rather than ending up in the document.
+    ///
+    /// ```
+    /// use esvg::value::NumberFormat;
+    ///
+    /// let mut el = esvg::Element::new("path");
+    /// el.set("d", "M0.50000 1.25 L10.12345 0").set("opacity", 0.1 + 0.2);
+    /// let format = NumberFormat { drop_leading_zero: true, ..NumberFormat::decimals(2) };
+    /// assert_eq!(el.to_pretty_string_with(&format).unwrap(), "<path d=\"M.5 1.25 L10.12 0\" opacity=\".3\" />\n");
+    ///
+    /// el.set("x", f64::NAN);
+    /// assert!(el.to_pretty_string_with(&format).is_err());
+    /// ```
+    pub fn to_pretty_string_with(&self, format: &value::NumberFormat) -> Result<String, Error> {
+        self.pretty_fmt_with(Some(format), true)
+    }
+
+    fn pretty_fmt_with(
+        &self,
+        format: Option<&value::NumberFormat>,
+        checked: bool,
+    ) -> Result<String, Error> {
         let buff = if self.name == "svg" {
             "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE svg PUBLIC \"-//W3C//DTD SVG 1.0//EN\" \"http://www.w3.org/TR/2001/REC-SVG-20010904/DTD/svg10.dtd\">\n".to_string()
         } else {
             "".to_string()
         };
 
-        self.pretty_fmt_internal(buff, 0)
+        self.pretty_fmt_internal(buff, 0, format, checked)
     }
 
-    fn pretty_fmt_internal(&self, mut buff: String, depth: usize) -> String {
+    fn pretty_fmt_internal(
+        &self,
+        mut buff: String,
+        depth: usize,
+        format: Option<&value::NumberFormat>,
+        checked: bool,
+    ) -> Result<String, Error> {
         buff = format!("{}{}<{}", buff, "\t".repeat(depth), self.name);
 
         let mut attributes = self.attributes.iter().collect::<Vec<_>>();
         attributes.sort_by_key(|pair| pair.0.as_str());
         for (k, v) in attributes {
-            buff = format!("{buff} {k}={v}");
+            if checked {
+                v.check(k)?;
+            }
+            buff = match format {
+                Some(format) => format!("{buff} {k}={}", v.format_with(k, format)?),
+                None => format!("{buff} {k}={v}"),
+            };
         }
         if !self.children.is_empty() {
             buff = format!("{buff}>\n");
             for child in self.children.iter() {
                 buff = match child {
-                    Node::Text(s) => format!("{buff}{s}"),
-                    Node::Element(e) => e.pretty_fmt_internal(buff, depth + 1),
+                    Node::Text(s) => format!("{buff}{}", value::escape(s, false)),
+                    Node::Element(e) => e.pretty_fmt_internal(buff, depth + 1, format, checked)?,
                     Node::Comment(c) => format!("{buff}<!-- {c} -->"),
                 };
             }
@@ -224,7 +417,7 @@ impl Element {
             buff = format!("{buff} />\n");
         }
 
-        buff
+        Ok(buff)
     }
 }
 
@@ -253,7 +446,7 @@ mod tests {
 
     use std::collections::HashMap;
 
-    use super::Element;
+    use super::{value::NumberFormat, Element};
 
     #[test]
     fn element_display() {
@@ -292,6 +485,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn non_finite_not_saved() {
+        let path = std::env::temp_dir().join("esvg_non_finite.svg");
+        let path = path.to_str().unwrap();
+
+        let mut element = Element::new("svg");
+        element.set("x", f64::NAN);
+        assert!(super::save(path, &element).is_err());
+        assert!(element
+            .to_pretty_string_with(&NumberFormat::default())
+            .is_err());
+
+        element
+            .set("x", 1.5)
+            .set("d", format!("M{} 0", f64::INFINITY));
+        assert!(super::save(path, &element).is_err());
+        assert!(element
+            .to_pretty_string_with(&NumberFormat::default())
+            .is_err());
+
+        element.set("d", "M1 0").set("class", "NaN");
+        super::save(path, &element).unwrap();
+        assert!(std::fs::read_to_string(path).unwrap().contains("x=\"1.5\""));
+        std::fs::remove_file(path).unwrap();
+    }
+
     #[test]
     fn element_add_style() {
         let mut element = Element::new("foo");
@@ -0,0 +1,333 @@
+//! Printer's marks for sending work to a professional printer: crop marks, registration targets, a slug with job
+//! details, and colour bars, all drawn outside the trimmed page.
+use polygonical::point::Point;
+
+use crate::convert;
+use crate::error::Error;
+use crate::length::Length;
+use crate::page::Page;
+use crate::shapes;
+use crate::text;
+use crate::{path, Element};
+
+/// Which marks to draw and what to write in the slug.
+pub struct PrintMarks {
+    /// Name of the job written in the slug
+    pub job_name: String,
+    /// Date written in the slug, in whatever format the printer wants
+    pub date: String,
+    /// Size of the area outside the bleed that holds the marks
+    pub slug: Length,
+    /// Lines at each corner showing where to cut
+    pub crop_marks: bool,
+    /// Targets in the middle of each side for lining up the printing plates
+    pub registration: bool,
+    /// Patches of the process colours and tints of black along the top
+    pub colour_bars: bool,
+}
+
+impl PrintMarks {
+    /// Crop marks and registration targets with a half inch slug, no colour bars
+    pub fn new(job_name: &str, date: &str) -> Self {
+        PrintMarks {
+            job_name: job_name.to_string(),
+            date: date.to_string(),
+            slug: Length::inches(0.5),
+            crop_marks: true,
+            registration: true,
+            colour_bars: false,
+        }
+    }
+}
+
+/// The process colours, primaries, and tints of black used for colour bars
+const COLOUR_BARS: [&str; 10] = [
+    "#00ffff", "#ff00ff", "#ffff00", "#000000", "#ff0000", "#00ff00", "#0000ff", "#404040",
+    "#808080", "#bfbfbf",
+];
+
+/// Create a document for printing the page with its bleed and marks around it.
+///
+/// The document is big enough for the page, its bleed, and the slug on every side, but the view box is moved so the
+/// top left corner of the trimmed page is still at 0, 0. Anything you would have drawn on a document from
+/// [`crate::create_document`] can be drawn on this one in the same place.
+///
+/// ```
+/// use esvg::length::Length;
+/// use esvg::marks::{create_document, PrintMarks};
+/// use esvg::page::Page;
+///
+/// // an eighth of an inch bleed and the default half inch slug
+/// let page = Page::letter(96).with_bleed(Length::inches(0.125)).unwrap();
+/// let doc = create_document(&page, &PrintMarks::new("booklet", "2024-05-01")).unwrap();
+/// assert_eq!(doc.get("viewBox").unwrap(), "-60, -60, 936, 1176");
+/// ```
+pub fn create_document(page: &Page, marks: &PrintMarks) -> Result<Element, Error> {
+    let slug = marks.slug.to_pixels(page.dpi)?;
+    let outside = page.bleed + slug;
+    let mut el = crate::sized_document(
+        -outside,
+        -outside,
+        page.width + outside * 2.0,
+        page.height + outside * 2.0,
+        page.dpi,
+    );
+    el.add(&draw_marks(page, marks, slug));
+
+    Ok(el)
+}
+
+/// Copy the content of a document made for the page on to a print document, clipped to the bleed.
+pub fn apply(doc: &Element, page: &Page, marks: &PrintMarks) -> Result<Element, Error> {
+    let mut result = create_document(page, marks)?;
+
+    let mut clip = Element::new("clipPath");
+    clip.set("id", "bleed-box");
+    clip.add(&shapes::rectangle(
+        Point::new(page.width / 2.0, page.height / 2.0),
+        page.width + page.bleed * 2.0,
+        page.height + page.bleed * 2.0,
+    ));
+    let mut defs = Element::new("defs");
+    defs.add(&clip);
+    result.add(&defs);
+
+    let mut content = Element::group();
+    content.set("clip-path", "url(#bleed-box)");
+    for child in &doc.children {
+        content.add_node(child.clone());
+    }
+    result.add(&content);
+
+    Ok(result)
+}
+
+/// All the marks for a page, in trim coordinates, with the slug in pixels
+fn draw_marks(page: &Page, marks: &PrintMarks, slug: f64) -> Element {
+    let dpi = page.dpi as f64;
+    let (w, h, b) = (page.width, page.height, page.bleed);
+    // 0.25pt hairlines as printers expect
+    let line_width = dpi / 288.0;
+
+    let mut group = Element::group();
+    group.set("class", "printer-marks");
+
+    let line = |data: &path::Data| {
+        let mut el = data.to_path();
+        el.set("stroke", "black");
+        el.set("stroke-width", line_width);
+        el
+    };
+
+    // marks start a little way out from the bleed so they never print on the finished piece
+    let gap = dpi / 32.0;
+    if marks.crop_marks && slug > gap {
+        let length = (slug - gap).min(dpi / 4.0);
+        let start = b + gap;
+        let mut data = path::Data::new();
+        for (x, dx) in [(0.0, -1.0), (w, 1.0)] {
+            for (y, dy) in [(0.0, -1.0), (h, 1.0)] {
+                data.move_to(Point::new(x + dx * start, y))
+                    .line_to(Point::new(x + dx * (start + length), y))
+                    .move_to(Point::new(x, y + dy * start))
+                    .line_to(Point::new(x, y + dy * (start + length)));
+            }
+        }
+        let mut crops = line(&data);
+        crops.set("class", "crop-marks");
+        group.add(&crops);
+    }
+
+    let radius = (slug / 4.0).min(dpi / 8.0);
+    let middle = b + slug / 2.0;
+    if marks.registration && radius > 0.0 {
+        let centres = [
+            Point::new(w / 2.0, -middle),
+            Point::new(w / 2.0, h + middle),
+            Point::new(-middle, h / 2.0),
+            Point::new(w + middle, h / 2.0),
+        ];
+        for c in centres {
+            group.add(&registration_target(c, radius, line_width, "black"));
+        }
+    }
+
+    // the bars go between the top left crop mark and the top registration target, shrinking to fit on small pages
+    let start = gap;
+    let end = if marks.registration && radius > 0.0 {
+        w / 2.0 - radius * 1.5 - gap
+    } else {
+        w - gap
+    };
+    let size = (slug / 2.0)
+        .min(dpi / 5.0)
+        .min((end - start) / COLOUR_BARS.len() as f64);
+    if marks.colour_bars && size > 0.0 {
+        let mut bars = Element::group();
+        bars.set("class", "colour-bars");
+        for (i, colour) in COLOUR_BARS.iter().enumerate() {
+            let centre = Point::new(start + size * (i as f64 + 0.5), -middle);
+            let mut patch = shapes::rectangle(centre, size, size);
+            patch.set("fill", *colour);
+            bars.add(&patch);
+        }
+        group.add(&bars);
+    }
+
+    if slug > 0.0 {
+        let font_size = (slug / 3.0).min(dpi / 9.0);
+        let details = format!(
+            "{} | {} | trim {} x {} mm | bleed {} mm",
+            marks.job_name,
+            marks.date,
+            round(convert::pixels_to_mm(w, page.dpi)),
+            round(convert::pixels_to_mm(h, page.dpi)),
+            round(convert::pixels_to_mm(b, page.dpi)),
+        );
+        let mut slug_text = text::create_text(
+            details,
+            Point::new(0.0, h + middle + font_size / 3.0),
+            &format!("font-family:sans-serif;font-size:{font_size}px;fill:black"),
+        );
+        slug_text.set("class", "slug");
+        group.add(&slug_text);
+    }
+
+    group
+}
+
+/// A circle with a cross through it for lining up passes, drawn in the given colour
+pub(crate) fn registration_target(
+    centre: Point,
+    radius: f64,
+    line_width: f64,
+    colour: &str,
+) -> Element {
+    let mut target = Element::group();
+    target.set("class", "registration-mark");
+
+    let mut circle = shapes::circle(centre, 0);
+    circle.set("r", radius);
+    circle.set("fill", "none");
+    circle.set("stroke", colour);
+    circle.set("stroke-width", line_width);
+    target.add(&circle);
+
+    let arm = radius * 1.5;
+    let mut data = path::Data::new();
+    data.move_to(Point::new(centre.x - arm, centre.y))
+        .line_to(Point::new(centre.x + arm, centre.y))
+        .move_to(Point::new(centre.x, centre.y - arm))
+        .line_to(Point::new(centre.x, centre.y + arm));
+    let mut cross = data.to_path();
+    cross.set("stroke", colour);
+    cross.set("stroke-width", line_width);
+    target.add(&cross);
+
+    target
+}
+
+/// Round to a tenth of a millimetre for the slug
+fn round(mm: f64) -> f64 {
+    (mm * 10.0).round() / 10.0
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{apply, create_document, PrintMarks};
+    use crate::length::Length;
+    use crate::page::{Borders, Page};
+    use crate::{shapes, Element};
+
+    fn count(el: &Element, class: &str) -> usize {
+        let own = usize::from(el.get("class").as_deref() == Some(class));
+        own + el.elements().map(|e| count(e, class)).sum::<usize>()
+    }
+
+    #[test]
+    fn media_size() {
+        let page = Page::letter(96).with_bleed(Length::inches(0.125)).unwrap();
+        let doc = create_document(&page, &PrintMarks::new("job", "today")).unwrap();
+        assert_eq!(doc.get("viewBox").unwrap(), "-60, -60, 936, 1176");
+        assert_eq!(doc.get("width").unwrap(), "247.65mm");
+    }
+
+    #[test]
+    fn marks_drawn() {
+        let page = Page::A4(300).with_bleed(Length::mm(3.0)).unwrap();
+        let mut marks = PrintMarks::new("Pattern booklet", "2024-05-01");
+        let doc = create_document(&page, &marks).unwrap();
+        assert_eq!(count(&doc, "crop-marks"), 1);
+        assert_eq!(count(&doc, "registration-mark"), 4);
+        assert_eq!(count(&doc, "colour-bars"), 0);
+        assert!(doc
+            .to_pretty_string()
+            .contains("Pattern booklet | 2024-05-01 | trim 210 x 297 mm | bleed 3 mm"));
+
+        marks.colour_bars = true;
+        marks.registration = false;
+        let doc = create_document(&page, &marks).unwrap();
+        assert_eq!(count(&doc, "registration-mark"), 0);
+        assert_eq!(count(&doc, "colour-bars"), 1);
+
+        marks.slug = Length::zero();
+        let doc = create_document(&page, &marks).unwrap();
+        assert_eq!(doc.elements().next().unwrap().children.len(), 0);
+
+        marks.slug = Length::percent(5.0);
+        assert!(create_document(&page, &marks).is_err());
+    }
+
+    #[test]
+    fn colour_bars_clear_of_marks() {
+        for width in [40.0, 100.0, 300.0] {
+            let page = Page::new(
+                Length::mm(width),
+                Length::mm(50.0),
+                96,
+                Borders::even(0.0, 96),
+            )
+            .unwrap()
+            .with_bleed(Length::mm(3.0))
+            .unwrap();
+            let mut marks = PrintMarks::new("job", "today");
+            marks.colour_bars = true;
+            let doc = create_document(&page, &marks).unwrap();
+            let group = doc.elements().next().unwrap();
+
+            let top = group
+                .elements()
+                .filter(|e| e.get("class").as_deref() == Some("registration-mark"))
+                .map(|e| e.elements().next().unwrap())
+                .find(|c| c.get("cy").unwrap().parse::<f64>().unwrap() < 0.0)
+                .unwrap();
+            let radius: f64 = top.get("r").unwrap().parse().unwrap();
+            let target_left = page.width / 2.0 - radius * 1.5;
+
+            let bars = group
+                .elements()
+                .find(|e| e.get("class").as_deref() == Some("colour-bars"))
+                .unwrap();
+            for patch in bars.elements() {
+                let x: f64 = patch.get("x").unwrap().parse().unwrap();
+                let size: f64 = patch.get("width").unwrap().parse().unwrap();
+                assert!(x > 0.0, "{width}mm page, patch at {x}");
+                assert!(x + size < target_left, "{width}mm page, patch at {x}");
+            }
+        }
+    }
+
+    #[test]
+    fn apply_keeps_trim_coordinates() {
+        let page = Page::A5(96).with_bleed(Length::mm(3.0)).unwrap();
+        let mut doc = crate::create_document(&page);
+        doc.add(&shapes::circle(page.center(), 20));
+
+        let printed = apply(&doc, &page, &PrintMarks::new("job", "today")).unwrap();
+        let content = printed.elements().last().unwrap();
+        assert_eq!(content.get("clip-path").unwrap(), "url(#bleed-box)");
+        let circle = content.elements().next().unwrap();
+        assert_eq!(circle.get("cx").unwrap(), page.center().x.to_string());
+    }
+}
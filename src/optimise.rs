@@ -0,0 +1,973 @@
+//! Make documents smaller without changing how they look, along the lines of svgo
+//!
+//! ```
+//! use esvg::optimise::{optimise, Options};
+//! use esvg::read::parse_string;
+//!
+//! let mut doc = parse_string(
+//!     r#"<svg xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape">
+//!         <g><g /></g>
+//!         <line x1="0.00001" y1="0" x2="10" y2="10.12345" stroke="red" inkscape:label="diagonal" />
+//!     </svg>"#
+//!         .to_string(),
+//! )
+//! .unwrap();
+//! let report = optimise(&mut doc, &Options::default()).unwrap();
+//! assert_eq!(doc.to_string(), "<svg>\n<path d=\"M0 0 10 10.123\" stroke=\"red\" />\n</svg>");
+//! assert!(report.bytes_after < report.bytes_before);
+//! ```
+use std::collections::HashMap;
+use std::fmt;
+
+use polygonical::point::Point;
+
+use crate::error::Error;
+use crate::page::number_list;
+use crate::path;
+use crate::value::{self, number_length, NumberFormat};
+use crate::{Element, Node};
+
+/// Presentation attributes children inherit, so they can be moved from a group on to its only child
+const INHERITED: [&str; 22] = [
+    "fill",
+    "fill-opacity",
+    "fill-rule",
+    "stroke",
+    "stroke-width",
+    "stroke-opacity",
+    "stroke-linecap",
+    "stroke-linejoin",
+    "stroke-miterlimit",
+    "stroke-dasharray",
+    "stroke-dashoffset",
+    "color",
+    "visibility",
+    "display",
+    "clip-rule",
+    "font-family",
+    "font-size",
+    "font-style",
+    "font-weight",
+    "text-anchor",
+    "letter-spacing",
+    "word-spacing",
+];
+
+/// Inherited properties that decide whether shapes can be converted or merged
+const PAINT: [&str; 9] = [
+    "fill",
+    "fill-opacity",
+    "stroke",
+    "stroke-opacity",
+    "stroke-width",
+    "color",
+    "marker-start",
+    "marker-mid",
+    "marker-end",
+];
+
+const MARKERS: [&str; 3] = ["marker-start", "marker-mid", "marker-end"];
+
+/// Elements that could refer to ids in ways that can't be rewritten, documents with these keep their ids
+const ID_USERS: [&str; 6] = [
+    "style",
+    "script",
+    "animate",
+    "animateMotion",
+    "animateTransform",
+    "set",
+];
+
+/// Which passes to run, all of them by default
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    /// Take out sodipodi and inkscape elements and attributes, and metadata elements
+    pub remove_metadata: bool,
+    /// Rewrite numbers in geometry attributes and path data in this format, usually rounding them
+    pub numbers: Option<NumberFormat>,
+    /// Turn rectangles, lines, polylines, and polygons into paths when that is shorter, and round ellipses into
+    /// circles
+    pub convert_shapes: bool,
+    /// Take out groups and defs with nothing in them
+    pub remove_empty: bool,
+    /// Replace groups that don't do anything with their children, and move the attributes of groups with one child on
+    /// to the child
+    pub collapse_groups: bool,
+    /// Join paths next to each other with the same attributes into one path, where that doesn't change how they look
+    pub merge_paths: bool,
+    /// Rename ids that are referred to as short as possible and take out those that aren't
+    pub shorten_ids: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            remove_metadata: true,
+            numbers: Some(NumberFormat {
+                drop_leading_zero: true,
+                ..NumberFormat::decimals(3)
+            }),
+            convert_shapes: true,
+            remove_empty: true,
+            collapse_groups: true,
+            merge_paths: true,
+            shorten_ids: true,
+        }
+    }
+}
+
+impl Options {
+    /// No passes at all, to turn on just the ones that are wanted
+    ///
+    /// ```
+    /// use esvg::optimise::Options;
+    ///
+    /// let options = Options { merge_paths: true, ..Options::none() };
+    /// assert!(!options.shorten_ids);
+    /// ```
+    pub fn none() -> Self {
+        Options {
+            remove_metadata: false,
+            numbers: None,
+            convert_shapes: false,
+            remove_empty: false,
+            collapse_groups: false,
+            merge_paths: false,
+            shorten_ids: false,
+        }
+    }
+}
+
+/// How much smaller the document got, measured as it would be saved
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Report {
+    pub bytes_before: usize,
+    pub bytes_after: usize,
+    /// Bytes saved by each pass that ran, in the order they ran
+    pub passes: Vec<(&'static str, usize)>,
+}
+
+impl Report {
+    /// The fraction of the document that was removed
+    pub fn saving(&self) -> f64 {
+        if self.bytes_before > 0 {
+            1.0 - self.bytes_after as f64 / self.bytes_before as f64
+        } else {
+            0.0
+        }
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            formatter,
+            "{} -> {} bytes ({:.0}% smaller)",
+            self.bytes_before,
+            self.bytes_after,
+            self.saving() * 100.0
+        )?;
+        for (pass, saved) in &self.passes {
+            write!(formatter, "\n  {pass}: {saved} bytes")?;
+        }
+        Ok(())
+    }
+}
+
+/// Run the passes turned on in the options over a document.
+///
+/// Everything is done so the document looks the same afterwards, so passes skip anything they can't be sure about.
+/// Paths are only merged when they have no fill or don't overlap, aren't see through, have no markers, and aren't
+/// painted with gradients or patterns. Shapes with markers aren't converted to paths. Ids are left alone in
+/// documents with style sheets, scripts, or animations as those can refer to ids in ways that can't be followed.
+pub fn optimise(doc: &mut Element, options: &Options) -> Result<Report, Error> {
+    let size = |doc: &Element| doc.to_pretty_string().len();
+    let mut report = Report {
+        bytes_before: size(doc),
+        ..Default::default()
+    };
+
+    let mut before = report.bytes_before;
+    let mut run = |doc: &mut Element,
+                   name: &'static str,
+                   pass: &dyn Fn(&mut Element) -> Result<(), Error>|
+     -> Result<(), Error> {
+        pass(doc)?;
+        let after = size(doc);
+        report.passes.push((name, before.saturating_sub(after)));
+        before = after;
+        Ok(())
+    };
+    let format = options.numbers.unwrap_or_default();
+    if options.remove_metadata {
+        run(doc, "remove metadata", &|doc| {
+            remove_metadata(doc);
+            Ok(())
+        })?;
+    }
+    if let Some(format) = &options.numbers {
+        run(doc, "round numbers", &|doc| format_numbers(doc, format))?;
+    }
+    if options.convert_shapes {
+        run(doc, "convert shapes", &|doc| {
+            convert_shapes(doc, &format, &HashMap::new());
+            Ok(())
+        })?;
+    }
+    if options.remove_empty {
+        run(doc, "remove empty", &|doc| {
+            remove_empty(doc);
+            Ok(())
+        })?;
+    }
+    if options.collapse_groups {
+        run(doc, "collapse groups", &|doc| {
+            collapse_groups(doc);
+            Ok(())
+        })?;
+    }
+    if options.merge_paths {
+        run(doc, "merge paths", &|doc| {
+            merge_paths(doc, &HashMap::new());
+            Ok(())
+        })?;
+    }
+    if options.shorten_ids {
+        run(doc, "shorten ids", &|doc| {
+            shorten_ids(doc);
+            Ok(())
+        })?;
+    }
+    report.bytes_after = before;
+    Ok(report)
+}
+
+fn is_editor(name: &str) -> bool {
+    name.starts_with("sodipodi:") || name.starts_with("inkscape:")
+}
+
+fn remove_metadata(element: &mut Element) {
+    element.children.retain(|c| match c {
+        Node::Element(e) => e.name != "metadata" && !is_editor(&e.name),
+        _ => true,
+    });
+    element
+        .attributes
+        .retain(|k, _| !is_editor(k) && k != "xmlns:sodipodi" && k != "xmlns:inkscape");
+    if let Some(style) = element.get("style") {
+        let kept: Vec<&str> = style
+            .split(';')
+            .filter(|d| !d.trim().is_empty() && !d.trim().starts_with("-inkscape-"))
+            .collect();
+        if kept.is_empty() {
+            element.remove("style");
+        } else {
+            element.set("style", kept.join(";"));
+        }
+    }
+    for child in element.elements_mut() {
+        remove_metadata(child);
+    }
+}
+
+/// Write every number in the document in the format
+fn format_numbers(element: &mut Element, format: &NumberFormat) -> Result<(), Error> {
+    for (name, value) in element.attributes.iter_mut() {
+        *value = value.format_with(name, format)?;
+    }
+    for child in element.elements_mut() {
+        format_numbers(child, format)?;
+    }
+    Ok(())
+}
+
+/// A plain number attribute, missing ones are zero
+fn plain(element: &Element, name: &str) -> Option<f64> {
+    match element.get(name) {
+        Some(value) => value.trim().parse().ok().filter(|n: &f64| n.is_finite()),
+        None => Some(0.0),
+    }
+}
+
+/// The path data for a shape, if it can be written as one
+fn shape_data(element: &Element) -> Option<String> {
+    let points = |close: bool| -> Option<String> {
+        let numbers = number_list(&element.get("points")?).ok()?;
+        if numbers.len() < 4 || numbers.len() % 2 == 1 {
+            return None;
+        }
+        let list: Vec<String> = numbers.iter().map(|n| n.to_string()).collect();
+        Some(format!(
+            "M{}{}",
+            list.join(" "),
+            if close { "z" } else { "" }
+        ))
+    };
+
+    match element.name.as_str() {
+        "rect" => {
+            if plain(element, "rx")? != 0.0 || plain(element, "ry")? != 0.0 {
+                return None;
+            }
+            let (x, y) = (plain(element, "x")?, plain(element, "y")?);
+            let (width, height) = (plain(element, "width")?, plain(element, "height")?);
+            if width <= 0.0 || height <= 0.0 {
+                return None;
+            }
+            Some(format!("M{x} {y}H{}V{}H{x}z", x + width, y + height))
+        }
+        "line" => Some(format!(
+            "M{} {} {} {}",
+            plain(element, "x1")?,
+            plain(element, "y1")?,
+            plain(element, "x2")?,
+            plain(element, "y2")?
+        )),
+        "polyline" => points(false),
+        "polygon" => points(true),
+        _ => None,
+    }
+}
+
+/// Attributes shapes have that paths don't need
+const GEOMETRY: [&str; 10] = [
+    "x", "y", "width", "height", "rx", "ry", "x1", "y1", "x2", "y2",
+];
+
+/// The paint properties in effect on an element, given the ones in effect on its parent
+fn in_effect(
+    element: &Element,
+    parent: &HashMap<&'static str, String>,
+) -> HashMap<&'static str, String> {
+    let mut result = parent.clone();
+    if let Some(marker) = element.property("marker").filter(|v| v != "inherit") {
+        for name in MARKERS {
+            result.insert(name, marker.clone());
+        }
+    }
+    for name in PAINT {
+        if let Some(value) = element.property(name).filter(|v| v != "inherit") {
+            result.insert(name, value);
+        }
+    }
+    result
+}
+
+/// Whether markers are drawn on the shapes with these properties
+fn has_markers(paint: &HashMap<&'static str, String>) -> bool {
+    MARKERS
+        .iter()
+        .any(|m| paint.get(m).is_some_and(|v| v != "none"))
+}
+
+/// Whether a colour can be seen through, either with an alpha or by being transparent
+fn translucent(colour: &str) -> bool {
+    let colour = colour.trim().to_lowercase();
+    if let Some(hex) = colour.strip_prefix('#') {
+        return match hex.len() {
+            4 => !hex.ends_with('f'),
+            8 => !hex.ends_with("ff"),
+            _ => false,
+        };
+    }
+    if colour == "transparent" {
+        return true;
+    }
+    // rgba(1, 2, 3, 0.5), hsla(...), and rgb(1 2 3 / 50%)
+    let Some(arguments) = colour
+        .split_once('(')
+        .and_then(|(_, rest)| rest.strip_suffix(')'))
+    else {
+        return false;
+    };
+    let parts: Vec<&str> = arguments
+        .split([',', '/', ' '])
+        .filter(|p| !p.is_empty())
+        .collect();
+    if parts.len() < 4 {
+        return false;
+    }
+    let alpha = parts[parts.len() - 1];
+    let alpha = match alpha.strip_suffix('%') {
+        Some(percent) => percent.parse::<f64>().map(|p| p / 100.0),
+        None => alpha.parse::<f64>(),
+    };
+    alpha != Ok(1.0)
+}
+
+fn convert_shapes(
+    element: &mut Element,
+    format: &NumberFormat,
+    inherited: &HashMap<&'static str, String>,
+) {
+    let here = in_effect(element, inherited);
+    for child in element.elements_mut() {
+        convert_shapes(child, format, &here);
+
+        // rectangles don't get markers but paths do
+        let data = shape_data(child)
+            .filter(|_| !has_markers(&in_effect(child, &here)))
+            .and_then(|d| value::format_numbers(&d, format, true).ok());
+        if let Some(d) = data {
+            let mut path = Element::new("path");
+            for (k, v) in &child.attributes {
+                if !GEOMETRY.contains(&k.as_str()) && k != "points" {
+                    path.attributes.insert(k.clone(), v.clone());
+                }
+            }
+            path.set("d", d);
+            if path.to_string().len() < child.to_string().len() {
+                *child = path;
+            }
+        } else if child.name == "ellipse"
+            && child.get("rx").is_some()
+            && child.get("rx") == child.get("ry")
+        {
+            child.name = "circle".to_string();
+            let r = child.remove("rx").unwrap();
+            child.remove("ry");
+            child.set("r", r);
+        }
+    }
+}
+
+fn remove_empty(element: &mut Element) {
+    for child in element.elements_mut() {
+        remove_empty(child);
+    }
+    element.children.retain(|c| match c {
+        Node::Element(e) => {
+            !((e.name == "g" || e.name == "defs")
+                && e.children.is_empty()
+                && e.get("filter").is_none())
+        }
+        _ => true,
+    });
+}
+
+fn collapse_groups(element: &mut Element) {
+    for child in element.elements_mut() {
+        collapse_groups(child);
+    }
+    if element.name == "switch" {
+        return;
+    }
+
+    let mut children = vec![];
+    for child in element.children.drain(..) {
+        match child {
+            Node::Element(group) if group.name == "g" => {
+                if group.attributes.is_empty() {
+                    children.extend(group.children);
+                    continue;
+                }
+                if let [Node::Element(only)] = &group.children[..] {
+                    let movable = group.attributes.keys().all(|k| {
+                        k == "transform"
+                            || (INHERITED.contains(&k.as_str())
+                                && !only.attributes.contains_key(k)
+                                && only.property(k).is_none())
+                    });
+                    if movable {
+                        let mut only = only.clone();
+                        for (k, v) in &group.attributes {
+                            if k == "transform" {
+                                let transform = match only.get("transform") {
+                                    Some(inner) => format!("{} {inner}", v.to_string_bare()),
+                                    None => v.to_string_bare(),
+                                };
+                                only.set("transform", transform);
+                            } else {
+                                only.attributes.insert(k.clone(), v.clone());
+                            }
+                        }
+                        children.push(Node::Element(only));
+                        continue;
+                    }
+                }
+                children.push(Node::Element(group));
+            }
+            other => children.push(other),
+        }
+    }
+    element.children = children;
+}
+
+/// The corners of the box around a path, or none if the path can't be read
+fn bounds(d: &str) -> Option<(Point, Point)> {
+    let lines = path::flatten(&path::parse(d).ok()?, 0.5);
+    let mut points = lines.iter().flatten();
+    let first = *points.next()?;
+    Some(points.fold((first, first), |(low, high), p| (low.min(p), high.max(p))))
+}
+
+/// Whether two paths can be drawn as one without looking any different, given the paint properties in effect on
+/// their parent
+fn can_merge(a: &Element, b: &Element, inherited: &HashMap<&'static str, String>) -> bool {
+    if a.name != "path" || b.name != "path" {
+        return false;
+    }
+    let others = |e: &Element| {
+        let mut attributes: Vec<(String, String)> = e
+            .attributes
+            .iter()
+            .filter(|(k, _)| *k != "d")
+            .map(|(k, v)| (k.clone(), v.to_string_bare()))
+            .collect();
+        attributes.sort();
+        attributes
+    };
+    if others(a) != others(b)
+        || a.get("id").is_some()
+        || a.get("d").is_none()
+        || b.get("d").is_none()
+    {
+        return false;
+    }
+    // markers would be drawn where the paths join
+    let paint = in_effect(a, inherited);
+    if has_markers(&paint) {
+        return false;
+    }
+    // overlapping see through paths only get drawn once
+    if a.property("opacity")
+        .is_some_and(|o| o.parse::<f64>() != Ok(1.0))
+    {
+        return false;
+    }
+    for name in ["fill-opacity", "stroke-opacity"] {
+        if paint.get(name).is_some_and(|o| o.parse::<f64>() != Ok(1.0)) {
+            return false;
+        }
+    }
+    let colour = |name: &str| match paint.get(name).map(|c| c.as_str()) {
+        Some("currentColor") => paint.get("color").cloned(),
+        other => other.map(|c| c.to_string()),
+    };
+    for name in ["fill", "stroke"] {
+        // gradients and patterns are sized to the shape they are on, which merging changes
+        if colour(name).is_some_and(|c| translucent(&c) || c.starts_with("url(")) {
+            return false;
+        }
+    }
+    if colour("fill").as_deref() == Some("none") {
+        return true;
+    }
+
+    // filled paths can't overlap or the fills would combine differently, or one could cover the other's stroke
+    let margin = paint
+        .get("stroke-width")
+        .and_then(|w| w.parse::<f64>().ok())
+        .unwrap_or(1.0);
+    match (bounds(&a.get("d").unwrap()), bounds(&b.get("d").unwrap())) {
+        (Some((a_low, a_high)), Some((b_low, b_high))) => {
+            a_high.x + margin < b_low.x
+                || b_high.x + margin < a_low.x
+                || a_high.y + margin < b_low.y
+                || b_high.y + margin < a_low.y
+        }
+        _ => false,
+    }
+}
+
+/// Path data that still means the same on the end of another path. A path starting with a relative move is
+/// measured from the origin, which it wouldn't be after another path.
+fn absolute_start(d: &str) -> String {
+    let trimmed = d.trim_start();
+    let Some(rest) = trimmed.strip_prefix('m') else {
+        return trimmed.to_string();
+    };
+    let bytes = rest.as_bytes();
+    let mut i = 0;
+    let mut numbers = vec![];
+    while numbers.len() < 2 {
+        while i < bytes.len() && (bytes[i].is_ascii_whitespace() || bytes[i] == b',') {
+            i += 1;
+        }
+        let end = number_length(bytes, i);
+        if end == i {
+            return trimmed.to_string();
+        }
+        numbers.push(&rest[i..end]);
+        i = end;
+    }
+    let after = rest[i..].trim_start_matches(|c: char| c.is_ascii_whitespace() || c == ',');
+    // any more pairs after a move are relative lines
+    let line =
+        if after.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '+' || c == '.') {
+            "l"
+        } else {
+            ""
+        };
+    format!("M{} {}{line}{after}", numbers[0], numbers[1])
+}
+
+fn merge_paths(element: &mut Element, inherited: &HashMap<&'static str, String>) {
+    let here = in_effect(element, inherited);
+    for child in element.elements_mut() {
+        merge_paths(child, &here);
+    }
+
+    let mut children: Vec<Node> = vec![];
+    for child in element.children.drain(..) {
+        if let (Some(Node::Element(last)), Node::Element(next)) = (children.last_mut(), &child) {
+            if can_merge(last, next, &here) {
+                let d = format!(
+                    "{} {}",
+                    last.get("d").unwrap().trim_end(),
+                    absolute_start(&next.get("d").unwrap())
+                );
+                last.set("d", d);
+                continue;
+            }
+        }
+        children.push(child);
+    }
+    element.children = children;
+}
+
+/// The nth short id, a to z then A to Z then two letters and so on
+fn short_id(mut n: usize) -> String {
+    const LETTERS: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+    let mut id = vec![];
+    loop {
+        id.push(LETTERS[n % LETTERS.len()]);
+        n /= LETTERS.len();
+        if n == 0 {
+            break;
+        }
+        n -= 1;
+    }
+    id.reverse();
+    String::from_utf8(id).unwrap()
+}
+
+/// Where the `url(#id)` references in a value are, as the byte range of the whole url and the id
+fn url_references(value: &str) -> Vec<(usize, usize, String)> {
+    let mut result = vec![];
+    let mut from = 0;
+    while let Some(start) = value[from..].find("url(").map(|s| s + from) {
+        let Some(end) = value[start..].find(')').map(|e| e + start) else {
+            break;
+        };
+        let inside = value[start + 4..end]
+            .trim()
+            .trim_matches(|c| c == '\'' || c == '"');
+        if let Some(id) = inside.strip_prefix('#') {
+            result.push((start, end + 1, id.to_string()));
+        }
+        from = end + 1;
+    }
+    result
+}
+
+fn is_href(name: &str) -> bool {
+    name == "href" || name == "xlink:href"
+}
+
+fn uses_ids(element: &Element) -> bool {
+    ID_USERS.contains(&element.name.as_str()) || element.elements().any(uses_ids)
+}
+
+fn shorten_ids(doc: &mut Element) {
+    if uses_ids(doc) {
+        return;
+    }
+
+    fn referenced(element: &Element, found: &mut Vec<String>) {
+        for (k, v) in &element.attributes {
+            let value = v.to_string_bare();
+            if is_href(k) {
+                if let Some(id) = value.trim().strip_prefix('#') {
+                    found.push(id.to_string());
+                }
+            }
+            found.extend(url_references(&value).into_iter().map(|(_, _, id)| id));
+        }
+        for child in element.elements() {
+            referenced(child, found);
+        }
+    }
+    let mut references = vec![];
+    referenced(doc, &mut references);
+
+    fn defined(element: &Element, found: &mut Vec<String>) {
+        if let Some(id) = element.get("id") {
+            found.push(id);
+        }
+        for child in element.elements() {
+            defined(child, found);
+        }
+    }
+    let mut ids = vec![];
+    defined(doc, &mut ids);
+
+    let mut names = HashMap::new();
+    for id in ids {
+        if references.contains(&id) && !names.contains_key(&id) {
+            let short = short_id(names.len());
+            names.insert(id, short);
+        }
+    }
+
+    fn rename(element: &mut Element, names: &HashMap<String, String>) {
+        let keys: Vec<String> = element.attributes.keys().cloned().collect();
+        for key in keys {
+            let value = element.get(&key).unwrap();
+            if key == "id" {
+                match names.get(&value) {
+                    Some(short) => _ = element.set("id", short.as_str()),
+                    None => _ = element.remove("id"),
+                }
+                continue;
+            }
+            if is_href(&key) {
+                if let Some(short) = value.trim().strip_prefix('#').and_then(|id| names.get(id)) {
+                    element.set(key, format!("#{short}"));
+                }
+                continue;
+            }
+            let mut rewritten = value.clone();
+            for (start, end, id) in url_references(&value).into_iter().rev() {
+                if let Some(short) = names.get(&id) {
+                    rewritten.replace_range(start..end, &format!("url(#{short})"));
+                }
+            }
+            if rewritten != value {
+                element.set(key, rewritten);
+            }
+        }
+        for child in element.elements_mut() {
+            rename(child, names);
+        }
+    }
+    rename(doc, &names);
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{absolute_start, optimise, short_id, Options};
+    use crate::read::parse_string;
+    use crate::Element;
+
+    fn run(text: &str, options: Options) -> Element {
+        let mut doc = parse_string(text.to_string()).unwrap();
+        optimise(&mut doc, &options).unwrap();
+        doc
+    }
+
+    #[test]
+    fn small_pieces() {
+        assert_eq!(absolute_start("m10 10 5 5z"), "M10 10l5 5z");
+        assert_eq!(absolute_start("M1 2"), "M1 2");
+        assert_eq!(
+            [0, 25, 26, 51, 52, 53].map(short_id),
+            ["a", "z", "A", "Z", "aa", "ab"]
+        );
+    }
+
+    #[test]
+    fn shapes_and_metadata() {
+        let doc = run(
+            r#"<svg xmlns:sodipodi="s" xmlns:inkscape="i" inkscape:version="1.2">
+                <sodipodi:namedview />
+                <metadata><title>x</title></metadata>
+                <rect x="10" y="10" width="20" height="5" style="fill:red;-inkscape-font-specification:Sans" />
+                <rect x="1" y="1" width="2" height="2" rx="1" />
+                <polygon points="0,0 10,0 10,10" />
+                <ellipse cx="5" cy="5" rx="2" ry="2" />
+            </svg>"#,
+            Options::default(),
+        );
+        assert_eq!(
+            doc.to_string(),
+            "<svg>\n<path d=\"M10 10H30V15H10z\" style=\"fill:red\" />\n\
+             <rect height=\"2\" rx=\"1\" width=\"2\" x=\"1\" y=\"1\" />\n\
+             <path d=\"M0 0 10 0 10 10z\" />\n\
+             <circle cx=\"5\" cy=\"5\" r=\"2\" />\n</svg>"
+        );
+    }
+
+    #[test]
+    fn groups() {
+        let doc = run(
+            r#"<svg>
+                <g><g transform="scale(2)" fill="red"><circle r="1" transform="translate(1 1)" /></g></g>
+                <g fill="red"><circle r="1" fill="blue" /></g>
+                <g opacity="0.5"><circle r="1" /></g>
+                <defs />
+                <g><g /></g>
+            </svg>"#,
+            Options {
+                collapse_groups: true,
+                remove_empty: true,
+                ..Options::none()
+            },
+        );
+        assert_eq!(
+            doc.to_string(),
+            "<svg>\n<circle fill=\"red\" r=\"1\" transform=\"scale(2) translate(1 1)\" />\n\
+             <g fill=\"red\">\n<circle fill=\"blue\" r=\"1\" />\n</g>\n\
+             <g opacity=\"0.5\">\n<circle r=\"1\" />\n</g>\n</svg>"
+        );
+    }
+
+    #[test]
+    fn merging() {
+        let doc = run(
+            r#"<svg>
+                <path d="M0 0 L10 10" stroke="red" fill="none" />
+                <path d="m5 0 5 5" stroke="red" fill="none" />
+                <path d="M0 0 h10 v10 z" fill="blue" />
+                <path d="M5 5 h10 v10 z" fill="blue" />
+                <path d="M50 50 h10 v10 z" fill="blue" />
+                <path d="M0 0 L1 1" stroke="red" fill="none" opacity="0.5" />
+                <path d="M0 0 L1 1" stroke="red" fill="none" opacity="0.5" />
+            </svg>"#,
+            Options {
+                merge_paths: true,
+                ..Options::none()
+            },
+        );
+        let ds: Vec<String> = doc.elements().map(|e| e.get("d").unwrap()).collect();
+        assert_eq!(
+            ds,
+            [
+                "M0 0 L10 10 M5 0l5 5",
+                "M0 0 h10 v10 z",
+                "M5 5 h10 v10 z M50 50 h10 v10 z",
+                "M0 0 L1 1",
+                "M0 0 L1 1",
+            ]
+        );
+    }
+
+    #[test]
+    fn inherited_paint() {
+        let merge = Options {
+            merge_paths: true,
+            ..Options::none()
+        };
+        let count = |doc: &Element| doc.elements().next().unwrap().elements().count();
+        let pair = |group: &str, path: &str| {
+            format!(
+                r#"<svg><g {group}><path d="M0 0 L10 10" {path} /><path d="M0 10 L10 0" {path} /></g></svg>"#
+            )
+        };
+
+        // overlapping translucent strokes, from the group, a colour, or a style with a trailing semicolon
+        let doc = run(
+            &pair(r#"stroke-opacity="0.5""#, r#"stroke="red" fill="none""#),
+            merge.clone(),
+        );
+        assert_eq!(count(&doc), 2);
+        let doc = run(
+            &pair("", r##"stroke="#ff000080" fill="none""##),
+            merge.clone(),
+        );
+        assert_eq!(count(&doc), 2);
+        let doc = run(
+            &pair(
+                r#"color="rgba(0, 0, 0, 0.5)""#,
+                r#"stroke="currentColor" fill="none""#,
+            ),
+            merge.clone(),
+        );
+        assert_eq!(count(&doc), 2);
+        let doc = run(
+            &pair("", r#"stroke="red" fill="none" style="opacity:0.5;""#),
+            merge.clone(),
+        );
+        assert_eq!(count(&doc), 2);
+
+        // markers from the group would be lost where the paths join
+        let doc = run(
+            &pair(r#"marker-end="url(#m)""#, r#"stroke="red" fill="none""#),
+            merge.clone(),
+        );
+        assert_eq!(count(&doc), 2);
+        let doc = run(
+            &pair(r#"stroke="url(#g)""#, r#"fill="none""#),
+            merge.clone(),
+        );
+        assert_eq!(count(&doc), 2);
+
+        // and opaque ones still merge
+        let doc = run(&pair(r##"stroke="#ff0000ff""##, r#"fill="none""#), merge);
+        assert_eq!(count(&doc), 1);
+
+        // rectangles don't get markers but paths would
+        let shapes = Options {
+            convert_shapes: true,
+            ..Options::none()
+        };
+        let rect = r#"<rect x="0" y="0" width="10" height="10" />"#;
+        let doc = run(
+            &format!(r#"<svg><g marker-start="url(#m)">{rect}</g></svg>"#),
+            shapes.clone(),
+        );
+        assert_eq!(
+            doc.elements()
+                .next()
+                .unwrap()
+                .elements()
+                .next()
+                .unwrap()
+                .name,
+            "rect"
+        );
+        let doc = run(
+            &format!(r#"<svg><g marker-start="none">{rect}</g></svg>"#),
+            shapes,
+        );
+        assert_eq!(
+            doc.elements()
+                .next()
+                .unwrap()
+                .elements()
+                .next()
+                .unwrap()
+                .name,
+            "path"
+        );
+    }
+
+    #[test]
+    fn ids() {
+        let mut doc = parse_string(
+            r##"<svg xmlns:xlink="http://www.w3.org/1999/xlink">
+                <defs><linearGradient id="gradient-one" /><clipPath id="unused" /></defs>
+                <rect id="box" fill="url(#gradient-one)" style="clip-path:url('#gradient-one')" />
+                <use xlink:href="#box" />
+            </svg>"##
+                .to_string(),
+        )
+        .unwrap();
+        let report = optimise(
+            &mut doc,
+            &Options {
+                shorten_ids: true,
+                ..Options::none()
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            doc.to_string(),
+            "<svg xmlns:xlink=\"http://www.w3.org/1999/xlink\">\n\
+             <defs>\n<linearGradient id=\"a\" />\n<clipPath />\n</defs>\n\
+             <rect fill=\"url(#a)\" id=\"b\" style=\"clip-path:url(#a)\" />\n\
+             <use xlink:href=\"#b\" />\n</svg>"
+        );
+        assert_eq!(report.passes.len(), 1);
+        assert_eq!(report.bytes_before - report.bytes_after, report.passes[0].1);
+        assert!(report.to_string().contains("shorten ids: "));
+
+        // a style sheet could use any of them
+        let doc = run(
+            r#"<svg><style>#box { fill: red }</style><rect id="box" /></svg>"#,
+            Options::default(),
+        );
+        assert_eq!(doc.elements().nth(1).unwrap().get("id").unwrap(), "box");
+    }
+}
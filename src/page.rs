@@ -1,16 +1,22 @@
 //! Things to do with the document being created, its size, its borders, etc
+use std::fmt;
+use std::str::FromStr;
+
 use polygonical::point::Point;
 
 use crate::convert;
 use crate::error::Error;
+use crate::length::{Length, Unit, CSS_DPI};
+use crate::Element;
 
 /// Describe borders around the edge of a page. These don't prevent you drawing off the side of the page
 /// they are here to help you keep track of them, they can freely be set to zero.
+#[derive(Debug, Clone, PartialEq)]
 pub struct Borders {
-    pub top: i32,
-    pub bottom: i32,
-    pub left: i32,
-    pub right: i32,
+    pub top: f64,
+    pub bottom: f64,
+    pub left: f64,
+    pub right: f64,
 }
 
 impl Borders {
@@ -29,6 +35,17 @@ impl Borders {
         }
     }
 
+    /// Create a border of the given length on all four sides, relative lengths are an error
+    pub fn uniform(size: Length, dpi: i32) -> Result<Borders, Error> {
+        let px = size.to_pixels(dpi)?;
+        Ok(Borders {
+            top: px,
+            bottom: px,
+            left: px,
+            right: px,
+        })
+    }
+
     pub fn rotate(&self) -> Borders {
         Borders {
             top: self.left,
@@ -42,7 +59,7 @@ impl Borders {
 macro_rules! paper_size {
     // There isn't a way to concatenate identifiers in function names so we have to provide both.
     // this sucks, but the long_name should always be equal to ${name}_with_border
-    ($($name:ident, $long_name:ident: $width:expr, $height:expr,)*) => {
+    ($($name:ident, $long_name:ident: $paper:literal,)*) => {
         $(
             #[allow(non_snake_case)]
             pub fn $name(dpi:i32) -> Page {
@@ -51,75 +68,478 @@ macro_rules! paper_size {
 
             #[allow(non_snake_case)]
             pub fn $long_name(dpi: i32, border: Borders) -> Page {
-                Page {
-                    dpi,
-                    width: convert::inches_to_pixels($width, dpi),
-                    height: convert::inches_to_pixels($height, dpi),
-
-                    borders: border,
-                }
+                PaperSize::find($paper)
+                    .expect("paper size constructors must refer to the catalogue")
+                    .to_page(dpi, border)
+                    .expect("catalogue sizes are absolute")
             }
         )*
     };
 }
 
+macro_rules! catalogue {
+    ($($name:literal: $width:literal x $height:literal $unit:ident,)*) => {
+        /// Every named paper size we know about, in portrait orientation unless the paper is normally landscape.
+        pub const PAPER_SIZES: &[PaperSize] = &[
+            $(
+                PaperSize {
+                    name: $name,
+                    width: Length { value: $width, unit: Unit::$unit },
+                    height: Length { value: $height, unit: Unit::$unit },
+                },
+            )*
+        ];
+    };
+}
+
+// Sizes are given in the units of the standard that defines them so nothing is lost to rounding
+catalogue!(
+    "A0": 841.0 x 1189.0 Mm,
+    "A1": 594.0 x 841.0 Mm,
+    "A2": 420.0 x 594.0 Mm,
+    "A3": 297.0 x 420.0 Mm,
+    "A4": 210.0 x 297.0 Mm,
+    "A5": 148.0 x 210.0 Mm,
+    "A6": 105.0 x 148.0 Mm,
+    "A7": 74.0 x 105.0 Mm,
+    "A8": 52.0 x 74.0 Mm,
+    "A9": 37.0 x 52.0 Mm,
+    "A10": 26.0 x 37.0 Mm,
+    "B0": 1000.0 x 1414.0 Mm,
+    "B1": 707.0 x 1000.0 Mm,
+    "B2": 500.0 x 707.0 Mm,
+    "B3": 353.0 x 500.0 Mm,
+    "B4": 250.0 x 353.0 Mm,
+    "B5": 176.0 x 250.0 Mm,
+    "B6": 125.0 x 176.0 Mm,
+    "B7": 88.0 x 125.0 Mm,
+    "B8": 62.0 x 88.0 Mm,
+    "B9": 44.0 x 62.0 Mm,
+    "B10": 31.0 x 44.0 Mm,
+    "C0": 917.0 x 1297.0 Mm,
+    "C1": 648.0 x 917.0 Mm,
+    "C2": 458.0 x 648.0 Mm,
+    "C3": 324.0 x 458.0 Mm,
+    "C4": 229.0 x 324.0 Mm,
+    "C5": 162.0 x 229.0 Mm,
+    "C6": 114.0 x 162.0 Mm,
+    "C7": 81.0 x 114.0 Mm,
+    "C8": 57.0 x 81.0 Mm,
+    "C9": 40.0 x 57.0 Mm,
+    "C10": 28.0 x 40.0 Mm,
+    "Letter": 8.5 x 11.0 In,
+    "Legal": 8.5 x 14.0 In,
+    "Tabloid": 11.0 x 17.0 In,
+    "Ledger": 17.0 x 11.0 In,
+    "ANSI A": 8.5 x 11.0 In,
+    "ANSI B": 11.0 x 17.0 In,
+    "ANSI C": 17.0 x 22.0 In,
+    "ANSI D": 22.0 x 34.0 In,
+    "ANSI E": 34.0 x 44.0 In,
+    "Arch A": 9.0 x 12.0 In,
+    "Arch B": 12.0 x 18.0 In,
+    "Arch C": 18.0 x 24.0 In,
+    "Arch D": 24.0 x 36.0 In,
+    "Arch E": 36.0 x 48.0 In,
+    "Photo 4x6": 4.0 x 6.0 In,
+    "Photo 5x7": 5.0 x 7.0 In,
+    "Photo 8x10": 8.0 x 10.0 In,
+    "Index card 3x5": 3.0 x 5.0 In,
+    "Index card 4x6": 4.0 x 6.0 In,
+    "Business card": 3.5 x 2.0 In,
+    "Credit card": 85.6 x 53.98 Mm,
+);
+
+/// Which way round a page is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Orientation {
+    /// Taller than it is wide
+    Portrait,
+    /// Wider than it is tall
+    Landscape,
+}
+
+/// A named paper size from [`PAPER_SIZES`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PaperSize {
+    /// The name to show people, lookups ignore case, spaces, and punctuation
+    pub name: &'static str,
+    pub width: Length,
+    pub height: Length,
+}
+
+impl PaperSize {
+    /// Look up a paper size by name, ignoring case, spaces, and punctuation. "ANSI A", "ansi-a", and "ansia" are
+    /// all the same paper.
+    pub fn find(name: &str) -> Option<&'static PaperSize> {
+        let key = normalise_name(name);
+        PAPER_SIZES.iter().find(|p| normalise_name(p.name) == key)
+    }
+
+    /// Build a page of this size, see [`Page::new`]
+    pub fn to_page(&self, dpi: i32, border: Borders) -> Result<Page, Error> {
+        Page::new(self.width, self.height, dpi, border)
+    }
+}
+
+/// Lower case and strip everything that isn't a letter or a number
+fn normalise_name(name: &str) -> String {
+    name.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Split an orientation suffix like "-landscape" off the end of a paper name
+fn split_orientation(name: &str) -> (&str, Option<Orientation>) {
+    let lower = name.to_ascii_lowercase();
+    for (suffix, orientation) in [
+        ("landscape", Orientation::Landscape),
+        ("portrait", Orientation::Portrait),
+    ] {
+        if lower.ends_with(suffix) {
+            let rest = name[..name.len() - suffix.len()]
+                .trim_end_matches(|c: char| !c.is_ascii_alphanumeric());
+            return (rest, Some(orientation));
+        }
+    }
+    (name, None)
+}
+
 /// Page is used to define the size of an svg you wish to create.
+///
+/// Pages can be written as and parsed from a spec string, see [`Page::from_spec`] for the grammar.
+#[derive(Debug, Clone, PartialEq)]
 pub struct Page {
     /// Pixels per inch for calculating conversions
     pub dpi: i32,
-    /// Width of the page in pixels
-    pub width: i32,
-    /// Height of the page in pixels
-    pub height: i32,
+    /// Width of the page in pixels, this is not rounded so it may be fractional
+    pub width: f64,
+    /// Height of the page in pixels, this is not rounded so it may be fractional
+    pub height: f64,
     /// Borders represent the padding around the edge of the page that should be left alone.
     /// Note: it is up to you to deal with this, nothing will stop you drawing over the border areas
     pub borders: Borders,
+    /// How far in pixels the drawing carries on past the edge of the page so nothing is left white if the printer
+    /// cuts slightly off. Only used when printing with [`crate::marks`], the page size is always the trimmed size.
+    pub bleed: f64,
 }
 
 impl Page {
+    /// Construct a page from a width and height in any absolute unit, relative lengths are an error.
+    ///
+    /// ```
+    /// use esvg::length::Length;
+    /// use esvg::page::{Borders, Page};
+    ///
+    /// let page = Page::new(Length::mm(2.5), Length::inches(2.0), 96, Borders::even(0.0, 96)).unwrap();
+    /// assert_eq!(page.width, 9.448818897637796);
+    /// assert_eq!(page.height, 192.0);
+    /// assert!(Page::new(Length::percent(50.0), Length::inches(2.0), 96, Borders::even(0.0, 96)).is_err());
+    /// ```
+    pub fn new(width: Length, height: Length, dpi: i32, borders: Borders) -> Result<Page, Error> {
+        Ok(Page {
+            dpi,
+            width: width.to_pixels(dpi)?,
+            height: height.to_pixels(dpi)?,
+            borders,
+            bleed: 0.0,
+        })
+    }
+
+    /// Set the bleed of this page, relative lengths are an error
+    ///
+    /// ```
+    /// use esvg::length::Length;
+    /// use esvg::page::Page;
+    ///
+    /// let page = Page::A4(96).with_bleed(Length::inches(0.125)).unwrap();
+    /// assert_eq!(page.bleed, 12.0);
+    /// ```
+    pub fn with_bleed(mut self, bleed: Length) -> Result<Page, Error> {
+        self.bleed = bleed.to_pixels(self.dpi)?;
+        Ok(self)
+    }
+
     /// Construct a page given a name for it and the dpi and margin information.
     ///
     /// The name can either be something like A3 or Letter or 200mmx200in to create a 200 millimetre by 200 inch svg
     /// Margin is always in inches. It doesn't stop you drawing over the edge of the page.
     pub fn build_page(name: &str, dpi: i32, margin: f64) -> Result<Page, Error> {
-        let border = Borders::even(margin, dpi);
-
-        // WARN: if we ever get a paper size with an x this will break. At the moment it is fine.
-        if name.contains('x') {
-            let parts: Vec<&str> = name.split('x').collect();
-            if parts.len() == 2 {
-                let width = convert::parse_length(parts[0], dpi)?;
-                let height = convert::parse_length(parts[1], dpi)?;
-                Ok(Page {
-                    dpi,
-                    width,
-                    height,
-                    borders: border,
-                })
-            } else {
-                Err(Error::UnknownPaper(name.to_string()))
+        let (width, height) = parse_size(name, dpi)?;
+        Ok(Page {
+            dpi,
+            width,
+            height,
+            borders: Borders::even(margin, dpi),
+            bleed: 0.0,
+        })
+    }
+
+    /// Construct a page from a spec string, this is what [`FromStr`] uses.
+    ///
+    /// A spec is a size followed by any number of options separated by spaces or semicolons:
+    ///
+    /// * The size is either a paper name from [`Page::paper_names`] or two lengths separated by an `x`. If only the
+    ///   second length has a unit it applies to both, so `210 x 297 mm` and `8.5in X 11in` both work.
+    /// * `landscape` or `portrait` turns the page if needed, it can also be written as `orientation=landscape` or
+    ///   attached to a paper name like `A4-landscape`.
+    /// * `margin=` sets the borders with one to four lengths separated by commas in the same order as css:
+    ///   all sides, vertical then horizontal, top then horizontal then bottom, or top, right, bottom, left.
+    ///   Defaults to half an inch.
+    /// * `bleed=` sets how far the drawing carries on past the edge of the page when printing, defaults to zero.
+    /// * `dpi=` sets the pixels per inch, defaults to 96.
+    ///
+    /// ```
+    /// use esvg::page::Page;
+    ///
+    /// let page = Page::from_spec("A4 landscape margin=1in,0.5in dpi=300").unwrap();
+    /// assert!(page.is_landscape());
+    /// assert_eq!(page.dpi, 300);
+    /// assert_eq!(page.borders.top, 300.0);
+    /// assert_eq!(page.borders.left, 150.0);
+    ///
+    /// let page: Page = "210 x 297 mm; margin=0".parse().unwrap();
+    /// assert_eq!(page.to_string(), "A4 margin=0mm dpi=96");
+    /// ```
+    pub fn from_spec(spec: &str) -> Result<Page, Error> {
+        let invalid = |reason: String| Error::InvalidPageSpec(spec.to_string(), reason);
+
+        let mut size_words = vec![];
+        let mut orientation = None;
+        let mut margins: Vec<String> = vec![];
+        let mut bleed = None;
+        let mut dpi = 96;
+
+        let mut words = spec
+            .split(|c: char| c.is_whitespace() || c == ';')
+            .filter(|w| !w.is_empty())
+            .peekable();
+
+        while let Some(word) = words.next() {
+            let Some((key, first)) = word.split_once('=') else {
+                match parse_orientation(word) {
+                    Some(o) => orientation = Some(o),
+                    None => size_words.push(word),
+                }
+                continue;
+            };
+
+            // allow spaces after the commas in a list of values
+            let mut values = vec![first];
+            while values
+                .last()
+                .is_some_and(|v| v.is_empty() || v.ends_with(','))
+            {
+                match words.peek() {
+                    Some(next) if !next.contains('=') => {
+                        values.push(words.next().unwrap_or_default())
+                    }
+                    _ => break,
+                }
             }
-        } else {
-            match name.to_lowercase().as_str() {
-                // TODO: add more paper sizes
-                "a5" => Ok(Page::A5_with_border(dpi, border)),
-                "a4" => Ok(Page::A4_with_border(dpi, border)),
-                "a3" => Ok(Page::A3_with_border(dpi, border)),
-                "letter" => Ok(Page::letter_with_border(dpi, border)),
-                _ => Err(Error::UnknownPaper(name.to_string())),
+            let value = values.concat();
+
+            match key.to_ascii_lowercase().as_str() {
+                "dpi" => {
+                    dpi = value
+                        .parse::<i32>()
+                        .ok()
+                        .filter(|d| *d > 0)
+                        .ok_or_else(|| {
+                            invalid(format!(
+                                "dpi must be a positive whole number, not {value:?}"
+                            ))
+                        })?;
+                }
+                "margin" | "margins" => {
+                    margins = value.split(',').map(|m| m.trim().to_string()).collect();
+                    if margins.is_empty()
+                        || margins.len() > 4
+                        || margins.iter().any(|m| m.is_empty())
+                    {
+                        return Err(invalid(format!(
+                            "margin needs one to four lengths, not {value:?}"
+                        )));
+                    }
+                }
+                "bleed" => bleed = Some(value),
+                "orientation" => {
+                    orientation = Some(
+                        parse_orientation(&value)
+                            .ok_or_else(|| invalid(format!("unknown orientation {value:?}")))?,
+                    );
+                }
+                _ => return Err(invalid(format!("unknown option {key:?}"))),
             }
         }
+
+        if size_words.is_empty() {
+            return Err(invalid("no page size given".to_string()));
+        }
+        let (width, height) = parse_size(&size_words.join(" "), dpi)?;
+
+        let mut lengths = vec![];
+        for m in &margins {
+            lengths.push(
+                convert::parse_length(m, dpi)
+                    .map_err(|_| invalid(format!("invalid margin {m:?}")))?,
+            );
+        }
+        let borders = match lengths[..] {
+            [] => Borders::default(dpi),
+            [all] => Borders {
+                top: all,
+                bottom: all,
+                left: all,
+                right: all,
+            },
+            [vertical, horizontal] => Borders {
+                top: vertical,
+                bottom: vertical,
+                left: horizontal,
+                right: horizontal,
+            },
+            [top, horizontal, bottom] => Borders {
+                top,
+                bottom,
+                left: horizontal,
+                right: horizontal,
+            },
+            [top, right, bottom, left, ..] => Borders {
+                top,
+                bottom,
+                left,
+                right,
+            },
+        };
+
+        let bleed = match bleed {
+            Some(b) => convert::parse_length(&b, dpi)
+                .ok()
+                .filter(|b| *b >= 0.0)
+                .ok_or_else(|| invalid(format!("invalid bleed {b:?}")))?,
+            None => 0.0,
+        };
+
+        let page = Page {
+            dpi,
+            width,
+            height,
+            borders,
+            bleed,
+        };
+        Ok(match orientation {
+            Some(Orientation::Landscape) if page.is_portrait() => page.rotate_size(),
+            Some(Orientation::Portrait) if page.is_landscape() => page.rotate_size(),
+            _ => page,
+        })
+    }
+
+    /// Construct a page from one of the named sizes in [`PAPER_SIZES`].
+    ///
+    /// Names ignore case, spaces, and punctuation, and may end with an orientation, so "A4", "a4 Landscape", and
+    /// "ANSI-B-portrait" all work. Without an orientation the page is the way round it is in the catalogue.
+    ///
+    /// ```
+    /// use esvg::page::{Borders, Page};
+    ///
+    /// let page = Page::paper("A4-landscape", 96, Borders::even(0.0, 96)).unwrap();
+    /// assert!(page.is_landscape());
+    /// assert_eq!(page.height, 793.7007874015749);
+    /// ```
+    pub fn paper(name: &str, dpi: i32, border: Borders) -> Result<Page, Error> {
+        let (base, orientation) = split_orientation(name.trim());
+        let paper = PaperSize::find(base).ok_or_else(|| Error::UnknownPaper(name.to_string()))?;
+        let page = paper.to_page(dpi, border)?;
+
+        match orientation {
+            Some(Orientation::Landscape) if page.height > page.width => Ok(page.rotate()),
+            Some(Orientation::Portrait) if page.width > page.height => Ok(page.rotate()),
+            _ => Ok(page),
+        }
+    }
+
+    /// The names of all the paper sizes [`Page::paper`] knows about, suitable for showing in a list.
+    pub fn paper_names() -> Vec<&'static str> {
+        PAPER_SIZES.iter().map(|p| p.name).collect()
+    }
+
+    /// Work out the page an svg document was made for from its `width`, `height`, and `viewBox`.
+    ///
+    /// The dpi is worked out from how many view box units there are per inch of the physical width, rounded to a
+    /// whole number. When rounding changes the dpi the page is scaled to match, so its physical size is kept. If the
+    /// size has no physical units 96 dpi is assumed. A view box that doesn't start at 0,0 is an
+    /// [`Error::InvalidAttribute`], as pages always do. Borders come from the
+    /// [`BORDERS_ATTRIBUTE`] written by [`Page::annotate`] if it is there, otherwise from any Inkscape guides, and
+    /// are zero if there are neither.
+    ///
+    /// ```
+    /// use esvg::create_document;
+    /// use esvg::page::Page;
+    ///
+    /// let page = Page::A4(300);
+    /// let mut doc = create_document(&page);
+    /// page.annotate(&mut doc);
+    ///
+    /// let found = Page::from_document(&doc).unwrap();
+    /// assert_eq!(found.dpi, 300);
+    /// assert_eq!(found.width, page.width);
+    /// assert_eq!(found.borders, page.borders);
+    /// ```
+    pub fn from_document(doc: &Element) -> Result<Page, Error> {
+        let geometry = DocumentGeometry::read(doc)?;
+        let dpi = geometry.inferred_dpi();
+        let scale = dpi as f64 / geometry.inferred_dpi_exact();
+        geometry.into_page(dpi, scale)
+    }
+
+    /// Like [`Page::from_document`] but the result uses the dpi given, the page is scaled so its physical size is
+    /// kept. A document without physical units is taken to be at 96 dpi.
+    pub fn from_document_with_dpi(doc: &Element, dpi: i32) -> Result<Page, Error> {
+        let geometry = DocumentGeometry::read(doc)?;
+        let scale = dpi as f64 / geometry.inferred_dpi_exact();
+        geometry.into_page(dpi, scale)
+    }
+
+    /// Record the borders of this page on a document so [`Page::from_document`] can get them back later.
+    pub fn annotate(&self, doc: &mut Element) {
+        doc.set(
+            BORDERS_ATTRIBUTE,
+            format!(
+                "{} {} {} {}",
+                self.borders.top, self.borders.right, self.borders.bottom, self.borders.left
+            ),
+        );
     }
 
     // Create paper sizes using macros to avoid duplication
-    // TODO: add more paper sizes (make sure to keep with the same pattern)
+    // Sizes come from the PAPER_SIZES catalogue, add them there first (make sure to keep with the same pattern)
     paper_size!(
-        A5, A5_with_border: 5.8, 8.27,
-        A4, A4_with_border: 8.27, 11.7,
-        A3, A3_with_border: 11.7, 16.5,
-        letter, letter_with_border: 8.5, 11.0,
+        A0, A0_with_border: "A0",
+        A1, A1_with_border: "A1",
+        A2, A2_with_border: "A2",
+        A3, A3_with_border: "A3",
+        A4, A4_with_border: "A4",
+        A5, A5_with_border: "A5",
+        A6, A6_with_border: "A6",
+        letter, letter_with_border: "Letter",
+        legal, legal_with_border: "Legal",
+        tabloid, tabloid_with_border: "Tabloid",
     );
 
+    /// Swap the width and height but leave the borders where they are, used when a spec gives the borders for the
+    /// page the way up it will be used.
+    fn rotate_size(&self) -> Page {
+        Page {
+            dpi: self.dpi,
+            width: self.height,
+            height: self.width,
+            borders: self.borders.clone(),
+            bleed: self.bleed,
+        }
+    }
+
     /// Rotate this page through 90 degrees, portrait to landscape and landscape to portrait.
     pub fn rotate(&self) -> Page {
         Page {
@@ -127,6 +547,7 @@ impl Page {
             width: self.height,
             height: self.width,
             borders: self.borders.rotate(),
+            bleed: self.bleed,
         }
     }
 
@@ -142,6 +563,15 @@ impl Page {
         self.width > self.height
     }
 
+    /// Which way round the page is, a square page counts as portrait
+    pub fn orientation(&self) -> Orientation {
+        if self.is_landscape() {
+            Orientation::Landscape
+        } else {
+            Orientation::Portrait
+        }
+    }
+
     /// Return the point in the document that matches to the borders in the top left corner
     pub fn top_left(&self) -> Point {
         Point::new(self.borders.left, self.borders.top)
@@ -168,8 +598,8 @@ impl Page {
     /// Return the center point of the document including the borders
     pub fn center(&self) -> Point {
         Point::new(
-            self.borders.left + ((self.width - self.borders.right - self.borders.left) / 2),
-            self.borders.top + ((self.height - self.borders.top - self.borders.bottom) / 2),
+            self.borders.left + ((self.width - self.borders.right - self.borders.left) / 2.0),
+            self.borders.top + ((self.height - self.borders.top - self.borders.bottom) / 2.0),
         )
     }
 
@@ -177,7 +607,7 @@ impl Page {
     pub fn center_left(&self) -> Point {
         Point::new(
             self.borders.left,
-            self.borders.top + ((self.height - self.borders.top - self.borders.bottom) / 2),
+            self.borders.top + ((self.height - self.borders.top - self.borders.bottom) / 2.0),
         )
     }
 
@@ -185,14 +615,14 @@ impl Page {
     pub fn center_right(&self) -> Point {
         Point::new(
             self.width - self.borders.right,
-            self.borders.top + ((self.height - self.borders.top - self.borders.bottom) / 2),
+            self.borders.top + ((self.height - self.borders.top - self.borders.bottom) / 2.0),
         )
     }
 
     /// Return the point on the top border in the middle horizontally
     pub fn center_top(&self) -> Point {
         Point::new(
-            self.borders.left + ((self.width - self.borders.right - self.borders.left) / 2),
+            self.borders.left + ((self.width - self.borders.right - self.borders.left) / 2.0),
             self.borders.top,
         )
     }
@@ -200,18 +630,533 @@ impl Page {
     /// Return the point on the bottom border in the middle horizontally
     pub fn center_bottom(&self) -> Point {
         Point::new(
-            self.borders.left + ((self.width - self.borders.right - self.borders.left) / 2),
+            self.borders.left + ((self.width - self.borders.right - self.borders.left) / 2.0),
             self.height - self.borders.bottom,
         )
     }
 
     /// Return the width of the page minus the borders in pixels
-    pub fn display_width_px(&self) -> i32 {
+    pub fn display_width_px(&self) -> f64 {
         self.width - self.borders.right - self.borders.left
     }
 
     /// Return the width of the page minus the borders in pixels
-    pub fn display_height_px(&self) -> i32 {
+    pub fn display_height_px(&self) -> f64 {
         self.height - self.borders.top - self.borders.bottom
     }
 }
+
+/// The attribute [`Page::annotate`] stores the borders in, as "top right bottom left" in view box units
+pub const BORDERS_ATTRIBUTE: &str = "data-esvg-borders";
+
+/// The size related attributes of a document
+struct DocumentGeometry {
+    /// width and height in view box units
+    width: f64,
+    height: f64,
+    /// physical width in inches, if the document says
+    width_inches: Option<f64>,
+    borders: Borders,
+}
+
+impl DocumentGeometry {
+    fn read(doc: &Element) -> Result<DocumentGeometry, Error> {
+        let view_box = match doc.get("viewBox") {
+            Some(v) => Some(parse_view_box(&v)?),
+            None => None,
+        };
+
+        let width = doc.get("width").map(|w| svg_length(&w)).transpose()?;
+        let height = doc.get("height").map(|h| svg_length(&h)).transpose()?;
+
+        let (w, h) = match (view_box, width, height) {
+            (Some((w, h)), _, _) => (w, h),
+            (None, Some(w), Some(h)) if !w.unit.is_relative() && !h.unit.is_relative() => {
+                (w.to_pixels(CSS_DPI)?, h.to_pixels(CSS_DPI)?)
+            }
+            (None, None, _) | (None, Some(_), _) => {
+                return Err(Error::MissingAttribute("viewBox".to_string()))
+            }
+        };
+
+        let width_inches = width
+            .filter(|l| l.unit != Unit::Px && !l.unit.is_relative())
+            .map(|l| l.to_inches(CSS_DPI))
+            .transpose()?;
+
+        let borders = match doc.get(BORDERS_ATTRIBUTE) {
+            Some(b) => parse_borders(&b)?,
+            None => guide_borders(doc, w, h),
+        };
+
+        Ok(DocumentGeometry {
+            width: w,
+            height: h,
+            width_inches,
+            borders,
+        })
+    }
+
+    fn inferred_dpi_exact(&self) -> f64 {
+        match self.width_inches {
+            Some(inches) if inches > 0.0 => self.width / inches,
+            _ => CSS_DPI as f64,
+        }
+    }
+
+    fn inferred_dpi(&self) -> i32 {
+        (self.inferred_dpi_exact().round() as i32).max(1)
+    }
+
+    fn into_page(self, dpi: i32, scale: f64) -> Result<Page, Error> {
+        Ok(Page {
+            dpi,
+            width: self.width * scale,
+            height: self.height * scale,
+            borders: Borders {
+                top: self.borders.top * scale,
+                bottom: self.borders.bottom * scale,
+                left: self.borders.left * scale,
+                right: self.borders.right * scale,
+            },
+            bleed: 0.0,
+        })
+    }
+}
+
+/// Svg attributes without a unit are in user units, not inches
+pub(crate) fn svg_length(value: &str) -> Result<Length, Error> {
+    let length = Length::from_str(value)?;
+    match convert::extract_unit(value)? {
+        "" => Ok(Length::px(length.value)),
+        _ => Ok(length),
+    }
+}
+
+/// Split a list of numbers separated by commas and or whitespace
+pub(crate) fn number_list(value: &str) -> Result<Vec<f64>, Error> {
+    value
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .map(|s| f64::from_str(s).map_err(Error::from))
+        .collect()
+}
+
+/// The width and height of a view box, pages always start at 0,0 so a view box that starts anywhere else is an error
+fn parse_view_box(value: &str) -> Result<(f64, f64), Error> {
+    match number_list(value)?[..] {
+        [x, y, w, h] if x == 0.0 && y == 0.0 && w > 0.0 && h > 0.0 => Ok((w, h)),
+        _ => Err(Error::InvalidAttribute(
+            "viewBox".to_string(),
+            value.to_string(),
+        )),
+    }
+}
+
+fn parse_borders(value: &str) -> Result<Borders, Error> {
+    match number_list(value)?[..] {
+        [top, right, bottom, left] => Ok(Borders {
+            top,
+            bottom,
+            left,
+            right,
+        }),
+        _ => Err(Error::InvalidAttribute(
+            BORDERS_ATTRIBUTE.to_string(),
+            value.to_string(),
+        )),
+    }
+}
+
+/// Work out borders from the Inkscape guides closest to the edges of the page.
+///
+/// Guides are stored with the y axis pointing up from the bottom of the page, a guide with an orientation of
+/// "1,0" is a vertical line at its x position, and "0,1" is a horizontal line at its y position.
+fn guide_borders(doc: &Element, width: f64, height: f64) -> Borders {
+    let mut borders = Borders {
+        top: 0.0,
+        bottom: 0.0,
+        left: 0.0,
+        right: 0.0,
+    };
+
+    let guides = doc
+        .elements()
+        .filter(|e| e.name == "sodipodi:namedview")
+        .flat_map(|e| e.elements())
+        .filter(|e| e.name == "sodipodi:guide");
+
+    for guide in guides {
+        let (Some(position), Some(orientation)) = (guide.get("position"), guide.get("orientation"))
+        else {
+            continue;
+        };
+        let (Ok(position), Ok(orientation)) = (number_list(&position), number_list(&orientation))
+        else {
+            continue;
+        };
+        let (&[x, y], &[ox, oy]) = (&position[..], &orientation[..]) else {
+            continue;
+        };
+
+        if oy == 0.0 && ox != 0.0 && (0.0..=width).contains(&x) {
+            if x < width / 2.0 {
+                borders.left = borders.left.max(x);
+            } else {
+                borders.right = borders.right.max(width - x);
+            }
+        } else if ox == 0.0 && oy != 0.0 && (0.0..=height).contains(&y) {
+            let from_top = height - y;
+            if from_top < height / 2.0 {
+                borders.top = borders.top.max(from_top);
+            } else {
+                borders.bottom = borders.bottom.max(y);
+            }
+        }
+    }
+
+    borders
+}
+
+impl FromStr for Page {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Page::from_spec(s)
+    }
+}
+
+/// Writes the page as a spec that [`Page::from_spec`] can read back. Named paper sizes are written by name,
+/// everything else in millimetres.
+impl fmt::Display for Page {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mm = |px: f64| spec_number(convert::pixels_to_mm(px, self.dpi));
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-6;
+
+        let named = PAPER_SIZES.iter().find_map(|paper| {
+            let w = paper.width.to_pixels(self.dpi).ok()?;
+            let h = paper.height.to_pixels(self.dpi).ok()?;
+            if close(w, self.width) && close(h, self.height) {
+                Some((paper.name, ""))
+            } else if close(h, self.width) && close(w, self.height) {
+                Some((paper.name, if w < h { " landscape" } else { " portrait" }))
+            } else {
+                None
+            }
+        });
+
+        match named {
+            Some((name, orientation)) => write!(formatter, "{name}{orientation}")?,
+            None => write!(formatter, "{}mm x {}mm", mm(self.width), mm(self.height))?,
+        }
+
+        let b = &self.borders;
+        if b.top == b.bottom && b.top == b.left && b.top == b.right {
+            write!(formatter, " margin={}mm", mm(b.top))?;
+        } else {
+            write!(
+                formatter,
+                " margin={}mm,{}mm,{}mm,{}mm",
+                mm(b.top),
+                mm(b.right),
+                mm(b.bottom),
+                mm(b.left)
+            )?;
+        }
+
+        if self.bleed != 0.0 {
+            write!(formatter, " bleed={}mm", mm(self.bleed))?;
+        }
+
+        write!(formatter, " dpi={}", self.dpi)
+    }
+}
+
+/// Write a number with at most six decimal places and no trailing zeros
+fn spec_number(value: f64) -> String {
+    let s = format!("{value:.6}");
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    match s {
+        "-0" => "0".to_string(),
+        s => s.to_string(),
+    }
+}
+
+fn parse_orientation(word: &str) -> Option<Orientation> {
+    match word.to_ascii_lowercase().as_str() {
+        "landscape" => Some(Orientation::Landscape),
+        "portrait" => Some(Orientation::Portrait),
+        _ => None,
+    }
+}
+
+/// Parse a paper name or a "width x height" pair into a width and height in pixels
+fn parse_size(size: &str, dpi: i32) -> Result<(f64, f64), Error> {
+    let size = size.trim();
+    if let Ok(page) = Page::paper(size, dpi, Borders::even(0.0, dpi)) {
+        return Ok((page.width, page.height));
+    }
+
+    for (i, c) in size.char_indices() {
+        if !matches!(c, 'x' | 'X' | '×') {
+            continue;
+        }
+        let (left, right) = (&size[..i], &size[i + c.len_utf8()..]);
+        let (Ok(width), Ok(height)) = (Length::from_str(left), Length::from_str(right)) else {
+            continue;
+        };
+
+        // "210 x 297 mm" the unit on the height applies to both
+        let width = match convert::extract_unit(left) {
+            Ok("") => Length::new(width.value, height.unit),
+            _ => width,
+        };
+        return Ok((width.to_pixels(dpi)?, height.to_pixels(dpi)?));
+    }
+
+    Err(Error::UnknownPaper(size.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{Borders, Orientation, Page, PAPER_SIZES};
+    use crate::error::Error;
+    use crate::read::parse_string;
+    use crate::{create_document, Element};
+
+    #[test]
+    fn constructors_use_catalogue() {
+        let pages = [
+            Page::A0(96),
+            Page::A1(96),
+            Page::A2(96),
+            Page::A3(96),
+            Page::A4(96),
+            Page::A5(96),
+            Page::A6(96),
+            Page::letter(96),
+            Page::legal(96),
+            Page::tabloid(96),
+        ];
+        assert!(pages.iter().all(|p| p.is_portrait()));
+        assert_eq!(Page::A4(25).width, 210.0 / 25.4 * 25.0);
+        assert_eq!(Page::letter(100).height, 1100.0);
+    }
+
+    #[test]
+    fn paper_names() {
+        let names = Page::paper_names();
+        assert_eq!(names.len(), PAPER_SIZES.len());
+        for series in ["A", "B", "C"] {
+            for i in 0..=10 {
+                assert!(names.contains(&format!("{series}{i}").as_str()));
+            }
+        }
+        for name in names {
+            assert!(Page::paper(name, 96, Borders::even(0.0, 96)).is_ok());
+        }
+    }
+
+    #[test]
+    fn paper_lookup() {
+        let border = || Borders::even(0.0, 96);
+        let a4 = Page::paper("a4", 96, border()).unwrap();
+        assert_eq!(a4.width, 793.7007874015749);
+        assert_eq!(a4.height, 1122.5196850393702);
+
+        let ansi = Page::paper("ansi-b landscape", 96, border()).unwrap();
+        assert_eq!((ansi.width, ansi.height), (1632.0, 1056.0));
+
+        let ledger = Page::paper("LEDGER_portrait", 96, border()).unwrap();
+        assert_eq!((ledger.width, ledger.height), (1056.0, 1632.0));
+
+        let photo = Page::paper("Photo 4x6", 96, border()).unwrap();
+        assert_eq!((photo.width, photo.height), (384.0, 576.0));
+
+        assert!(Page::paper("A11", 96, border()).is_err());
+        assert!(Page::paper("landscape", 96, border()).is_err());
+    }
+
+    #[test]
+    fn build_page_names_and_sizes() {
+        assert!(Page::build_page("Arch D", 96, 0.5).unwrap().is_portrait());
+        assert!(Page::build_page("photo 8x10", 96, 0.5).is_ok());
+        let custom = Page::build_page("200mmx2in", 96, 0.5).unwrap();
+        assert_eq!(custom.height, 192.0);
+    }
+
+    #[test]
+    fn spec_sizes() {
+        let a4 = Page::A4(96);
+        for spec in [
+            "A4",
+            "a4 portrait",
+            "210 x 297 mm",
+            "210mmx297mm",
+            "21cm X 297mm",
+            "210×297mm",
+        ] {
+            let page = Page::from_spec(spec).unwrap();
+            assert!((page.width - a4.width).abs() < 1e-9, "{spec}");
+            assert!((page.height - a4.height).abs() < 1e-9, "{spec}");
+        }
+
+        let letter = Page::from_spec("8.5in X 11in").unwrap();
+        assert_eq!((letter.width, letter.height), (816.0, 1056.0));
+
+        let photo = Page::from_spec("Photo 4x6 landscape").unwrap();
+        assert_eq!((photo.width, photo.height), (576.0, 384.0));
+
+        let index = Page::from_spec("index card 3x5-landscape").unwrap();
+        assert_eq!((index.width, index.height), (480.0, 288.0));
+    }
+
+    #[test]
+    fn spec_options() {
+        let page = Page::from_spec("letter; margin=1in, 0.5in, 0.25in; dpi=100").unwrap();
+        assert_eq!(page.dpi, 100);
+        assert_eq!(page.borders.top, 100.0);
+        assert_eq!(page.borders.left, 50.0);
+        assert_eq!(page.borders.right, 50.0);
+        assert_eq!(page.borders.bottom, 25.0);
+
+        let page = Page::from_spec("A5 orientation=landscape margin=1mm,2mm,3mm,4mm").unwrap();
+        assert!(page.is_landscape());
+        assert_eq!(
+            page.borders.right,
+            Page::from_spec("2mmx2mm margin=2mm").unwrap().borders.top
+        );
+        assert_eq!(
+            page.borders.left,
+            Page::from_spec("4mmx4mm margin=4mm").unwrap().borders.top
+        );
+
+        let page = Page::from_spec("A4").unwrap();
+        assert_eq!(page.borders, Borders::default(96));
+    }
+
+    #[test]
+    fn spec_errors() {
+        for spec in [
+            "",
+            "dpi=300",
+            "A4 dpi=fish",
+            "A4 dpi=-3",
+            "A4 margin=",
+            "A4 margin=1,2,3,4,5",
+            "A4 margin=1in,wide",
+            "A4 colour=blue",
+            "A4 orientation=sideways",
+            "A4 bleed=-3mm",
+            "A4 bleed=lots",
+        ] {
+            match Page::from_spec(spec) {
+                Err(Error::InvalidPageSpec(s, _)) => assert_eq!(s, spec),
+                other => panic!("expected an invalid spec for {spec:?} got {other:?}"),
+            }
+        }
+
+        assert!(matches!(
+            Page::from_spec("A11"),
+            Err(Error::UnknownPaper(_))
+        ));
+        assert!(matches!(
+            Page::from_spec("3 x"),
+            Err(Error::UnknownPaper(_))
+        ));
+    }
+
+    #[test]
+    fn spec_round_trip() {
+        let specs = [
+            "A4 margin=12.7mm dpi=96",
+            "A3 landscape margin=10mm,5mm,10mm,5mm dpi=300",
+            "Business card portrait margin=0mm dpi=72",
+            "123.4mm x 56.7mm margin=1mm dpi=150",
+            "Letter margin=12.7mm bleed=3.175mm dpi=96",
+        ];
+        for spec in specs {
+            let page = Page::from_spec(spec).unwrap();
+            assert_eq!(page.to_string(), spec);
+            let again: Page = page.to_string().parse().unwrap();
+            assert!((again.width - page.width).abs() < 1e-4);
+            assert!((again.height - page.height).abs() < 1e-4);
+            assert!((again.borders.left - page.borders.left).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn from_document_units() {
+        let mut doc = Element::new("svg");
+        doc.set("width", "210mm")
+            .set("height", "297mm")
+            .set("viewBox", "0 0 210 297");
+        let page = Page::from_document(&doc).unwrap();
+        // 25.4 view box units per inch rounds to 25 dpi, the page is scaled so it is still 210mm wide
+        assert_eq!(page.dpi, 25);
+        assert!((page.width / 25.0 * 25.4 - 210.0).abs() < 1e-9);
+        assert!((page.height / 25.0 * 25.4 - 297.0).abs() < 1e-9);
+        assert_eq!(page.orientation(), Orientation::Portrait);
+
+        let page = Page::from_document_with_dpi(&doc, 96).unwrap();
+        assert!((page.width - Page::A4(96).width).abs() < 1e-9);
+
+        let mut doc = Element::new("svg");
+        doc.set("width", "11in").set("height", "8.5in");
+        let page = Page::from_document(&doc).unwrap();
+        assert_eq!(page.dpi, 96);
+        assert_eq!((page.width, page.height), (1056.0, 816.0));
+        assert_eq!(page.orientation(), Orientation::Landscape);
+
+        let mut doc = Element::new("svg");
+        doc.set("width", "100%").set("viewBox", "0,0,400,300");
+        let page = Page::from_document(&doc).unwrap();
+        assert_eq!((page.dpi, page.width, page.height), (96, 400.0, 300.0));
+
+        assert!(Page::from_document(&Element::new("svg")).is_err());
+        let mut doc = Element::new("svg");
+        doc.set("viewBox", "0 0 fish 2");
+        assert!(Page::from_document(&doc).is_err());
+        doc.set("viewBox", "10 10 100 50");
+        assert!(matches!(
+            Page::from_document(&doc),
+            Err(Error::InvalidAttribute(a, v)) if a == "viewBox" && v == "10 10 100 50"
+        ));
+    }
+
+    #[test]
+    fn from_document_round_trip() {
+        for spec in [
+            "A3 landscape margin=1in,2in,3in,4in dpi=300",
+            "Letter dpi=72",
+        ] {
+            let page = Page::from_spec(spec).unwrap();
+            let mut doc = create_document(&page);
+            page.annotate(&mut doc);
+            let found = Page::from_document(&doc).unwrap();
+            assert_eq!(found, page);
+        }
+    }
+
+    #[test]
+    fn from_document_guides() {
+        let input = r#"<svg width="100mm" height="50mm" viewBox="0 0 100 50">
+            <sodipodi:namedview id="namedview1">
+                <sodipodi:guide position="10,0" orientation="1,0" />
+                <sodipodi:guide position="95,0" orientation="-1,0" />
+                <sodipodi:guide position="0,45" orientation="0,1" />
+                <sodipodi:guide position="0,2" orientation="0,-1" />
+                <sodipodi:guide position="20,20" orientation="0.7,0.7" />
+            </sodipodi:namedview>
+        </svg>"#;
+        let doc = parse_string(input.to_string()).unwrap();
+        let page = Page::from_document(&doc).unwrap();
+        // the guides are in millimetres but the page is at a whole 25 dpi
+        let mm = |px: f64| px / page.dpi as f64 * 25.4;
+        assert!((mm(page.borders.left) - 10.0).abs() < 1e-9);
+        assert!((mm(page.borders.right) - 5.0).abs() < 1e-9);
+        assert!((mm(page.borders.top) - 5.0).abs() < 1e-9);
+        assert!((mm(page.borders.bottom) - 2.0).abs() < 1e-9);
+    }
+}
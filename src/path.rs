@@ -1,11 +1,61 @@
 //! Helpers for handling Path data
+use crate::error::Error;
+use crate::value::NumberFormat;
 use crate::Element;
 use ::polygonical::point::Point;
 use ::polygonical::polygon::Polygon;
 
 /// Represents the data attribute of a svg path
 pub struct Data {
-    segments: Vec<String>,
+    segments: Vec<Step>,
+}
+
+/// One command in some path data, kept as numbers until the data is built
+#[derive(Debug, Clone)]
+enum Step {
+    Move(Point),
+    Line(Point),
+    /// The radii and rotation are kept as they were written so `build` gives what it always has
+    Arc {
+        rx: String,
+        ry: String,
+        rotation: String,
+        large: bool,
+        sweep: bool,
+        to: Point,
+    },
+    Close,
+}
+
+impl Step {
+    fn write<F, G>(&self, number: F, written: G) -> Result<String, Error>
+    where
+        F: Fn(f64) -> Result<String, Error>,
+        G: Fn(&str) -> Result<String, Error>,
+    {
+        Ok(match self {
+            Step::Move(p) => format!("M{} {}", number(p.x)?, number(p.y)?),
+            Step::Line(p) => format!("L{} {}", number(p.x)?, number(p.y)?),
+            Step::Arc {
+                rx,
+                ry,
+                rotation,
+                large,
+                sweep,
+                to,
+            } => format!(
+                "A{} {} {} {} {} {} {}",
+                written(rx)?,
+                written(ry)?,
+                written(rotation)?,
+                *large as u8,
+                *sweep as u8,
+                number(to.x)?,
+                number(to.y)?
+            ),
+            Step::Close => "z".to_string(),
+        })
+    }
 }
 
 /// Create a data attribute from a series of points
@@ -45,19 +95,19 @@ impl Data {
 
     /// Add a Move To step to this path.
     pub fn move_to(&mut self, p: Point) -> &mut Data {
-        self.segments.push(format!("M{:.3} {:.3}", p.x, p.y));
+        self.segments.push(Step::Move(p));
 
         self
     }
 
     /// Add a line to step to this path
     pub fn line_to(&mut self, p: Point) -> &mut Data {
-        self.segments.push(format!("L{:.3} {:.3}", p.x, p.y));
+        self.segments.push(Step::Line(p));
 
         self
     }
 
-    /// Add an arc to step to this path
+    /// Add an arc to step to this path, the rotation is in degrees
     pub fn arc_to<RX, RY, ROT>(
         &mut self,
         p: Point,
@@ -72,33 +122,111 @@ impl Data {
         RY: std::fmt::Display,
         ROT: std::fmt::Display,
     {
-        let lv = match large {
-            true => 1,
-            false => 0,
-        };
-
-        let sv = match sweep {
-            true => 1,
-            false => 0,
-        };
-
-        self.segments.push(format!(
-            "A{} {} {:.3} {} {} {:.3} {:.3}",
-            rx, ry, rotation, lv, sv, p.x, p.y
-        ));
+        self.segments.push(Step::Arc {
+            rx: rx.to_string(),
+            ry: ry.to_string(),
+            rotation: format!("{rotation:.3}"),
+            large,
+            sweep,
+            to: p,
+        });
 
         self
     }
 
     /// Close the loop of this path.
     pub fn close(&mut self) -> &mut Data {
-        self.segments.push("z".to_string());
+        self.segments.push(Step::Close);
         self
     }
 
-    /// Turn this data attribute into a string.
+    /// Add all the steps of another path to the end of this one.
+    pub fn append(&mut self, other: &Data) -> &mut Data {
+        self.segments.extend(other.segments.iter().cloned());
+        self
+    }
+
+    /// Turn this data attribute into a string, with numbers to three decimal places.
     pub fn build(&self) -> String {
-        self.segments.join(" ")
+        let format = NumberFormat {
+            strip_trailing_zeros: false,
+            ..NumberFormat::decimals(3)
+        };
+        self.build_steps(|n| Ok(format.format_unchecked(n)), |t| Ok(t.to_string()))
+            .expect("formatting without checks can't fail")
+    }
+
+    /// Turn this data attribute into a string with numbers written in a format, numbers that aren't finite are an
+    /// error.
+    ///
+    /// ```
+    /// use esvg::path::Data;
+    /// use esvg::value::NumberFormat;
+    /// use polygonical::point::Point;
+    ///
+    /// let mut data = Data::new();
+    /// data.move_to(Point::new(0.5, 1.0)).arc_to(Point::new(10.25, 0.0), 5, 5, 0, false, true);
+    /// assert_eq!(data.build(), "M0.500 1.000 A5 5 0 0 1 10.250 0.000");
+    ///
+    /// let format = NumberFormat { drop_leading_zero: true, ..NumberFormat::decimals(1) };
+    /// assert_eq!(data.build_with(&format).unwrap(), "M.5 1 A5 5 0 0 1 10.2 0");
+    ///
+    /// data.line_to(Point::new(f64::NAN, 0.0));
+    /// assert!(data.build_with(&format).is_err());
+    /// ```
+    pub fn build_with(&self, format: &NumberFormat) -> Result<String, Error> {
+        self.build_steps(|n| format.format(n), |t| format.format(written_number(t)?))
+    }
+
+    /// The steps as absolute segments, for flattening without rounding the numbers by building the data first.
+    ///
+    /// ```
+    /// use esvg::path::{flatten, Data};
+    /// use polygonical::point::Point;
+    ///
+    /// let data = Data::from_points(&[Point::new(0.0, 0.0), Point::new(0.0001, 0.0001)]);
+    /// let lines = flatten(&data.segments().unwrap(), 0.1);
+    /// assert_eq!(lines[0][1], Point::new(0.0001, 0.0001));
+    /// ```
+    pub fn segments(&self) -> Result<Vec<Segment>, Error> {
+        self.segments
+            .iter()
+            .map(|step| {
+                Ok(match step {
+                    Step::Move(p) => Segment::MoveTo(*p),
+                    Step::Line(p) => Segment::LineTo(*p),
+                    Step::Arc {
+                        rx,
+                        ry,
+                        rotation,
+                        large,
+                        sweep,
+                        to,
+                    } => Segment::ArcTo {
+                        rx: written_number(rx)?,
+                        ry: written_number(ry)?,
+                        rotation: written_number(rotation)?,
+                        large: *large,
+                        sweep: *sweep,
+                        to: *to,
+                    },
+                    Step::Close => Segment::Close,
+                })
+            })
+            .collect()
+    }
+
+    fn build_steps<F, G>(&self, number: F, written: G) -> Result<String, Error>
+    where
+        F: Fn(f64) -> Result<String, Error>,
+        G: Fn(&str) -> Result<String, Error>,
+    {
+        let steps = self
+            .segments
+            .iter()
+            .map(|s| s.write(&number, &written))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(steps.join(" "))
     }
 
     /// build a path element using this data attribute
@@ -110,8 +238,587 @@ impl Data {
     }
 }
 
+/// A number kept as it was written, read back for formatting or flattening
+fn written_number(text: &str) -> Result<f64, Error> {
+    text.trim()
+        .parse::<f64>()
+        .map_err(|_| Error::InvalidAttribute("d".to_string(), text.to_string()))
+}
+
 impl Default for Data {
     fn default() -> Self {
         Self::new()
     }
 }
+
+/// One step of a path with everything in absolute coordinates.
+///
+/// Relative commands, horizontal and vertical lines, and the smooth curve short hands are all turned into these
+/// by [`parse`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Segment {
+    /// Lift the pen and start a new sub path here
+    MoveTo(Point),
+    /// A straight line to the point
+    LineTo(Point),
+    /// A cubic bezier curve with two control points to the last point
+    CubicTo(Point, Point, Point),
+    /// A quadratic bezier curve with one control point to the last point
+    QuadTo(Point, Point),
+    /// An elliptical arc to a point, rotation is in degrees
+    ArcTo {
+        rx: f64,
+        ry: f64,
+        rotation: f64,
+        large: bool,
+        sweep: bool,
+        to: Point,
+    },
+    /// A line back to the start of the sub path
+    Close,
+}
+
+/// Parse the d attribute of a path into absolute segments
+///
+/// ```
+/// use esvg::path::{parse, Segment};
+/// use polygonical::point::Point;
+///
+/// let segments = parse("m10 10 h5 v5 z").unwrap();
+/// assert_eq!(
+///     segments,
+///     [
+///         Segment::MoveTo(Point::new(10.0, 10.0)),
+///         Segment::LineTo(Point::new(15.0, 10.0)),
+///         Segment::LineTo(Point::new(15.0, 15.0)),
+///         Segment::Close,
+///     ]
+/// );
+/// ```
+pub fn parse(d: &str) -> Result<Vec<Segment>, Error> {
+    let invalid = || Error::InvalidAttribute("d".to_string(), d.to_string());
+    let mut lexer = Lexer {
+        data: d.as_bytes(),
+        pos: 0,
+    };
+
+    let mut result = vec![];
+    let mut current = Point::zero();
+    let mut start = Point::zero();
+    // the reflected control point for smooth curves, only valid straight after the matching curve type
+    let mut last_cubic: Option<Point> = None;
+    let mut last_quad: Option<Point> = None;
+    let mut command = None;
+
+    loop {
+        lexer.skip_separators();
+        if lexer.done() {
+            break;
+        }
+
+        let c = match lexer.command() {
+            Some(c) => c,
+            // numbers straight after a command repeat it, apart from move to which becomes line to
+            None => match command {
+                Some('M') => 'L',
+                Some('m') => 'l',
+                Some(c) => c,
+                None => return Err(invalid()),
+            },
+        };
+        if command.is_none() && c != 'M' && c != 'm' {
+            return Err(invalid());
+        }
+        command = Some(c);
+
+        let relative = c.is_ascii_lowercase();
+        let offset = if relative { current } else { Point::zero() };
+        let point = |lexer: &mut Lexer| -> Result<Point, Error> {
+            let x = lexer.number().ok_or_else(invalid)?;
+            let y = lexer.number().ok_or_else(invalid)?;
+            Ok(Point::new(x + offset.x, y + offset.y))
+        };
+
+        let mut cubic = None;
+        let mut quad = None;
+        match c.to_ascii_uppercase() {
+            'M' => {
+                current = point(&mut lexer)?;
+                start = current;
+                result.push(Segment::MoveTo(current));
+            }
+            'L' => {
+                current = point(&mut lexer)?;
+                result.push(Segment::LineTo(current));
+            }
+            'H' => {
+                let x = lexer.number().ok_or_else(invalid)?;
+                current = Point::new(x + offset.x, current.y);
+                result.push(Segment::LineTo(current));
+            }
+            'V' => {
+                let y = lexer.number().ok_or_else(invalid)?;
+                current = Point::new(current.x, y + offset.y);
+                result.push(Segment::LineTo(current));
+            }
+            'C' => {
+                let c1 = point(&mut lexer)?;
+                let c2 = point(&mut lexer)?;
+                current = point(&mut lexer)?;
+                result.push(Segment::CubicTo(c1, c2, current));
+                cubic = Some(c2);
+            }
+            'S' => {
+                let c1 = reflect(last_cubic, current);
+                let c2 = point(&mut lexer)?;
+                current = point(&mut lexer)?;
+                result.push(Segment::CubicTo(c1, c2, current));
+                cubic = Some(c2);
+            }
+            'Q' => {
+                let c1 = point(&mut lexer)?;
+                current = point(&mut lexer)?;
+                result.push(Segment::QuadTo(c1, current));
+                quad = Some(c1);
+            }
+            'T' => {
+                let c1 = reflect(last_quad, current);
+                current = point(&mut lexer)?;
+                result.push(Segment::QuadTo(c1, current));
+                quad = Some(c1);
+            }
+            'A' => {
+                let rx = lexer.number().ok_or_else(invalid)?;
+                let ry = lexer.number().ok_or_else(invalid)?;
+                let rotation = lexer.number().ok_or_else(invalid)?;
+                let large = lexer.flag().ok_or_else(invalid)?;
+                let sweep = lexer.flag().ok_or_else(invalid)?;
+                current = point(&mut lexer)?;
+                result.push(Segment::ArcTo {
+                    rx,
+                    ry,
+                    rotation,
+                    large,
+                    sweep,
+                    to: current,
+                });
+            }
+            'Z' => {
+                current = start;
+                result.push(Segment::Close);
+                // there are no numbers after a close so it can't be repeated
+                command = Some('z');
+                lexer.skip_separators();
+                if !lexer.done() && lexer.command_ahead().is_none() {
+                    return Err(invalid());
+                }
+            }
+            _ => return Err(invalid()),
+        }
+        last_cubic = cubic;
+        last_quad = quad;
+    }
+
+    Ok(result)
+}
+
+/// The first control point of a smooth curve, the last control point reflected through the current point
+fn reflect(control: Option<Point>, current: Point) -> Point {
+    match control {
+        Some(c) => Point::new(2.0 * current.x - c.x, 2.0 * current.y - c.y),
+        None => current,
+    }
+}
+
+/// Reads commands and numbers out of path data
+struct Lexer<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl Lexer<'_> {
+    fn done(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    fn skip_separators(&mut self) {
+        while !self.done()
+            && (self.data[self.pos].is_ascii_whitespace() || self.data[self.pos] == b',')
+        {
+            self.pos += 1;
+        }
+    }
+
+    fn command_ahead(&self) -> Option<char> {
+        let c = *self.data.get(self.pos)? as char;
+        (c.is_ascii_alphabetic() && c != 'e' && c != 'E').then_some(c)
+    }
+
+    fn command(&mut self) -> Option<char> {
+        let c = self.command_ahead()?;
+        self.pos += 1;
+        Some(c)
+    }
+
+    fn number(&mut self) -> Option<f64> {
+        self.skip_separators();
+        let start = self.pos;
+        let digits = |lexer: &mut Lexer| {
+            let from = lexer.pos;
+            while !lexer.done() && lexer.data[lexer.pos].is_ascii_digit() {
+                lexer.pos += 1;
+            }
+            lexer.pos > from
+        };
+
+        if matches!(self.data.get(self.pos), Some(b'+' | b'-')) {
+            self.pos += 1;
+        }
+        let mut any = digits(self);
+        if self.data.get(self.pos) == Some(&b'.') {
+            self.pos += 1;
+            any |= digits(self);
+        }
+        if !any {
+            self.pos = start;
+            return None;
+        }
+        if matches!(self.data.get(self.pos), Some(b'e' | b'E')) {
+            let before = self.pos;
+            self.pos += 1;
+            if matches!(self.data.get(self.pos), Some(b'+' | b'-')) {
+                self.pos += 1;
+            }
+            if !digits(self) {
+                self.pos = before;
+            }
+        }
+
+        std::str::from_utf8(&self.data[start..self.pos])
+            .ok()?
+            .parse()
+            .ok()
+    }
+
+    /// Arc flags are a single 0 or 1 and don't need separating from what follows them
+    fn flag(&mut self) -> Option<bool> {
+        self.skip_separators();
+        let flag = match self.data.get(self.pos)? {
+            b'0' => false,
+            b'1' => true,
+            _ => return None,
+        };
+        self.pos += 1;
+        Some(flag)
+    }
+}
+
+/// Turn path segments into lines, a polyline per sub path.
+///
+/// Curves and arcs are split into straight lines that stay within tolerance of the real curve. Closed sub paths end
+/// with their first point again.
+///
+/// ```
+/// use esvg::path::{flatten, parse};
+///
+/// let lines = flatten(&parse("M0 0 L10 0 L10 10 Z M20 20 Q30 20 30 30").unwrap(), 0.1);
+/// assert_eq!(lines.len(), 2);
+/// assert_eq!(lines[0].len(), 4);
+/// assert!(lines[1].len() > 2);
+/// ```
+pub fn flatten(segments: &[Segment], tolerance: f64) -> Vec<Vec<Point>> {
+    let tolerance = tolerance.max(1e-6);
+    let mut result = vec![];
+    let mut line: Vec<Point> = vec![];
+    let mut current = Point::zero();
+    let mut start = Point::zero();
+
+    let finish = |line: &mut Vec<Point>, result: &mut Vec<Vec<Point>>| {
+        if line.len() > 1 {
+            result.push(std::mem::take(line));
+        } else {
+            line.clear();
+        }
+    };
+
+    for segment in segments {
+        if line.is_empty() && !matches!(segment, Segment::MoveTo(_)) {
+            line.push(current);
+        }
+        match *segment {
+            Segment::MoveTo(p) => {
+                finish(&mut line, &mut result);
+                line.push(p);
+                current = p;
+                start = p;
+            }
+            Segment::LineTo(p) => {
+                line.push(p);
+                current = p;
+            }
+            Segment::CubicTo(c1, c2, p) => {
+                let dd = distance(second_difference(current, c1, c2), Point::zero())
+                    .max(distance(second_difference(c1, c2, p), Point::zero()));
+                let steps = steps((0.75 * dd / tolerance).sqrt());
+                for i in 1..=steps {
+                    let t = i as f64 / steps as f64;
+                    let mt = 1.0 - t;
+                    let a = mt * mt * mt;
+                    let b = 3.0 * mt * mt * t;
+                    let c = 3.0 * mt * t * t;
+                    let d = t * t * t;
+                    line.push(Point::new(
+                        a * current.x + b * c1.x + c * c2.x + d * p.x,
+                        a * current.y + b * c1.y + c * c2.y + d * p.y,
+                    ));
+                }
+                current = p;
+            }
+            Segment::QuadTo(c1, p) => {
+                let dd = distance(second_difference(current, c1, p), Point::zero());
+                let steps = steps((dd / (4.0 * tolerance)).sqrt());
+                for i in 1..=steps {
+                    let t = i as f64 / steps as f64;
+                    let mt = 1.0 - t;
+                    line.push(Point::new(
+                        mt * mt * current.x + 2.0 * mt * t * c1.x + t * t * p.x,
+                        mt * mt * current.y + 2.0 * mt * t * c1.y + t * t * p.y,
+                    ));
+                }
+                current = p;
+            }
+            Segment::ArcTo {
+                rx,
+                ry,
+                rotation,
+                large,
+                sweep,
+                to,
+            } => {
+                match Arc::from_endpoints(current, to, rx, ry, rotation, large, sweep) {
+                    Some(arc) => {
+                        let radius = arc.rx.max(arc.ry);
+                        let step = if tolerance < radius {
+                            2.0 * (1.0 - tolerance / radius).acos()
+                        } else {
+                            std::f64::consts::FRAC_PI_2
+                        };
+                        let steps = steps(arc.sweep.abs() / step);
+                        for i in 1..steps {
+                            line.push(arc.point(arc.start + arc.sweep * i as f64 / steps as f64));
+                        }
+                        line.push(to);
+                    }
+                    None => line.push(to),
+                }
+                current = to;
+            }
+            Segment::Close => {
+                if line.len() > 1 && line.last() != Some(&start) {
+                    line.push(start);
+                }
+                finish(&mut line, &mut result);
+                current = start;
+            }
+        }
+    }
+    finish(&mut line, &mut result);
+
+    result
+}
+
+fn steps(estimate: f64) -> usize {
+    (estimate.ceil() as usize).clamp(1, 1000)
+}
+
+fn second_difference(a: Point, b: Point, c: Point) -> Point {
+    Point::new(a.x - 2.0 * b.x + c.x, a.y - 2.0 * b.y + c.y)
+}
+
+/// The straight line distance between two points
+pub fn distance(a: Point, b: Point) -> f64 {
+    (a.x - b.x).hypot(a.y - b.y)
+}
+
+/// An elliptical arc described by its centre, the way it is easiest to draw
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Arc {
+    pub center: Point,
+    pub rx: f64,
+    pub ry: f64,
+    /// rotation of the x axis of the ellipse in radians
+    pub rotation: f64,
+    /// angle of the first point in radians
+    pub start: f64,
+    /// how far round the arc goes in radians, positive is clockwise on screen
+    pub sweep: f64,
+}
+
+impl Arc {
+    /// Work out the centre of an svg arc from its end points, following the svg spec's implementation notes.
+    /// Returns None if the arc is really a straight line because the end points are the same or a radius is zero.
+    pub fn from_endpoints(
+        from: Point,
+        to: Point,
+        rx: f64,
+        ry: f64,
+        rotation: f64,
+        large: bool,
+        sweep: bool,
+    ) -> Option<Arc> {
+        let (mut rx, mut ry) = (rx.abs(), ry.abs());
+        if rx == 0.0 || ry == 0.0 || (from.x == to.x && from.y == to.y) {
+            return None;
+        }
+
+        let phi = rotation.to_radians();
+        let (sin, cos) = phi.sin_cos();
+        let dx = (from.x - to.x) / 2.0;
+        let dy = (from.y - to.y) / 2.0;
+        let x1 = cos * dx + sin * dy;
+        let y1 = -sin * dx + cos * dy;
+
+        // scale up radii that are too small to reach
+        let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+        if lambda > 1.0 {
+            rx *= lambda.sqrt();
+            ry *= lambda.sqrt();
+        }
+
+        let numerator = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+        let denominator = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+        let mut factor = (numerator / denominator).max(0.0).sqrt();
+        if large == sweep {
+            factor = -factor;
+        }
+        let cx1 = factor * rx * y1 / ry;
+        let cy1 = -factor * ry * x1 / rx;
+
+        let center = Point::new(
+            cos * cx1 - sin * cy1 + (from.x + to.x) / 2.0,
+            sin * cx1 + cos * cy1 + (from.y + to.y) / 2.0,
+        );
+
+        let angle =
+            |ux: f64, uy: f64, vx: f64, vy: f64| (ux * vy - uy * vx).atan2(ux * vx + uy * vy);
+        let start = angle(1.0, 0.0, (x1 - cx1) / rx, (y1 - cy1) / ry);
+        let mut delta = angle(
+            (x1 - cx1) / rx,
+            (y1 - cy1) / ry,
+            (-x1 - cx1) / rx,
+            (-y1 - cy1) / ry,
+        );
+        if !sweep && delta > 0.0 {
+            delta -= std::f64::consts::TAU;
+        } else if sweep && delta < 0.0 {
+            delta += std::f64::consts::TAU;
+        }
+
+        Some(Arc {
+            center,
+            rx,
+            ry,
+            rotation: phi,
+            start,
+            sweep: delta,
+        })
+    }
+
+    /// The point on the ellipse at an angle
+    pub fn point(&self, angle: f64) -> Point {
+        let (sin, cos) = self.rotation.sin_cos();
+        let x = self.rx * angle.cos();
+        let y = self.ry * angle.sin();
+        Point::new(
+            self.center.x + cos * x - sin * y,
+            self.center.y + sin * x + cos * y,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{flatten, parse, Arc, Segment};
+    use polygonical::point::Point;
+
+    #[test]
+    fn parse_commands() {
+        let segments =
+            parse("M1,2 l1-1 H0 v.5 C1 1 2 2 3 3 s1 1 2 2 Q0 0 1 1 t1 1 A5 5 30 1 0 10 10 z")
+                .unwrap();
+        assert_eq!(segments.len(), 10);
+        assert_eq!(segments[1], Segment::LineTo(Point::new(2.0, 1.0)));
+        assert_eq!(segments[3], Segment::LineTo(Point::new(0.0, 1.5)));
+        assert_eq!(
+            segments[5],
+            Segment::CubicTo(
+                Point::new(4.0, 4.0),
+                Point::new(4.0, 4.0),
+                Point::new(5.0, 5.0)
+            )
+        );
+        assert_eq!(
+            segments[7],
+            Segment::QuadTo(Point::new(2.0, 2.0), Point::new(2.0, 2.0))
+        );
+        assert_eq!(
+            segments[8],
+            Segment::ArcTo {
+                rx: 5.0,
+                ry: 5.0,
+                rotation: 30.0,
+                large: true,
+                sweep: false,
+                to: Point::new(10.0, 10.0)
+            }
+        );
+    }
+
+    #[test]
+    fn parse_compact() {
+        // implicit line tos, packed numbers and flags
+        let segments = parse("m0 0 10 0-5.5.5e1a1 1 0 0110 10").unwrap();
+        assert_eq!(segments[1], Segment::LineTo(Point::new(10.0, 0.0)));
+        assert_eq!(segments[2], Segment::LineTo(Point::new(4.5, 5.0)));
+        assert!(matches!(
+            segments[3],
+            Segment::ArcTo {
+                large: false,
+                sweep: true,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(parse("L10 10").is_err());
+        assert!(parse("M10").is_err());
+        assert!(parse("M10 10 X5 5").is_err());
+        assert!(parse("M0 0 z 5").is_err());
+        assert!(parse("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn arcs() {
+        let arc = Arc::from_endpoints(
+            Point::new(0.0, 0.0),
+            Point::new(10.0, 0.0),
+            5.0,
+            5.0,
+            0.0,
+            false,
+            true,
+        )
+        .unwrap();
+        assert_eq!(arc.center, Point::new(5.0, 0.0));
+        assert!((arc.sweep - std::f64::consts::PI).abs() < 1e-9);
+
+        let lines = flatten(&parse("M0 0 A5 5 0 0 1 10 0").unwrap(), 0.01);
+        for p in &lines[0] {
+            assert!((super::distance(*p, Point::new(5.0, 0.0)) - 5.0).abs() < 1e-9);
+            assert!(p.y <= 1e-9);
+        }
+    }
+}
@@ -0,0 +1,470 @@
+//! Split a drawing into one document per pen so multi colour plots can be done one pass at a time
+use std::collections::HashMap;
+
+use polygonical::point::Point;
+
+use crate::convert::parse_colour;
+use crate::layer;
+use crate::marks::registration_target;
+use crate::page::Page;
+use crate::Element;
+
+/// Elements that put ink on the page
+const DRAWABLE: [&str; 10] = [
+    "path", "line", "polyline", "polygon", "rect", "circle", "ellipse", "text", "use", "image",
+];
+
+/// Elements that aren't drawn where they are, these go in every pen's document as anything could refer to them
+const SHARED: [&str; 14] = [
+    "defs",
+    "metadata",
+    "title",
+    "desc",
+    "style",
+    "symbol",
+    "clipPath",
+    "mask",
+    "marker",
+    "pattern",
+    "linearGradient",
+    "radialGradient",
+    "filter",
+    "sodipodi:namedview",
+];
+
+/// Elements that only hold other elements, these are kept in each pen's document if they hold something for that pen
+const CONTAINERS: [&str; 4] = ["svg", "g", "a", "switch"];
+
+/// The order the pens are returned in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Order {
+    /// Lightest colour first, so dark pens don't get dragged through and smear lighter ink
+    LightToDark,
+    /// Darkest colour first
+    DarkToLight,
+    /// The order the colours first appear in the document
+    Document,
+}
+
+/// One pass of a plot
+#[derive(Debug, Clone)]
+pub struct Pen {
+    /// What the mapping function called this pen, the colour itself when splitting by stroke
+    pub name: String,
+    /// The first stroke colour seen for this pen, as `#rrggbb` when it could be understood
+    pub colour: String,
+    /// Everything drawn with this pen, with the original root element and view box
+    pub document: Element,
+}
+
+/// Split a document into one document per stroke colour.
+///
+/// See [`split_by`] for the details.
+///
+/// ```
+/// use esvg::page::Page;
+/// use esvg::pens::{split_by_stroke, Order};
+/// use esvg::{create_document, shapes};
+///
+/// let page = Page::A4(96);
+/// let mut doc = create_document(&page);
+/// let mut group = esvg::Element::group();
+/// group.set("stroke", "black");
+/// group.add(&shapes::circle(page.center(), 50));
+/// let mut red = shapes::circle(page.center(), 100);
+/// red.set("style", "stroke:#f00");
+/// group.add(&red);
+/// doc.add(&group);
+///
+/// let pens = split_by_stroke(&doc, &page, Order::LightToDark);
+/// let colours: Vec<_> = pens.iter().map(|p| p.colour.as_str()).collect();
+/// assert_eq!(colours, ["#ff0000", "#000000"]);
+/// ```
+pub fn split_by_stroke(doc: &Element, page: &Page, order: Order) -> Vec<Pen> {
+    split_by(doc, page, order, |colour| colour.to_string())
+}
+
+/// Split a document into one document per pen, using a function to pick the pen for each stroke colour.
+///
+/// The stroke of every drawn element is worked out the way a renderer would: the `style` attribute wins over the
+/// `stroke` attribute, unset strokes are inherited from the parent, and `currentColor` uses the `color` property.
+/// Colours are normalised to `#rrggbb` before they are passed to the function, so `black`, `#000` and
+/// `rgb(0, 0, 0)` are all the same pen. `use` and `image` elements go by their stroke like shapes do. Elements with
+/// no stroke, hidden elements, and anything else a plotter can't draw are left out.
+///
+/// Each document keeps the original root element, groups, and transforms, along with definitions like `defs` and
+/// `clipPath`. Registration marks are drawn in the corners of the page's borders in the pen's colour so the passes
+/// can be lined up; they assume the document was made for the page with [`crate::create_document`].
+///
+/// ```
+/// use esvg::page::Page;
+/// use esvg::pens::{split_by, Order};
+///
+/// let doc = esvg::read::parse_string(
+///     r#"<svg viewBox="0 0 816 1056"><path stroke="navy" d="M0 0 L1 1" /><path stroke="blue" d="M1 0 L0 1" /></svg>"#
+///         .to_string(),
+/// )
+/// .unwrap();
+/// let pens = split_by(&doc, &Page::letter(96), Order::Document, |_| "blue pen".to_string());
+/// assert_eq!(pens.len(), 1);
+/// assert_eq!(pens[0].colour, "#000080");
+/// ```
+pub fn split_by<F>(doc: &Element, page: &Page, order: Order, pen: F) -> Vec<Pen>
+where
+    F: Fn(&str) -> String,
+{
+    let mut found = vec![];
+    collect_strokes(doc, &Paint::default(), &mut found);
+
+    // the first colour seen for each pen, in document order
+    let mut pens: Vec<(String, String)> = vec![];
+    let mut assigned = HashMap::new();
+    for colour in found {
+        let name = pen(&colour);
+        if !pens.iter().any(|(n, _)| *n == name) {
+            pens.push((name.clone(), colour.clone()));
+        }
+        assigned.insert(colour, name);
+    }
+
+    match order {
+        Order::Document => {}
+        Order::LightToDark => pens.sort_by(|a, b| compare(luminance(&b.1), luminance(&a.1))),
+        Order::DarkToLight => pens.sort_by(|a, b| compare(luminance(&a.1), luminance(&b.1))),
+    }
+
+    let root = Paint::of(doc, &Paint::default());
+    pens.into_iter()
+        .map(|(name, colour)| {
+            let mut document = doc.shallow_clone();
+            let keep = |c: &str| assigned.get(c) == Some(&name);
+            for child in doc.elements() {
+                if let Some(kept) = filter(child, &root, &keep) {
+                    document.add(&kept);
+                }
+            }
+            document.add(&registration_marks(page, &colour));
+            Pen {
+                name,
+                colour,
+                document,
+            }
+        })
+        .collect()
+}
+
+/// The properties that decide which pen draws an element
+#[derive(Debug, Clone, Default)]
+struct Paint {
+    stroke: Option<String>,
+    color: Option<String>,
+}
+
+impl Paint {
+    /// The paint of an element given the paint of its parent
+    fn of(el: &Element, parent: &Paint) -> Paint {
+        let color = match property(el, "color") {
+            Some(c) if c != "inherit" => Some(c),
+            _ => parent.color.clone(),
+        };
+        let stroke = match property(el, "stroke").as_deref() {
+            None | Some("inherit") => parent.stroke.clone(),
+            Some("currentcolor") => color.clone(),
+            Some(s) => Some(s.to_string()),
+        };
+        Paint { stroke, color }
+    }
+
+    /// The normalised stroke colour, if there is one
+    fn colour(&self) -> Option<String> {
+        self.stroke.as_deref().and_then(normalise_colour)
+    }
+}
+
+/// The normalised stroke colour of the last element in a chain of ancestors, if it has one
+pub(crate) fn stroke_colour(chain: &[&Element]) -> Option<String> {
+    chain
+        .iter()
+        .fold(Paint::default(), |paint, el| Paint::of(el, &paint))
+        .colour()
+}
+
+fn property(el: &Element, name: &str) -> Option<String> {
+    el.property(name).map(|v| v.to_lowercase())
+}
+
+fn collect_strokes(el: &Element, parent: &Paint, found: &mut Vec<String>) {
+    if !layer::is_visible(el) {
+        return;
+    }
+    let paint = Paint::of(el, parent);
+    if DRAWABLE.contains(&el.name.as_str()) {
+        if let Some(colour) = paint.colour() {
+            if !found.contains(&colour) {
+                found.push(colour);
+            }
+        }
+    } else if CONTAINERS.contains(&el.name.as_str()) {
+        for child in el.elements() {
+            collect_strokes(child, &paint, found);
+        }
+    }
+}
+
+/// Copy of an element with only the drawing for one pen, or None if there is nothing left
+fn filter(el: &Element, parent: &Paint, keep: &dyn Fn(&str) -> bool) -> Option<Element> {
+    let name = el.name.as_str();
+    if SHARED.contains(&name) {
+        return Some(el.clone());
+    }
+    if !layer::is_visible(el) || !(DRAWABLE.contains(&name) || CONTAINERS.contains(&name)) {
+        return None;
+    }
+
+    let paint = Paint::of(el, parent);
+    if DRAWABLE.contains(&name) {
+        return match paint.colour() {
+            Some(c) if keep(&c) => Some(el.clone()),
+            _ => None,
+        };
+    }
+
+    let mut result = el.shallow_clone();
+    let mut drawn = false;
+    for child in el.elements() {
+        if let Some(kept) = filter(child, &paint, keep) {
+            let kind = kept.name.as_str();
+            drawn |= DRAWABLE.contains(&kind) || CONTAINERS.contains(&kind);
+            result.add(&kept);
+        }
+    }
+    drawn.then_some(result)
+}
+
+/// Targets in the corners of the page's borders
+fn registration_marks(page: &Page, colour: &str) -> Element {
+    let b = &page.borders;
+    let dpi = page.dpi as f64;
+    let radius = (b.top.min(b.right).min(b.bottom).min(b.left) / 4.0).min(dpi / 8.0);
+
+    let mut group = Element::group();
+    group.set("class", "registration-marks");
+    if radius <= 0.0 {
+        return group;
+    }
+
+    for (x, y) in [
+        (b.left / 2.0, b.top / 2.0),
+        (page.width - b.right / 2.0, b.top / 2.0),
+        (b.left / 2.0, page.height - b.bottom / 2.0),
+        (page.width - b.right / 2.0, page.height - b.bottom / 2.0),
+    ] {
+        group.add(&registration_target(
+            Point::new(x, y),
+            radius,
+            dpi / 96.0,
+            colour,
+        ));
+    }
+    group
+}
+
+/// Turn any of the colour forms svg allows into `#rrggbb` so the same colour always ends up with the same pen.
+/// Colours that can't be understood are kept as they are, `none` and `transparent` are no stroke at all.
+pub(crate) fn normalise_colour(value: &str) -> Option<String> {
+    let value = value.trim().to_lowercase();
+    if value.is_empty() || value == "none" || value == "transparent" {
+        return None;
+    }
+
+    if let Some(hex) = value.strip_prefix('#') {
+        if hex.len() == 3 && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Some(hex.chars().fold("#".to_string(), |mut s, c| {
+                s.push(c);
+                s.push(c);
+                s
+            }));
+        }
+        if hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Some(value);
+        }
+    }
+
+    if let Some(args) = value.strip_prefix("rgb(").and_then(|v| v.strip_suffix(')')) {
+        let channels: Option<Vec<u8>> = args
+            .split(',')
+            .map(|c| {
+                let c = c.trim();
+                match c.strip_suffix('%') {
+                    Some(p) => p.trim().parse::<f64>().ok().map(|p| p / 100.0 * 255.0),
+                    None => c.parse::<f64>().ok(),
+                }
+                .map(|v| v.round().clamp(0.0, 255.0) as u8)
+            })
+            .collect();
+        if let Some([r, g, b]) = channels.as_deref() {
+            return Some(format!("#{r:02x}{g:02x}{b:02x}"));
+        }
+    }
+
+    match NAMED_COLOURS.iter().find(|(name, _)| *name == value) {
+        Some((_, hex)) => Some(hex.to_string()),
+        None => Some(value),
+    }
+}
+
+/// Perceived brightness between 0 and 1, None if the colour couldn't be understood
+fn luminance(colour: &str) -> Option<f64> {
+    let (r, g, b, _) = parse_colour(colour).ok()?;
+    Some(0.2126 * r + 0.7152 * g + 0.0722 * b)
+}
+
+/// Sort colours by luminance with ones we couldn't understand at the end
+fn compare(a: Option<f64>, b: Option<f64>) -> std::cmp::Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => a.total_cmp(&b),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => std::cmp::Ordering::Equal,
+    }
+}
+
+/// The common named colours, pens tend to come in these
+const NAMED_COLOURS: [(&str, &str); 24] = [
+    ("black", "#000000"),
+    ("white", "#ffffff"),
+    ("red", "#ff0000"),
+    ("lime", "#00ff00"),
+    ("green", "#008000"),
+    ("blue", "#0000ff"),
+    ("yellow", "#ffff00"),
+    ("cyan", "#00ffff"),
+    ("aqua", "#00ffff"),
+    ("magenta", "#ff00ff"),
+    ("fuchsia", "#ff00ff"),
+    ("gray", "#808080"),
+    ("grey", "#808080"),
+    ("silver", "#c0c0c0"),
+    ("maroon", "#800000"),
+    ("olive", "#808000"),
+    ("navy", "#000080"),
+    ("purple", "#800080"),
+    ("teal", "#008080"),
+    ("orange", "#ffa500"),
+    ("brown", "#a52a2a"),
+    ("pink", "#ffc0cb"),
+    ("gold", "#ffd700"),
+    ("violet", "#ee82ee"),
+];
+
+#[cfg(test)]
+mod tests {
+
+    use super::{normalise_colour, split_by, split_by_stroke, Order};
+    use crate::page::Page;
+    use crate::read::parse_string;
+
+    const DRAWING: &str = r##"<svg viewBox="0 0 816 1056" width="8.5in" height="11in">
+  <defs><marker id="arrow" /></defs>
+  <g stroke="black" transform="translate(10, 10)">
+    <path d="M0 0 L10 10" />
+    <g style="stroke:yellow">
+      <circle cx="5" cy="5" r="1" />
+      <rect x="1" y="1" width="2" height="2" stroke="inherit" />
+    </g>
+    <path d="M0 0 L10 10" style="stroke:#FF0" />
+    <path d="M0 0 L10 10" stroke="none" fill="black" />
+    <path d="M0 0 L10 10" stroke="red" style="display:none" />
+  </g>
+  <g color="rgb(0, 0, 255)"><line x1="0" y1="0" x2="1" y2="1" stroke="currentColor" /></g>
+</svg>"##;
+
+    #[test]
+    fn colours() {
+        assert_eq!(normalise_colour("#F0a").unwrap(), "#ff00aa");
+        assert_eq!(normalise_colour(" Navy ").unwrap(), "#000080");
+        assert_eq!(normalise_colour("rgb(255, 0, 50%)").unwrap(), "#ff0080");
+        assert_eq!(normalise_colour("url(#paint)").unwrap(), "url(#paint)");
+        assert!(normalise_colour("none").is_none());
+    }
+
+    #[test]
+    fn split_and_order() {
+        let doc = parse_string(DRAWING.to_string()).unwrap();
+        let page = Page::letter(96);
+
+        let pens = split_by_stroke(&doc, &page, Order::Document);
+        let names: Vec<_> = pens.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["#000000", "#ffff00", "#0000ff"]);
+
+        let pens = split_by_stroke(&doc, &page, Order::LightToDark);
+        let names: Vec<_> = pens.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["#ffff00", "#0000ff", "#000000"]);
+
+        let pens = split_by_stroke(&doc, &page, Order::DarkToLight);
+        assert_eq!(pens[0].name, "#000000");
+    }
+
+    #[test]
+    fn documents() {
+        let doc = parse_string(DRAWING.to_string()).unwrap();
+        let page = Page::letter(96);
+        let pens = split_by_stroke(&doc, &page, Order::Document);
+
+        let yellow = &pens[1].document;
+        assert_eq!(yellow.get("viewBox").unwrap(), "0 0 816 1056");
+        let text = yellow.to_pretty_string();
+        assert!(text.contains("<marker id=\"arrow\" />"));
+        assert!(text.contains("transform=\"translate(10, 10)\""));
+        assert_eq!(text.matches("<circle").count(), 1 + 4);
+        assert_eq!(text.matches("<rect").count(), 1);
+        assert_eq!(text.matches("<path").count(), 1 + 4);
+        assert!(!text.contains("<line"));
+        assert_eq!(text.matches("stroke=\"#ffff00\"").count(), 8);
+
+        // the blue group has nothing in it for this pen so it goes
+        assert_eq!(yellow.elements().filter(|e| e.name == "g").count(), 2);
+    }
+
+    #[test]
+    fn mapping() {
+        let doc = parse_string(DRAWING.to_string()).unwrap();
+        let pens = split_by(&doc, &Page::letter(96), Order::Document, |c| {
+            if c == "#000000" { "black" } else { "colour" }.to_string()
+        });
+        assert_eq!(pens.len(), 2);
+        assert_eq!(pens[1].colour, "#ffff00");
+        assert!(pens[1].document.to_pretty_string().contains("<line"));
+    }
+
+    #[test]
+    fn uses_and_images() {
+        let doc = parse_string(
+            r##"<svg viewBox="0 0 100 100">
+  <defs><circle id="dot" r="5" /></defs>
+  <use href="#dot" stroke="red" />
+  <use href="#dot" x="50" stroke="blue" />
+  <image href="photo.png" width="10" height="10" />
+</svg>"##
+                .to_string(),
+        )
+        .unwrap();
+        let pens = split_by_stroke(&doc, &Page::letter(96), Order::Document);
+        let names: Vec<_> = pens.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["#ff0000", "#0000ff"]);
+        for pen in &pens {
+            let uses: Vec<_> = pen
+                .document
+                .elements()
+                .filter(|e| e.name == "use")
+                .collect();
+            assert_eq!(uses.len(), 1);
+            assert_eq!(
+                normalise_colour(&uses[0].get("stroke").unwrap()).unwrap(),
+                pen.name
+            );
+            assert!(pen.document.find_by_id("dot").is_some());
+            assert!(!pen.document.to_string().contains("<image"));
+        }
+    }
+}
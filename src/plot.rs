@@ -0,0 +1,790 @@
+//! Reorder and join strokes so a pen plotter spends less time travelling with the pen up
+use std::fmt;
+
+use polygonical::point::Point;
+
+use crate::error::Error;
+use crate::flatten;
+use crate::layer;
+use crate::path::{self, distance};
+use crate::{Element, Node};
+
+/// Attributes that are turned into the points of the new paths, along with the transform. Everything else is copied
+/// on to them, and strokes are only joined if all of that matches.
+const GEOMETRY: [&str; 17] = [
+    "d",
+    "x",
+    "y",
+    "width",
+    "height",
+    "rx",
+    "ry",
+    "cx",
+    "cy",
+    "r",
+    "x1",
+    "y1",
+    "x2",
+    "y2",
+    "points",
+    "pathLength",
+    "transform",
+];
+
+/// Elements that are turned into strokes
+const SHAPES: [&str; 7] = [
+    "path", "line", "polyline", "polygon", "rect", "circle", "ellipse",
+];
+
+/// Properties that would be lost by turning a shape into strokes, shapes with any of these are left alone
+const KEPT_PROPERTIES: [&str; 7] = [
+    "clip-path",
+    "mask",
+    "filter",
+    "marker",
+    "marker-start",
+    "marker-mid",
+    "marker-end",
+];
+
+/// How hard the optimiser works and what it is allowed to change
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    /// Strokes whose ends are closer than this are joined into one, in user units
+    pub join_tolerance: f64,
+    /// Allow strokes to be drawn backwards. 2-opt improves the order by drawing runs of strokes backwards, so it is
+    /// only used when this is on.
+    pub reverse: bool,
+    /// How many times to look for improvements to the order once it has been built, zero to skip this step
+    pub passes: usize,
+    /// How far apart in the order two strokes can be for 2-opt to try reversing the run between them. Each pass
+    /// takes time in proportion to this times the number of strokes.
+    pub window: usize,
+    /// How far curves can be from the straight lines that replace them, in user units
+    pub curve_tolerance: f64,
+    /// Where the pen starts
+    pub home: Point,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            join_tolerance: 0.1,
+            reverse: true,
+            passes: 10,
+            window: 50,
+            curve_tolerance: 0.1,
+            home: Point::zero(),
+        }
+    }
+}
+
+/// What the optimiser managed
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Report {
+    pub strokes_before: usize,
+    pub strokes_after: usize,
+    /// Distance travelled with the pen up, in user units
+    pub pen_up_before: f64,
+    pub pen_up_after: f64,
+}
+
+impl Report {
+    /// The fraction of the pen up travel that was removed
+    pub fn saving(&self) -> f64 {
+        if self.pen_up_before > 0.0 {
+            1.0 - self.pen_up_after / self.pen_up_before
+        } else {
+            0.0
+        }
+    }
+
+    fn add(&mut self, other: &Report) {
+        self.strokes_before += other.strokes_before;
+        self.strokes_after += other.strokes_after;
+        self.pen_up_before += other.pen_up_before;
+        self.pen_up_after += other.pen_up_after;
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            formatter,
+            "pen up travel {:.1} -> {:.1} ({:.0}% less), strokes {} -> {}",
+            self.pen_up_before,
+            self.pen_up_after,
+            self.saving() * 100.0,
+            self.strokes_before,
+            self.strokes_after
+        )
+    }
+}
+
+/// Optimise the plot order of a document in place.
+///
+/// The shapes directly in each group, including layers and the document itself, are optimised together, as groups
+/// can have their own transforms, clip paths, and styles, and layers are usually plotted with different pens. The
+/// shapes are turned into polylines, put in nearest neighbour order, improved with 2-opt if they can be reversed,
+/// and joined where their ends touch. They are then written back as paths where the first of them was, with every
+/// attribute of the shape other than its geometry, and any transform of its own applied so they draw in the same place.
+///
+/// Groups, text, images, definitions, hidden elements, and shapes with an id, clip path, mask, filter, or markers are
+/// left where they were. So are shapes that are filled, which a pen can't draw, and shapes with no stroke. Styles from
+/// a stylesheet can't be seen, so a shape with a class is taken to have a stroke, but its fill still has to be turned
+/// off by an attribute or style.
+///
+/// ```
+/// use esvg::plot::{optimise, Options};
+/// use esvg::read::parse_string;
+///
+/// let mut doc = parse_string(
+///     r#"<svg stroke="black" fill="none"><path d="M100 0 L110 0" /><path d="M0 0 L10 0" /><path d="M20 0 L10 0" /></svg>"#
+///         .to_string(),
+/// )
+/// .unwrap();
+/// let report = optimise(&mut doc, &Options::default()).unwrap();
+/// assert_eq!(report.strokes_after, 2);
+/// assert_eq!(report.pen_up_after, 80.0);
+/// ```
+pub fn optimise(doc: &mut Element, options: &Options) -> Result<Report, Error> {
+    let mut report = Report::default();
+    rewrite(doc, options.curve_tolerance, &mut |strokes| {
+        let (strokes, layer_report) = optimise_strokes(strokes, options);
+        report.add(&layer_report);
+        strokes
+    })?;
+    Ok(report)
+}
+
+/// Replace the shapes in each group of a document with paths made from whatever the function does to their
+/// strokes. The new paths go where the first of the shapes was, and everything else is left where it was: groups
+/// with their attributes, text, images, definitions, and shapes that flattening would change, like those with an id,
+/// a clip path, markers, or a fill.
+pub(crate) fn rewrite<F>(el: &mut Element, tolerance: f64, f: &mut F) -> Result<(), Error>
+where
+    F: FnMut(Vec<Stroke>) -> Vec<Stroke>,
+{
+    let paint = Paint::of(el, &Paint::default());
+    rewrite_in(el, &paint, tolerance, f)
+}
+
+fn rewrite_in<F>(el: &mut Element, paint: &Paint, tolerance: f64, f: &mut F) -> Result<(), Error>
+where
+    F: FnMut(Vec<Stroke>) -> Vec<Stroke>,
+{
+    for child in el.elements_mut() {
+        if !flatten::NOT_DRAWN.contains(&child.name.as_str())
+            && !SHAPES.contains(&child.name.as_str())
+            && layer::is_visible(child)
+        {
+            let paint = Paint::of(child, paint);
+            rewrite_in(child, &paint, tolerance, f)?;
+        }
+    }
+
+    let mut shapes = Element::group();
+    let mut first = None;
+    let mut kept = vec![];
+    for child in el.children.drain(..) {
+        match child {
+            Node::Element(e) if rewritable(&e, paint) => {
+                first.get_or_insert(kept.len());
+                shapes.add_node(Node::Element(e));
+            }
+            other => kept.push(other),
+        }
+    }
+    let Some(at) = first else {
+        el.children = kept;
+        return Ok(());
+    };
+
+    // the paths stay in the same group, so only the shapes' own attributes need copying
+    let mut strokes = vec![];
+    flatten::walk(&shapes, tolerance, &mut |chain, lines| {
+        let style = drawn_with(chain[chain.len() - 1]);
+        strokes.extend(lines.into_iter().map(|points| Stroke {
+            points,
+            style: style.clone(),
+        }));
+    })?;
+
+    let paths = f(strokes).into_iter().map(|s| {
+        let mut el = path::Data::from_points(&s.points).to_path();
+        for (k, v) in s.style {
+            el.set(k, v);
+        }
+        Node::Element(el)
+    });
+    kept.splice(at..at, paths);
+    el.children = kept;
+
+    Ok(())
+}
+
+/// Optimise the order of some polylines, see [`optimise`]. All of them can be joined to each other.
+///
+/// ```
+/// use esvg::plot::{optimise_lines, Options};
+/// use polygonical::point::Point;
+///
+/// let lines = vec![
+///     vec![Point::new(10.0, 0.0), Point::new(10.0, 10.0)],
+///     vec![Point::new(0.0, 10.0), Point::new(0.0, 0.0)],
+/// ];
+/// let (lines, report) = optimise_lines(lines, &Options::default());
+/// assert_eq!(lines[0][0], Point::new(0.0, 0.0));
+/// assert_eq!(report.pen_up_before, 20.0);
+/// assert_eq!(report.pen_up_after, 10.0);
+/// ```
+pub fn optimise_lines(lines: Vec<Vec<Point>>, options: &Options) -> (Vec<Vec<Point>>, Report) {
+    let strokes = lines
+        .into_iter()
+        .map(|points| Stroke {
+            points,
+            style: vec![],
+        })
+        .collect();
+    let (strokes, report) = optimise_strokes(strokes, options);
+    (strokes.into_iter().map(|s| s.points).collect(), report)
+}
+
+/// A line drawn without lifting the pen
+#[derive(Debug, Clone)]
+pub(crate) struct Stroke {
+    pub(crate) points: Vec<Point>,
+    /// the attributes it is drawn with, other than its geometry
+    pub(crate) style: Vec<(String, String)>,
+}
+
+impl Stroke {
+    fn start(&self) -> Point {
+        self.points[0]
+    }
+
+    fn end(&self) -> Point {
+        self.points[self.points.len() - 1]
+    }
+
+    fn reverse(&mut self) {
+        self.points.reverse();
+    }
+}
+
+/// The fill and stroke in effect on an element, and whether a stylesheet could be changing them
+#[derive(Debug, Clone)]
+struct Paint {
+    fill: String,
+    stroke: String,
+    classed: bool,
+}
+
+impl Default for Paint {
+    fn default() -> Self {
+        Paint {
+            fill: "black".to_string(),
+            stroke: "none".to_string(),
+            classed: false,
+        }
+    }
+}
+
+impl Paint {
+    fn of(el: &Element, parent: &Paint) -> Paint {
+        let property = |name: &str| {
+            el.property(name)
+                .map(|v| v.to_lowercase())
+                .filter(|v| v != "inherit")
+        };
+        Paint {
+            fill: property("fill").unwrap_or_else(|| parent.fill.clone()),
+            stroke: property("stroke").unwrap_or_else(|| parent.stroke.clone()),
+            classed: parent.classed || el.get("class").is_some(),
+        }
+    }
+
+    fn stroked(&self) -> bool {
+        self.classed || !(self.stroke == "none" || self.stroke == "transparent")
+    }
+}
+
+/// true if the element is a visible, stroked, unfilled shape that can be replaced by plain paths without changing
+/// how it draws. Lines are never filled, whatever their fill says.
+fn rewritable(el: &Element, parent: &Paint) -> bool {
+    if !SHAPES.contains(&el.name.as_str())
+        || !layer::is_visible(el)
+        || el.get("id").is_some()
+        || el.elements().next().is_some()
+        || KEPT_PROPERTIES.iter().any(|p| el.property(p).is_some())
+    {
+        return false;
+    }
+    let paint = Paint::of(el, parent);
+    paint.stroked() && (el.name == "line" || paint.fill == "none")
+}
+
+/// The attributes of a shape other than its geometry, sorted so shapes drawn the same way compare equal. A line's fill
+/// is left off as the path replacing it would be filled.
+fn drawn_with(el: &Element) -> Vec<(String, String)> {
+    let line = el.name == "line";
+    let mut result: Vec<(String, String)> = el
+        .attributes
+        .iter()
+        .filter(|(k, _)| !(GEOMETRY.contains(&k.as_str()) || (line && *k == "fill")))
+        .map(|(k, v)| {
+            let mut v = v.to_string_bare();
+            if line && k == "style" {
+                v = v
+                    .split(';')
+                    .filter(|p| !matches!(p.split_once(':'), Some((k, _)) if k.trim() == "fill"))
+                    .collect::<Vec<_>>()
+                    .join(";");
+            }
+            (k.clone(), v)
+        })
+        .collect();
+    result.sort();
+    result
+}
+
+fn optimise_strokes(strokes: Vec<Stroke>, options: &Options) -> (Vec<Stroke>, Report) {
+    let strokes: Vec<Stroke> = strokes
+        .into_iter()
+        .filter(|s| !s.points.is_empty())
+        .collect();
+    let mut report = Report {
+        strokes_before: strokes.len(),
+        pen_up_before: travel(&strokes, options.home),
+        ..Report::default()
+    };
+
+    let mut ordered = nearest_neighbour(strokes, options);
+    if options.reverse {
+        two_opt(&mut ordered, options);
+    }
+    let joined = join(ordered, options.join_tolerance);
+
+    report.strokes_after = joined.len();
+    report.pen_up_after = travel(&joined, options.home);
+    (joined, report)
+}
+
+/// Distance travelled between the strokes, starting from home
+fn travel(strokes: &[Stroke], home: Point) -> f64 {
+    let mut at = home;
+    let mut total = 0.0;
+    for s in strokes {
+        total += distance(at, s.start());
+        at = s.end();
+    }
+    total
+}
+
+/// Always draw the closest stroke next. The stroke ends are kept in a grid so only the ones near the pen are
+/// looked at.
+fn nearest_neighbour(strokes: Vec<Stroke>, options: &Options) -> Vec<Stroke> {
+    let mut result = Vec::with_capacity(strokes.len());
+    let mut remaining: Vec<Option<Stroke>> = strokes.into_iter().map(Some).collect();
+    let mut left = remaining.len();
+    let mut grid = Grid::new(&remaining, options.reverse);
+    let mut at = options.home;
+
+    while left > 0 {
+        // a grid with mostly empty cells is slow to search, so shrink it as strokes are used up
+        if left * 4 < grid.size {
+            grid = Grid::new(&remaining, options.reverse);
+        }
+        let (i, backward) = grid.closest(at, &remaining);
+        let mut next = remaining[i].take().unwrap();
+        grid.remove(i, &next, options.reverse);
+        left -= 1;
+        if backward {
+            next.reverse();
+        }
+        at = next.end();
+        result.push(next);
+    }
+
+    result
+}
+
+/// Stroke ends bucketed by where they are
+struct Grid {
+    min: Point,
+    cell: f64,
+    columns: usize,
+    rows: usize,
+    /// the strokes with an end in each cell, and whether it is the end rather than the start
+    cells: Vec<Vec<(usize, bool)>>,
+    /// how many strokes were left when the grid was built
+    size: usize,
+}
+
+impl Grid {
+    fn new(strokes: &[Option<Stroke>], ends: bool) -> Grid {
+        let points = || {
+            strokes.iter().flatten().flat_map(|s| {
+                let end = ends.then(|| s.end());
+                std::iter::once(s.start()).chain(end)
+            })
+        };
+        let size = strokes.iter().flatten().count();
+        let (mut min, mut max) = (
+            Point::new(f64::MAX, f64::MAX),
+            Point::new(f64::MIN, f64::MIN),
+        );
+        for p in points() {
+            min = Point::new(min.x.min(p.x), min.y.min(p.y));
+            max = Point::new(max.x.max(p.x), max.y.max(p.y));
+        }
+        // about one stroke per cell
+        let side = (size as f64).sqrt().ceil().max(1.0);
+        let cell = ((max.x - min.x).max(max.y - min.y) / side).max(1e-9);
+        let columns = ((max.x - min.x) / cell) as usize + 1;
+        let rows = ((max.y - min.y) / cell) as usize + 1;
+
+        let mut grid = Grid {
+            min,
+            cell,
+            columns,
+            rows,
+            cells: vec![vec![]; columns * rows],
+            size,
+        };
+        for (i, s) in strokes.iter().enumerate() {
+            if let Some(s) = s {
+                let start = grid.index(s.start());
+                grid.cells[start].push((i, false));
+                if ends {
+                    let end = grid.index(s.end());
+                    grid.cells[end].push((i, true));
+                }
+            }
+        }
+        grid
+    }
+
+    /// The column and row of the cell a point is in, or the closest cell for points outside the grid
+    fn position(&self, p: Point) -> (usize, usize) {
+        let column = ((p.x - self.min.x) / self.cell).max(0.0) as usize;
+        let row = ((p.y - self.min.y) / self.cell).max(0.0) as usize;
+        (column.min(self.columns - 1), row.min(self.rows - 1))
+    }
+
+    fn index(&self, p: Point) -> usize {
+        let (column, row) = self.position(p);
+        row * self.columns + column
+    }
+
+    fn remove(&mut self, i: usize, stroke: &Stroke, ends: bool) {
+        let start = self.index(stroke.start());
+        self.cells[start].retain(|(j, _)| *j != i);
+        if ends {
+            let end = self.index(stroke.end());
+            self.cells[end].retain(|(j, _)| *j != i);
+        }
+    }
+
+    /// The stroke with an end closest to a point, looking in rings of cells further and further out until nothing
+    /// further out can be closer
+    fn closest(&self, at: Point, strokes: &[Option<Stroke>]) -> (usize, bool) {
+        let (column, row) = self.position(at);
+        let mut best = (0, false, f64::MAX);
+        for ring in 0..=self.columns.max(self.rows) {
+            let left = column as isize - ring as isize;
+            let right = column + ring;
+            let top = row as isize - ring as isize;
+            let bottom = row + ring;
+            for r in top.max(0) as usize..=bottom.min(self.rows - 1) {
+                for c in left.max(0) as usize..=right.min(self.columns - 1) {
+                    // only the edge of the ring, the inside has been done
+                    let edge = r as isize == top || r == bottom || c as isize == left || c == right;
+                    if !edge {
+                        continue;
+                    }
+                    for &(i, backward) in &self.cells[r * self.columns + c] {
+                        let s = strokes[i].as_ref().unwrap();
+                        let p = if backward { s.end() } else { s.start() };
+                        let d = distance(at, p);
+                        // ties go to the earliest stroke, drawn forwards, to keep the order stable
+                        if d < best.2 || (d == best.2 && (i, backward) < (best.0, best.1)) {
+                            best = (i, backward, d);
+                        }
+                    }
+                }
+            }
+            // anything in the next ring out is at least this far away
+            if best.2 <= ring as f64 * self.cell {
+                break;
+            }
+        }
+        (best.0, best.1)
+    }
+}
+
+/// Reverse runs of strokes when that shortens the trip, until nothing improves or we run out of passes. Only runs
+/// up to the window long are tried, so a pass is linear in the number of strokes.
+fn two_opt(strokes: &mut [Stroke], options: &Options) {
+    let n = strokes.len();
+    for _ in 0..options.passes {
+        let mut improved = false;
+        for i in 0..n {
+            for j in i + 1..n.min(i + 1 + options.window) {
+                let before = if i == 0 {
+                    options.home
+                } else {
+                    strokes[i - 1].end()
+                };
+                let after = strokes.get(j + 1).map(|s| s.start());
+
+                let old = distance(before, strokes[i].start())
+                    + after.map_or(0.0, |a| distance(strokes[j].end(), a));
+                let new = distance(before, strokes[j].end())
+                    + after.map_or(0.0, |a| distance(strokes[i].start(), a));
+
+                if new < old - 1e-9 {
+                    strokes[i..=j].reverse();
+                    strokes[i..=j].iter_mut().for_each(Stroke::reverse);
+                    improved = true;
+                }
+            }
+        }
+        if !improved {
+            break;
+        }
+    }
+}
+
+/// Join strokes that follow each other with the same style when the gap between them is within tolerance
+fn join(strokes: Vec<Stroke>, tolerance: f64) -> Vec<Stroke> {
+    let mut result: Vec<Stroke> = vec![];
+    for s in strokes {
+        match result.last_mut() {
+            Some(last) if last.style == s.style && distance(last.end(), s.start()) <= tolerance => {
+                let skip = usize::from(distance(last.end(), s.start()) == 0.0);
+                last.points.extend(s.points.into_iter().skip(skip));
+            }
+            _ => result.push(s),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{nearest_neighbour, optimise, optimise_lines, travel, two_opt, Options, Stroke};
+    use crate::layer;
+    use crate::path::distance;
+    use crate::read::parse_string;
+    use polygonical::point::Point;
+
+    fn line(x1: f64, y1: f64, x2: f64, y2: f64) -> Vec<Point> {
+        vec![Point::new(x1, y1), Point::new(x2, y2)]
+    }
+
+    #[test]
+    fn reorder_and_reverse() {
+        // a row of short dashes given in a bad order, half of them backwards
+        let lines = vec![
+            line(40.0, 0.0, 45.0, 0.0),
+            line(5.0, 0.0, 0.0, 0.0),
+            line(20.0, 0.0, 25.0, 0.0),
+            line(35.0, 0.0, 30.0, 0.0),
+            line(10.0, 0.0, 15.0, 0.0),
+        ];
+        let (result, report) = optimise_lines(lines, &Options::default());
+        let starts: Vec<f64> = result.iter().map(|l| l[0].x).collect();
+        assert_eq!(starts, [0.0, 10.0, 20.0, 30.0, 40.0]);
+        assert_eq!(report.pen_up_before, 40.0 + 40.0 + 20.0 + 10.0 + 20.0);
+        assert_eq!(report.pen_up_after, 20.0);
+
+        let options = Options {
+            reverse: false,
+            ..Options::default()
+        };
+        let (result, _) = optimise_lines(vec![line(5.0, 0.0, 0.0, 0.0)], &options);
+        assert_eq!(result[0][0].x, 5.0);
+    }
+
+    #[test]
+    fn two_opt_untangles() {
+        let mut strokes: Vec<Stroke> = [5.0, 1.0, 3.0]
+            .iter()
+            .map(|x| Stroke {
+                points: line(*x, 0.0, *x, 0.0),
+                style: vec![],
+            })
+            .collect();
+        let options = Options::default();
+        assert_eq!(travel(&strokes, options.home), 5.0 + 4.0 + 2.0);
+
+        two_opt(&mut strokes, &options);
+        let starts: Vec<f64> = strokes.iter().map(|s| s.start().x).collect();
+        assert_eq!(starts, [1.0, 3.0, 5.0]);
+        assert_eq!(travel(&strokes, options.home), 1.0 + 2.0 + 2.0);
+    }
+
+    #[test]
+    fn join_touching() {
+        let lines = vec![
+            line(0.0, 0.0, 10.0, 0.0),
+            line(10.0, 10.0, 10.05, 0.0),
+            line(50.0, 50.0, 60.0, 60.0),
+        ];
+        let (result, report) = optimise_lines(lines, &Options::default());
+        assert_eq!(report.strokes_after, 2);
+        assert_eq!(result[0].len(), 4);
+    }
+
+    #[test]
+    fn layers_kept_apart() {
+        let mut doc = parse_string(
+            r#"<svg xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape">
+  <defs><marker id="m" /></defs>
+  <g inkscape:groupmode="layer" inkscape:label="red" stroke="red" fill="none" transform="translate(5, 5)">
+    <g stroke-width="2"><line x1="10" y1="0" x2="20" y2="0" /></g>
+    <rect x="0" y="0" width="5" height="5" stroke="blue" />
+    <text>kept</text>
+  </g>
+  <g inkscape:groupmode="layer" inkscape:label="black" stroke="black" fill="none">
+    <path d="M0 0 L1 1" />
+  </g>
+</svg>"#
+                .to_string(),
+        )
+        .unwrap();
+        let report = optimise(&mut doc, &Options::default()).unwrap();
+        assert_eq!(report.strokes_before, 3);
+
+        let red = layer::find_layer(&doc, "red").unwrap();
+        let el = red.element();
+        assert_eq!(el.get("transform").unwrap(), "translate(5, 5)");
+        let names: Vec<&str> = el.elements().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["g", "path", "text"]);
+        let group = el.elements().next().unwrap();
+        assert_eq!(group.get("stroke-width").unwrap(), "2");
+        let line = group.elements().next().unwrap();
+        assert_eq!(line.get("d").unwrap(), "M10.000 0.000 L20.000 0.000");
+        assert!(line.get("stroke-width").is_none());
+        let rect = el.elements().nth(1).unwrap();
+        assert_eq!(rect.get("stroke").unwrap(), "blue");
+
+        assert_eq!(doc.elements().next().unwrap().name, "defs");
+    }
+
+    #[test]
+    fn mixed_group_survives() {
+        let mut doc = parse_string(
+            r#"<svg>
+  <defs><clipPath id="clip"><rect width="50" height="50" /></clipPath></defs>
+  <g id="art" clip-path="url(#clip)" stroke="red" fill="none">
+    <path d="M20 0 L30 0" />
+    <text>label</text>
+    <image href="photo.png" width="10" height="10" />
+    <path d="M0 0 L10 0" />
+    <circle id="dot" r="2" />
+    <line x1="40" y1="0" x2="50" y2="0" marker-end="url(#arrow)" />
+  </g>
+</svg>"#
+                .to_string(),
+        )
+        .unwrap();
+        let report = optimise(&mut doc, &Options::default()).unwrap();
+        assert_eq!(report.strokes_before, 2);
+
+        let group = doc.find_by_id("art").unwrap();
+        assert_eq!(group.get("clip-path").unwrap(), "url(#clip)");
+        assert_eq!(group.get("stroke").unwrap(), "red");
+        let names: Vec<&str> = group.elements().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["path", "path", "text", "image", "circle", "line"]);
+        let first = group.elements().next().unwrap();
+        assert_eq!(first.get("d").unwrap(), "M0.000 0.000 L10.000 0.000");
+        assert!(doc.find_by_id("dot").is_some());
+        assert!(doc.find_by_id("clip").is_some());
+    }
+
+    #[test]
+    fn attributes_kept() {
+        let mut doc = parse_string(
+            r#"<svg>
+  <style>.red { stroke: red }</style>
+  <path class="red" fill="none" d="M20 0 L30 0" />
+  <line x1="0" y1="0" x2="10" y2="0" stroke="blue" style="fill:green;opacity:0.5" vector-effect="non-scaling-stroke" />
+  <rect x="50" y="0" width="10" height="10" fill="blue" />
+  <path d="M100 0 L110 0" fill="none" />
+</svg>"#
+                .to_string(),
+        )
+        .unwrap();
+        let report = optimise(&mut doc, &Options::default()).unwrap();
+        assert_eq!(report.strokes_before, 2);
+
+        let names: Vec<&str> = doc.elements().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["style", "path", "path", "rect", "path"]);
+        let line = doc.elements().nth(1).unwrap();
+        assert_eq!(line.get("d").unwrap(), "M0.000 0.000 L10.000 0.000");
+        assert_eq!(line.get("fill").unwrap(), "none");
+        assert_eq!(line.get("style").unwrap(), "opacity:0.5");
+        assert_eq!(line.get("vector-effect").unwrap(), "non-scaling-stroke");
+        let styled = doc.elements().nth(2).unwrap();
+        assert_eq!(styled.get("class").unwrap(), "red");
+        assert_eq!(styled.get("d").unwrap(), "M20.000 0.000 L30.000 0.000");
+
+        // filled and unstroked shapes are left as they were
+        let rect = doc.elements().nth(3).unwrap();
+        assert_eq!(rect.get("fill").unwrap(), "blue");
+        assert_eq!(rect.get("width").unwrap(), "10");
+        let unstroked = doc.elements().nth(4).unwrap();
+        assert_eq!(unstroked.get("d").unwrap(), "M100 0 L110 0");
+    }
+
+    #[test]
+    fn grid_matches_brute_force() {
+        // a simple generator so the test is the same every time
+        let mut seed = 12345u64;
+        let mut random = || {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+            (seed >> 33) as f64 / (1u64 << 31) as f64 * 100.0
+        };
+        let strokes: Vec<Stroke> = (0..500)
+            .map(|_| Stroke {
+                points: line(random(), random(), random(), random()),
+                style: vec![],
+            })
+            .collect();
+
+        for reverse in [true, false] {
+            let options = Options {
+                reverse,
+                home: Point::new(-50.0, 30.0),
+                ..Options::default()
+            };
+            let fast = nearest_neighbour(strokes.clone(), &options);
+
+            let mut remaining = strokes.clone();
+            let mut at = options.home;
+            for s in &fast {
+                let best = remaining
+                    .iter()
+                    .flat_map(|r| {
+                        let end = reverse.then(|| distance(at, r.end()));
+                        std::iter::once(distance(at, r.start())).chain(end)
+                    })
+                    .fold(f64::MAX, f64::min);
+                assert_eq!(distance(at, s.start()), best);
+                let i = remaining
+                    .iter()
+                    .position(|r| {
+                        (r.start() == s.start() && r.end() == s.end())
+                            || (r.start() == s.end() && r.end() == s.start())
+                    })
+                    .unwrap();
+                remaining.swap_remove(i);
+                at = s.end();
+            }
+            assert!(remaining.is_empty());
+        }
+    }
+}
@@ -0,0 +1,1023 @@
+//! Draw documents into pixels, for previews and checking drawings look right in tests
+use std::fs::File;
+use std::io::Write;
+
+use polygonical::point::Point;
+
+use crate::convert::parse_colour;
+use crate::error::Error;
+use crate::flatten;
+use crate::layer;
+use crate::length::{Context, CSS_DPI};
+use crate::page::{number_list, svg_length, Page};
+use crate::path;
+use crate::pens::normalise_colour;
+use crate::transform::Transform;
+use crate::Element;
+
+mod png;
+
+/// An image as 8 bit RGBA pixels, not premultiplied, in rows from the top left
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    /// A fully transparent image
+    pub fn new(width: u32, height: u32) -> Self {
+        Image {
+            width,
+            height,
+            pixels: vec![0; width as usize * height as usize * 4],
+        }
+    }
+
+    /// The red, green, blue, and alpha of a pixel
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        [
+            self.pixels[i],
+            self.pixels[i + 1],
+            self.pixels[i + 2],
+            self.pixels[i + 3],
+        ]
+    }
+
+    /// The image as the bytes of a PNG file
+    pub fn to_png(&self) -> Vec<u8> {
+        png::encode(self.width, self.height, &self.pixels)
+    }
+
+    /// Write the image to a PNG file at the given path
+    pub fn save_png(&self, path: &str) -> Result<(), Error> {
+        let mut f = File::create(path)?;
+        f.write_all(&self.to_png())?;
+        Ok(())
+    }
+}
+
+/// Draw a document the size of a page, one pixel for each of the page's pixels so the resolution follows its dpi.
+///
+/// Paths, the basic shapes, fill rules, stroke widths, caps, and joins, opacity, solid colours, and linear and radial
+/// gradients are drawn, with anti-aliasing. Text, images, markers, clipping, masks, filters, and dashes are not.
+///
+/// ```
+/// use esvg::page::Page;
+/// use esvg::raster::render;
+/// use esvg::read::parse_string;
+///
+/// let doc = parse_string(
+///     r#"<svg><rect x="10" y="10" width="20" height="20" fill="red" /></svg>"#.to_string(),
+/// )
+/// .unwrap();
+/// let image = render(&doc, &Page::A6(96)).unwrap();
+/// assert_eq!((image.width, image.height), (397, 560));
+/// assert_eq!(image.pixel(15, 15), [255, 0, 0, 255]);
+/// assert_eq!(image.pixel(5, 5), [0, 0, 0, 0]);
+/// ```
+pub fn render(doc: &Element, page: &Page) -> Result<Image, Error> {
+    let width = page.width.ceil().max(1.0) as usize;
+    let height = page.height.ceil().max(1.0) as usize;
+    let mut canvas = Canvas::new(width, height);
+
+    let (transform, viewport) = view_box(doc, page)?;
+    let renderer = Renderer { doc, viewport };
+    let style = Style::of(doc, &Style::default());
+    for child in doc.elements() {
+        renderer.draw(child, &style, &transform, &mut canvas)?;
+    }
+    Ok(canvas.image())
+}
+
+/// Fit the document's view box into the page, keeping its shape and centring it. Also gives the width and height
+/// percentages in user space are of, the view box's if there is one otherwise the page's.
+fn view_box(doc: &Element, page: &Page) -> Result<(Transform, (f64, f64)), Error> {
+    let page_size = (page.width, page.height);
+    let Some(value) = doc.get("viewBox") else {
+        return Ok((Transform::identity(), page_size));
+    };
+    let numbers = number_list(&value)
+        .map_err(|_| Error::InvalidAttribute("viewBox".to_string(), value.clone()))?;
+    let [x, y, w, h] = numbers[..] else {
+        return Err(Error::InvalidAttribute("viewBox".to_string(), value));
+    };
+    if w <= 0.0 || h <= 0.0 {
+        return Ok((Transform::identity(), page_size));
+    }
+    let scale = (page.width / w).min(page.height / h);
+    let transform = Transform::translate(-x, -y)
+        .then(&Transform::scale(scale, scale))
+        .then(&Transform::translate(
+            (page.width - w * scale) / 2.0,
+            (page.height - h * scale) / 2.0,
+        ));
+    Ok((transform, (w, h)))
+}
+
+/// Sub scanlines in each row of pixels, the vertical part of the anti-aliasing
+const SAMPLES: usize = 5;
+/// How far in pixels curves and round joins are allowed to be from the real shape
+const TOLERANCE: f64 = 0.2;
+
+/// Elements that can contain drawing
+const CONTAINERS: [&str; 4] = ["svg", "g", "a", "switch"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FillRule {
+    NonZero,
+    EvenOdd,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Cap {
+    Butt,
+    Round,
+    Square,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Join {
+    Miter,
+    Round,
+    Bevel,
+}
+
+/// The inherited presentation properties
+#[derive(Debug, Clone)]
+struct Style {
+    fill: Option<String>,
+    fill_rule: FillRule,
+    fill_opacity: f64,
+    stroke: Option<String>,
+    stroke_width: f64,
+    stroke_opacity: f64,
+    cap: Cap,
+    join: Join,
+    miter_limit: f64,
+    color: String,
+    visible: bool,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Style {
+            fill: Some("black".to_string()),
+            fill_rule: FillRule::NonZero,
+            fill_opacity: 1.0,
+            stroke: None,
+            stroke_width: 1.0,
+            stroke_opacity: 1.0,
+            cap: Cap::Butt,
+            join: Join::Miter,
+            miter_limit: 4.0,
+            color: "black".to_string(),
+            visible: true,
+        }
+    }
+}
+
+impl Style {
+    fn of(el: &Element, parent: &Style) -> Style {
+        let own = |name: &str| el.property(name).filter(|v| v != "inherit");
+        let mut style = parent.clone();
+        if let Some(c) = own("color") {
+            style.color = c;
+        }
+        let paint = |value: String, color: &str| match value.as_str() {
+            "none" => None,
+            v if v.eq_ignore_ascii_case("currentcolor") => Some(color.to_string()),
+            _ => Some(value),
+        };
+        if let Some(f) = own("fill") {
+            style.fill = paint(f, &style.color);
+        }
+        if let Some(s) = own("stroke") {
+            style.stroke = paint(s, &style.color);
+        }
+        match own("fill-rule").as_deref() {
+            Some("evenodd") => style.fill_rule = FillRule::EvenOdd,
+            Some("nonzero") => style.fill_rule = FillRule::NonZero,
+            _ => {}
+        }
+        if let Some(o) = own("fill-opacity").and_then(|v| opacity(&v)) {
+            style.fill_opacity = o;
+        }
+        if let Some(o) = own("stroke-opacity").and_then(|v| opacity(&v)) {
+            style.stroke_opacity = o;
+        }
+        let width = own("stroke-width")
+            .and_then(|v| svg_length(&v).ok()?.resolve(&Context::new(CSS_DPI)).ok());
+        if let Some(w) = width {
+            style.stroke_width = w.max(0.0);
+        }
+        match own("stroke-linecap").as_deref() {
+            Some("butt") => style.cap = Cap::Butt,
+            Some("round") => style.cap = Cap::Round,
+            Some("square") => style.cap = Cap::Square,
+            _ => {}
+        }
+        match own("stroke-linejoin").as_deref() {
+            Some("miter") | Some("miter-clip") | Some("arcs") => style.join = Join::Miter,
+            Some("round") => style.join = Join::Round,
+            Some("bevel") => style.join = Join::Bevel,
+            _ => {}
+        }
+        if let Some(m) = own("stroke-miterlimit").and_then(|v| v.parse::<f64>().ok()) {
+            style.miter_limit = m.max(1.0);
+        }
+        match own("visibility").as_deref() {
+            Some("hidden") | Some("collapse") => style.visible = false,
+            Some("visible") => style.visible = true,
+            _ => {}
+        }
+        style
+    }
+}
+
+/// An opacity as a number or a percentage, kept between 0 and 1
+fn opacity(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let number = match value.strip_suffix('%') {
+        Some(p) => p.trim().parse::<f64>().ok()? / 100.0,
+        None => value.parse::<f64>().ok()?,
+    };
+    Some(number.clamp(0.0, 1.0))
+}
+
+struct Renderer<'a> {
+    doc: &'a Element,
+    viewport: (f64, f64),
+}
+
+impl Renderer<'_> {
+    fn draw(
+        &self,
+        el: &Element,
+        parent: &Style,
+        parent_transform: &Transform,
+        canvas: &mut Canvas,
+    ) -> Result<(), Error> {
+        if !layer::is_visible(el) {
+            return Ok(());
+        }
+        let segments = if CONTAINERS.contains(&el.name.as_str()) {
+            None
+        } else {
+            // anything else that isn't a shape isn't drawn
+            let segments = flatten::segments(el)?;
+            if segments.is_empty() {
+                return Ok(());
+            }
+            Some(segments)
+        };
+
+        let style = Style::of(el, parent);
+        let transform = match el.get("transform") {
+            Some(t) => t.parse::<Transform>()?.then(parent_transform),
+            None => *parent_transform,
+        };
+        let opacity = el
+            .property("opacity")
+            .and_then(|o| opacity(&o))
+            .unwrap_or(1.0);
+        if opacity <= 0.0 {
+            return Ok(());
+        }
+
+        if opacity < 1.0 {
+            // the element is drawn on its own then faded, so overlapping parts don't show through each other
+            let mut layer = Canvas::new(canvas.width, canvas.height);
+            self.draw_content(el, segments, &style, &transform, &mut layer)?;
+            canvas.composite(&layer, opacity as f32);
+        } else {
+            self.draw_content(el, segments, &style, &transform, canvas)?;
+        }
+        Ok(())
+    }
+
+    fn draw_content(
+        &self,
+        el: &Element,
+        segments: Option<Vec<path::Segment>>,
+        style: &Style,
+        transform: &Transform,
+        canvas: &mut Canvas,
+    ) -> Result<(), Error> {
+        let Some(segments) = segments else {
+            for child in el.elements() {
+                self.draw(child, style, transform, canvas)?;
+            }
+            return Ok(());
+        };
+        if !style.visible {
+            return Ok(());
+        }
+
+        let scale = transform.scale_factor();
+        let tolerance = if scale > 0.0 {
+            TOLERANCE / scale
+        } else {
+            TOLERANCE
+        };
+        let lines = path::flatten(&segments, tolerance);
+        let bounds = bounding_box(&lines);
+        let to_device = |rings: Vec<Vec<Point>>| -> Vec<Vec<Point>> {
+            rings
+                .into_iter()
+                .map(|r| r.into_iter().map(|p| transform.apply(p)).collect())
+                .collect()
+        };
+
+        if let Some(paint) = style
+            .fill
+            .as_deref()
+            .and_then(|f| self.paint(f, style.fill_opacity, bounds, transform))
+        {
+            let rings: Vec<Vec<Point>> = lines.iter().filter(|l| l.len() > 2).cloned().collect();
+            canvas.fill(&to_device(rings), style.fill_rule, &paint);
+        }
+
+        if style.stroke_width > 0.0 {
+            if let Some(paint) = style
+                .stroke
+                .as_deref()
+                .and_then(|s| self.paint(s, style.stroke_opacity, bounds, transform))
+            {
+                let mut pieces = vec![];
+                for line in &lines {
+                    pieces.extend(stroke(line, style, tolerance));
+                }
+                canvas.fill(&to_device(pieces), FillRule::NonZero, &paint);
+            }
+        }
+        Ok(())
+    }
+
+    /// What to paint with for a fill or stroke value, None if nothing should be drawn
+    fn paint(
+        &self,
+        value: &str,
+        opacity: f64,
+        bounds: Option<(Point, Point)>,
+        transform: &Transform,
+    ) -> Option<Paint> {
+        if let Some(reference) = value.strip_prefix("url(") {
+            let (id, fallback) = reference.split_once(')')?;
+            let id = id.trim().trim_matches(|c| c == '\'' || c == '"');
+            let gradient = id
+                .strip_prefix('#')
+                .and_then(|id| self.doc.find_by_id(id))
+                .and_then(|g| self.gradient(g, opacity, bounds?, transform));
+            return match gradient {
+                Some(g) => Some(g),
+                None => {
+                    let fallback = fallback.trim();
+                    (!fallback.is_empty() && fallback != "none")
+                        .then(|| solid(fallback, opacity))
+                        .flatten()
+                }
+            };
+        }
+        solid(value, opacity)
+    }
+
+    fn gradient(
+        &self,
+        el: &Element,
+        opacity: f64,
+        (min, max): (Point, Point),
+        transform: &Transform,
+    ) -> Option<Paint> {
+        let linear = match el.name.as_str() {
+            "linearGradient" => true,
+            "radialGradient" => false,
+            _ => return None,
+        };
+        let attribute = |name: &str| gradient_attribute(self.doc, el, name, 0);
+
+        let bounding_box = attribute("gradientUnits").as_deref() != Some("userSpaceOnUse");
+        let (width, height) = if bounding_box {
+            if max.x - min.x <= 0.0 || max.y - min.y <= 0.0 {
+                return None;
+            }
+            (1.0, 1.0)
+        } else {
+            self.viewport
+        };
+        let coordinate = |name: &str, default: &str, size: f64| -> f64 {
+            let value = attribute(name).unwrap_or_else(|| default.to_string());
+            match value.trim().strip_suffix('%') {
+                Some(p) => p.trim().parse::<f64>().unwrap_or(0.0) / 100.0 * size,
+                None => svg_length(&value)
+                    .ok()
+                    .and_then(|l| l.to_pixels(CSS_DPI).ok())
+                    .unwrap_or(0.0),
+            }
+        };
+        let diagonal = (width * width + height * height).sqrt() / 2f64.sqrt();
+
+        let shape = if linear {
+            Shape::Linear(
+                Point::new(
+                    coordinate("x1", "0%", width),
+                    coordinate("y1", "0%", height),
+                ),
+                Point::new(
+                    coordinate("x2", "100%", width),
+                    coordinate("y2", "0%", height),
+                ),
+            )
+        } else {
+            let centre = Point::new(
+                coordinate("cx", "50%", width),
+                coordinate("cy", "50%", height),
+            );
+            let focus = Point::new(
+                attribute("fx").map_or(centre.x, |_| coordinate("fx", "50%", width)),
+                attribute("fy").map_or(centre.y, |_| coordinate("fy", "50%", height)),
+            );
+            Shape::Radial(centre, coordinate("r", "50%", diagonal), focus)
+        };
+
+        let mut gradient_transform = match attribute("gradientTransform") {
+            Some(t) => t.parse::<Transform>().ok()?,
+            None => Transform::identity(),
+        };
+        if bounding_box {
+            gradient_transform = gradient_transform.then(
+                &Transform::scale(max.x - min.x, max.y - min.y)
+                    .then(&Transform::translate(min.x, min.y)),
+            );
+        }
+        let to_gradient = gradient_transform.then(transform).inverse()?;
+
+        let stops = stops(self.doc, el, opacity, 0);
+        let spread = match attribute("spreadMethod").as_deref() {
+            Some("reflect") => Spread::Reflect,
+            Some("repeat") => Spread::Repeat,
+            _ => Spread::Pad,
+        };
+        match stops.len() {
+            0 => None,
+            1 => Some(Paint::Solid(stops[0].1)),
+            _ => Some(Paint::Gradient(Box::new(Gradient {
+                shape,
+                stops,
+                spread,
+                to_gradient,
+            }))),
+        }
+    }
+}
+
+/// A gradient attribute, taken from the gradients it links to if it doesn't have one itself
+fn gradient_attribute(doc: &Element, el: &Element, name: &str, depth: usize) -> Option<String> {
+    el.get(name).or_else(|| {
+        linked(doc, el, depth).and_then(|l| gradient_attribute(doc, l, name, depth + 1))
+    })
+}
+
+/// The gradient another one links to with href, for sharing stops
+fn linked<'a>(doc: &'a Element, el: &Element, depth: usize) -> Option<&'a Element> {
+    if depth > 8 {
+        return None;
+    }
+    let href = el.get("href").or_else(|| el.get("xlink:href"))?;
+    doc.find_by_id(href.trim().strip_prefix('#')?)
+}
+
+fn stops(doc: &Element, el: &Element, opacity: f64, depth: usize) -> Vec<(f64, [f32; 4])> {
+    let mut result: Vec<(f64, [f32; 4])> = vec![];
+    for stop in el.elements().filter(|e| e.name == "stop") {
+        let offset = stop
+            .get("offset")
+            .and_then(|o| match o.trim().strip_suffix('%') {
+                Some(p) => p.trim().parse::<f64>().ok().map(|p| p / 100.0),
+                None => o.trim().parse::<f64>().ok(),
+            })
+            .unwrap_or(0.0)
+            .clamp(0.0, 1.0);
+        // offsets can't go backwards
+        let offset = result.last().map_or(offset, |(last, _)| offset.max(*last));
+        let stop_opacity = stop
+            .property("stop-opacity")
+            .and_then(|o| self::opacity(&o))
+            .unwrap_or(1.0);
+        let colour = stop
+            .property("stop-color")
+            .unwrap_or_else(|| "black".to_string());
+        if let Some(Paint::Solid(c)) = solid(&colour, opacity * stop_opacity) {
+            result.push((offset, c));
+        }
+    }
+    if result.is_empty() {
+        if let Some(l) = linked(doc, el, depth) {
+            return stops(doc, l, opacity, depth + 1);
+        }
+    }
+    result
+}
+
+/// A flat colour with opacity as premultiplied RGBA
+fn solid(value: &str, opacity: f64) -> Option<Paint> {
+    let hex = normalise_colour(value)?;
+    let (r, g, b, a) = parse_colour(&hex).ok()?;
+    let a = a * opacity;
+    Some(Paint::Solid([
+        (r * a) as f32,
+        (g * a) as f32,
+        (b * a) as f32,
+        a as f32,
+    ]))
+}
+
+fn bounding_box(lines: &[Vec<Point>]) -> Option<(Point, Point)> {
+    let mut points = lines.iter().flatten();
+    let first = *points.next()?;
+    Some(points.fold((first, first), |(min, max), p| (min.min(p), max.max(p))))
+}
+
+#[derive(Debug, Clone)]
+enum Paint {
+    Solid([f32; 4]),
+    Gradient(Box<Gradient>),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Spread {
+    Pad,
+    Reflect,
+    Repeat,
+}
+
+#[derive(Debug, Clone)]
+enum Shape {
+    /// from the start to the end point
+    Linear(Point, Point),
+    /// centre, radius, and focal point
+    Radial(Point, f64, Point),
+}
+
+#[derive(Debug, Clone)]
+struct Gradient {
+    shape: Shape,
+    stops: Vec<(f64, [f32; 4])>,
+    spread: Spread,
+    /// from the canvas to the gradient's own coordinates
+    to_gradient: Transform,
+}
+
+impl Gradient {
+    fn colour(&self, device: Point) -> [f32; 4] {
+        let p = self.to_gradient.apply(device);
+        let t = match self.shape {
+            Shape::Linear(a, b) => {
+                let (dx, dy) = (b.x - a.x, b.y - a.y);
+                let length = dx * dx + dy * dy;
+                if length == 0.0 {
+                    1.0
+                } else {
+                    ((p.x - a.x) * dx + (p.y - a.y) * dy) / length
+                }
+            }
+            Shape::Radial(centre, r, focus) => {
+                // find the circle between the focus and the edge that passes through the point
+                let (dx, dy) = (p.x - focus.x, p.y - focus.y);
+                let (ex, ey) = (centre.x - focus.x, centre.y - focus.y);
+                let a = ex * ex + ey * ey - r * r;
+                let de = dx * ex + dy * ey;
+                let dd = dx * dx + dy * dy;
+                if r <= 0.0 {
+                    1.0
+                } else if a.abs() < 1e-12 {
+                    if de == 0.0 {
+                        0.0
+                    } else {
+                        dd / (2.0 * de)
+                    }
+                } else {
+                    (de - (de * de - a * dd).max(0.0).sqrt()) / a
+                }
+            }
+        };
+        let t = match self.spread {
+            Spread::Pad => t.clamp(0.0, 1.0),
+            Spread::Repeat => t - t.floor(),
+            Spread::Reflect => {
+                let t = t.rem_euclid(2.0);
+                if t > 1.0 {
+                    2.0 - t
+                } else {
+                    t
+                }
+            }
+        };
+
+        let after = self
+            .stops
+            .iter()
+            .position(|(offset, _)| *offset > t)
+            .unwrap_or(self.stops.len());
+        if after == 0 {
+            return self.stops[0].1;
+        }
+        if after == self.stops.len() {
+            return self.stops[after - 1].1;
+        }
+        let (o1, c1) = self.stops[after - 1];
+        let (o2, c2) = self.stops[after];
+        let f = ((t - o1) / (o2 - o1)) as f32;
+        [0, 1, 2, 3].map(|i| c1[i] + (c2[i] - c1[i]) * f)
+    }
+}
+
+/// Premultiplied floating point RGBA pixels to draw on
+struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<[f32; 4]>,
+}
+
+impl Canvas {
+    fn new(width: usize, height: usize) -> Self {
+        Canvas {
+            width,
+            height,
+            pixels: vec![[0.0; 4]; width * height],
+        }
+    }
+
+    fn blend(&mut self, index: usize, colour: [f32; 4], amount: f32) {
+        let pixel = &mut self.pixels[index];
+        let keep = 1.0 - colour[3] * amount;
+        for c in 0..4 {
+            pixel[c] = colour[c] * amount + pixel[c] * keep;
+        }
+    }
+
+    /// Draw another canvas over this one faded by an opacity
+    fn composite(&mut self, other: &Canvas, opacity: f32) {
+        for i in 0..self.pixels.len() {
+            self.blend(i, other.pixels[i], opacity);
+        }
+    }
+
+    /// Fill the inside of rings of points in canvas pixels
+    fn fill(&mut self, rings: &[Vec<Point>], rule: FillRule, paint: &Paint) {
+        // edges going down the page with their direction, bucketed by the rows of pixels they cross
+        let mut edges = vec![];
+        for ring in rings.iter().filter(|r| r.len() > 1) {
+            for (i, a) in ring.iter().enumerate() {
+                let b = ring[(i + 1) % ring.len()];
+                if a.y < b.y {
+                    edges.push((*a, b, 1));
+                } else if b.y < a.y {
+                    edges.push((b, *a, -1));
+                }
+            }
+        }
+        let mut rows: Vec<Vec<usize>> = vec![vec![]; self.height];
+        for (i, (top, bottom, _)) in edges.iter().enumerate() {
+            let first = top.y.floor().max(0.0) as usize;
+            let last = (bottom.y.ceil().max(0.0) as usize).min(self.height);
+            for row in rows.iter_mut().take(last).skip(first) {
+                row.push(i);
+            }
+        }
+
+        let mut coverage = vec![0.0f32; self.width + 1];
+        let mut crossings: Vec<(f64, i32)> = vec![];
+        for (y, row) in rows.iter().enumerate() {
+            if row.is_empty() {
+                continue;
+            }
+            let (mut left, mut right) = (self.width, 0);
+            for sample in 0..SAMPLES {
+                let sy = y as f64 + (sample as f64 + 0.5) / SAMPLES as f64;
+                crossings.clear();
+                for &e in row {
+                    let (top, bottom, direction) = edges[e];
+                    if top.y <= sy && sy < bottom.y {
+                        let x = top.x + (sy - top.y) / (bottom.y - top.y) * (bottom.x - top.x);
+                        crossings.push((x, direction));
+                    }
+                }
+                crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+                let mut winding = 0;
+                for (i, (x, direction)) in crossings.iter().enumerate() {
+                    let inside = match rule {
+                        FillRule::NonZero => winding != 0,
+                        FillRule::EvenOdd => i % 2 == 1,
+                    };
+                    winding += direction;
+                    if inside {
+                        let span = self.span(&mut coverage, crossings[i - 1].0, *x);
+                        if let Some((l, r)) = span {
+                            left = left.min(l);
+                            right = right.max(r);
+                        }
+                    }
+                }
+            }
+
+            for (x, c) in coverage.iter_mut().enumerate().take(right + 1).skip(left) {
+                if *c > 0.0 && x < self.width {
+                    let amount = (*c / SAMPLES as f32).min(1.0);
+                    let colour = match paint {
+                        Paint::Solid(colour) => *colour,
+                        Paint::Gradient(g) => g.colour(Point::new(x as f64 + 0.5, y as f64 + 0.5)),
+                    };
+                    self.blend(y * self.width + x, colour, amount);
+                }
+                *c = 0.0;
+            }
+        }
+    }
+
+    /// Add the part of a sub scanline between two x positions, returning the pixels touched
+    fn span(&self, coverage: &mut [f32], x0: f64, x1: f64) -> Option<(usize, usize)> {
+        let x0 = x0.clamp(0.0, self.width as f64);
+        let x1 = x1.clamp(0.0, self.width as f64);
+        if x1 <= x0 {
+            return None;
+        }
+        let (i0, i1) = (x0.floor() as usize, x1.floor() as usize);
+        if i0 == i1 {
+            coverage[i0] += (x1 - x0) as f32;
+        } else {
+            coverage[i0] += (i0 as f64 + 1.0 - x0) as f32;
+            for c in &mut coverage[i0 + 1..i1] {
+                *c += 1.0;
+            }
+            coverage[i1] += (x1 - i1 as f64) as f32;
+        }
+        Some((i0, i1))
+    }
+
+    fn image(&self) -> Image {
+        let mut image = Image::new(self.width as u32, self.height as u32);
+        for (i, [r, g, b, a]) in self.pixels.iter().enumerate() {
+            if *a <= 0.0 {
+                continue;
+            }
+            let byte = |v: f32| (v * 255.0).round().clamp(0.0, 255.0) as u8;
+            image.pixels[i * 4..i * 4 + 4].copy_from_slice(&[
+                byte(r / a),
+                byte(g / a),
+                byte(b / a),
+                byte(*a),
+            ]);
+        }
+        image
+    }
+}
+
+/// The outline of a stroked line as pieces that all wind the same way, so filling them with the non zero rule
+/// gives the whole stroke
+fn stroke(line: &[Point], style: &Style, tolerance: f64) -> Vec<Vec<Point>> {
+    let mut points = line.to_vec();
+    points.dedup_by(|a, b| path::distance(*a, *b) < 1e-9);
+    let half = style.stroke_width / 2.0;
+    let mut pieces = vec![];
+
+    if points.len() == 1 {
+        // a zero length line only shows with caps that stick out
+        let p = points[0];
+        match style.cap {
+            Cap::Round => pieces.push(circle(p, half, tolerance)),
+            Cap::Square => pieces.push(vec![
+                Point::new(p.x - half, p.y - half),
+                Point::new(p.x + half, p.y - half),
+                Point::new(p.x + half, p.y + half),
+                Point::new(p.x - half, p.y + half),
+            ]),
+            Cap::Butt => {}
+        }
+        return pieces;
+    }
+
+    let closed = points.len() > 2 && path::distance(points[0], points[points.len() - 1]) < 1e-9;
+    let direction = |a: Point, b: Point| {
+        let length = path::distance(a, b);
+        Point::new((b.x - a.x) / length, (b.y - a.y) / length)
+    };
+    let count = points.len() - 1;
+    for i in 0..count {
+        let (mut a, mut b) = (points[i], points[i + 1]);
+        let d = direction(a, b);
+        if style.cap == Cap::Square && !closed {
+            if i == 0 {
+                a = Point::new(a.x - d.x * half, a.y - d.y * half);
+            }
+            if i == count - 1 {
+                b = Point::new(b.x + d.x * half, b.y + d.y * half);
+            }
+        }
+        let n = Point::new(-d.y * half, d.x * half);
+        pieces.push(vec![
+            Point::new(a.x + n.x, a.y + n.y),
+            Point::new(b.x + n.x, b.y + n.y),
+            Point::new(b.x - n.x, b.y - n.y),
+            Point::new(a.x - n.x, a.y - n.y),
+        ]);
+    }
+
+    let mut corners: Vec<(Point, Point, Point)> = (1..count)
+        .map(|i| {
+            (
+                points[i],
+                direction(points[i - 1], points[i]),
+                direction(points[i], points[i + 1]),
+            )
+        })
+        .collect();
+    if closed {
+        corners.push((
+            points[0],
+            direction(points[count - 1], points[count]),
+            direction(points[0], points[1]),
+        ));
+    } else if style.cap == Cap::Round {
+        pieces.push(circle(points[0], half, tolerance));
+        pieces.push(circle(points[count], half, tolerance));
+    }
+
+    for (p, d_in, d_out) in corners {
+        let cross = d_in.x * d_out.y - d_in.y * d_out.x;
+        let dot = d_in.x * d_out.x + d_in.y * d_out.y;
+        if cross.abs() < 1e-9 && dot > 0.0 {
+            continue;
+        }
+        if style.join == Join::Round {
+            pieces.push(circle(p, half, tolerance));
+            continue;
+        }
+        // the outside of the corner is away from the way it turns
+        let side = if cross > 0.0 { -1.0 } else { 1.0 };
+        let n_in = Point::new(-d_in.y * half * side, d_in.x * half * side);
+        let n_out = Point::new(-d_out.y * half * side, d_out.x * half * side);
+        let a = Point::new(p.x + n_in.x, p.y + n_in.y);
+        let b = Point::new(p.x + n_out.x, p.y + n_out.y);
+
+        let ratio = 1.0 / ((1.0 + dot) / 2.0).max(0.0).sqrt();
+        if style.join == Join::Miter && ratio <= style.miter_limit {
+            let (mx, my) = (n_in.x + n_out.x, n_in.y + n_out.y);
+            let length = mx.hypot(my);
+            let tip = Point::new(
+                p.x + mx / length * half * ratio,
+                p.y + my / length * half * ratio,
+            );
+            pieces.push(vec![p, a, tip, b]);
+        } else {
+            pieces.push(vec![p, a, b]);
+        }
+    }
+
+    for piece in &mut pieces {
+        let area: f64 = (0..piece.len())
+            .map(|i| {
+                let (a, b) = (piece[i], piece[(i + 1) % piece.len()]);
+                a.x * b.y - b.x * a.y
+            })
+            .sum();
+        if area < 0.0 {
+            piece.reverse();
+        }
+    }
+    pieces
+}
+
+fn circle(centre: Point, radius: f64, tolerance: f64) -> Vec<Point> {
+    let steps = if radius > tolerance {
+        (std::f64::consts::PI / (1.0 - tolerance / radius).acos()).ceil() as usize
+    } else {
+        8
+    }
+    .clamp(8, 256);
+    (0..steps)
+        .map(|i| {
+            let angle = std::f64::consts::TAU * i as f64 / steps as f64;
+            Point::new(
+                centre.x + radius * angle.cos(),
+                centre.y + radius * angle.sin(),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{render, Image};
+    use crate::page::Page;
+    use crate::read::parse_string;
+
+    fn draw(content: &str) -> Image {
+        let doc = parse_string(format!(r#"<svg viewBox="0 0 100 100">{content}</svg>"#)).unwrap();
+        let page = Page::new(
+            crate::length::Length::px(100.0),
+            crate::length::Length::px(100.0),
+            96,
+            crate::page::Borders::even(0.0, 96),
+        )
+        .unwrap();
+        render(&doc, &page).unwrap()
+    }
+
+    #[test]
+    fn fills() {
+        let image = draw(
+            r#"<path d="M10 10 h80 v80 h-80 z M30 30 h40 v40 h-40 z" fill-rule="evenodd" fill="blue" />"#,
+        );
+        assert_eq!(image.pixel(20, 20), [0, 0, 255, 255]);
+        assert_eq!(image.pixel(50, 50), [0, 0, 0, 0]);
+
+        let image = draw(r#"<path d="M10 10 h80 v80 h-80 z M30 30 h40 v40 h-40 z" fill="blue" />"#);
+        assert_eq!(image.pixel(50, 50), [0, 0, 255, 255]);
+
+        // half covered pixels are half see through
+        let image = draw(r#"<rect x="10.5" y="10" width="20" height="20" fill="black" />"#);
+        assert_eq!(image.pixel(10, 15)[3], 128);
+    }
+
+    #[test]
+    fn strokes() {
+        let image = draw(
+            r#"<polyline points="10,50 50,50 50,90" fill="none" stroke="red" stroke-width="10" stroke-linecap="square" />"#,
+        );
+        // the square cap sticks out past the end and the miter fills the outside corner
+        assert_eq!(image.pixel(7, 50), [255, 0, 0, 255]);
+        assert_eq!(image.pixel(53, 47), [255, 0, 0, 255]);
+        assert_eq!(image.pixel(50, 40)[3], 0);
+
+        let image = draw(
+            r#"<polyline points="10,50 50,50 50,90" fill="none" stroke="red" stroke-width="10" stroke-linejoin="bevel" />"#,
+        );
+        assert_eq!(image.pixel(7, 50)[3], 0);
+        assert_eq!(image.pixel(54, 46)[3], 0);
+
+        let image = draw(
+            r#"<circle cx="50" cy="50" r="30" fill="none" stroke="black" stroke-width="4" />"#,
+        );
+        assert_eq!(image.pixel(50, 20), [0, 0, 0, 255]);
+        assert_eq!(image.pixel(50, 50)[3], 0);
+    }
+
+    #[test]
+    fn opacity_and_gradients() {
+        let image = draw(
+            r#"<g opacity="0.5"><rect width="60" height="60" fill="red" /><rect x="40" y="40" width="60" height="60" fill="red" /></g>"#,
+        );
+        // overlapping parts of a faded group don't add up
+        assert_eq!(image.pixel(50, 50), [255, 0, 0, 128]);
+        assert_eq!(image.pixel(10, 10), [255, 0, 0, 128]);
+
+        let image = draw(
+            r##"<defs>
+  <linearGradient id="fade"><stop offset="0" stop-color="black" /><stop offset="1" stop-color="white" /></linearGradient>
+  <radialGradient id="spot" href="#fade" />
+</defs>
+<rect width="100" height="50" fill="url(#fade)" />
+<rect y="50" width="100" height="50" fill="url(#spot)" fill-opacity="0.5" />"##,
+        );
+        assert_eq!(image.pixel(0, 10)[0], 1);
+        assert_eq!(image.pixel(99, 10)[0], 254);
+        assert!(image.pixel(50, 25)[0].abs_diff(128) <= 1);
+        // the middle of the radial gradient is the first stop
+        assert!(image.pixel(50, 75)[0] < 10);
+        assert_eq!(image.pixel(50, 75)[3], 128);
+        assert!(image.pixel(2, 52)[0] > 200);
+
+        // percentages in user space are of the view box, not the page it is drawn on
+        let doc = parse_string(
+            r#"<svg viewBox="0 0 50 50">
+  <linearGradient id="across" gradientUnits="userSpaceOnUse" x2="100%"><stop offset="0" stop-color="black" /><stop offset="1" stop-color="white" /></linearGradient>
+  <rect width="50" height="50" fill="url(#across)" />
+</svg>"#
+                .to_string(),
+        )
+        .unwrap();
+        let page = Page::new(
+            crate::length::Length::px(100.0),
+            crate::length::Length::px(100.0),
+            96,
+            crate::page::Borders::even(0.0, 96),
+        )
+        .unwrap();
+        let image = render(&doc, &page).unwrap();
+        assert!(image.pixel(50, 50)[0].abs_diff(128) <= 2);
+        assert!(image.pixel(99, 50)[0] > 250);
+    }
+
+    #[test]
+    fn png() {
+        let png = draw(r#"<rect width="50" height="50" fill="green" />"#).to_png();
+        assert_eq!(&png[1..4], b"PNG");
+        // flat colours compress down a lot
+        assert!(png.len() < 1000);
+    }
+}
@@ -0,0 +1,326 @@
+//! Just enough of PNG and zlib to write images, so previews don't need any other libraries
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+/// A PNG file for 8 bit RGBA pixels in rows from the top
+pub(crate) fn encode(width: u32, height: u32, rgba: &[u8]) -> Vec<u8> {
+    let mut out = SIGNATURE.to_vec();
+
+    let mut header = vec![];
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // 8 bits per channel, RGBA, deflate, adaptive filtering, not interlaced
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+    chunk(&mut out, b"IHDR", &header);
+    chunk(&mut out, b"IDAT", &zlib(&filter(width as usize, rgba)));
+    chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+fn chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Put a filter in front of each row, picking whichever of none, sub, and up gives the smallest numbers as those
+/// compress best
+fn filter(width: usize, rgba: &[u8]) -> Vec<u8> {
+    let stride = width * 4;
+    let mut out = Vec::with_capacity(rgba.len() + rgba.len() / stride.max(1));
+    let empty = vec![0; stride];
+    for (y, row) in rgba.chunks(stride.max(1)).enumerate() {
+        let above = if y == 0 {
+            &empty[..]
+        } else {
+            &rgba[(y - 1) * stride..y * stride]
+        };
+        let sub: Vec<u8> = (0..row.len())
+            .map(|i| row[i].wrapping_sub(if i >= 4 { row[i - 4] } else { 0 }))
+            .collect();
+        let up: Vec<u8> = (0..row.len())
+            .map(|i| row[i].wrapping_sub(above[i]))
+            .collect();
+
+        let cost =
+            |bytes: &[u8]| -> u64 { bytes.iter().map(|b| (*b as i8).unsigned_abs() as u64).sum() };
+        let candidates = [(0, row), (1, &sub[..]), (2, &up[..])];
+        let (kind, bytes) = candidates
+            .iter()
+            .min_by_key(|(_, bytes)| cost(bytes))
+            .unwrap();
+        out.push(*kind);
+        out.extend_from_slice(bytes);
+    }
+    out
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut table = [0u32; 256];
+    for (n, entry) in table.iter_mut().enumerate() {
+        let mut c = n as u32;
+        for _ in 0..8 {
+            c = if c & 1 == 1 {
+                0xedb8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+        }
+        *entry = c;
+    }
+    !data.iter().fold(!0u32, |c, b| {
+        table[((c ^ *b as u32) & 0xff) as usize] ^ (c >> 8)
+    })
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for byte in chunk {
+            a += *byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+fn zlib(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x9c];
+    out.extend(deflate(data));
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+const WINDOW: usize = 32768;
+const MAX_MATCH: usize = 258;
+/// How many earlier matches to try before giving up, more is smaller but slower
+const CHAIN: usize = 32;
+
+/// Deflate as a single block with the fixed huffman codes, finding repeats with a hash chain
+fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut bits = Bits::default();
+    // last block, fixed codes
+    bits.write(1, 1);
+    bits.write(1, 2);
+
+    let hash = |i: usize| -> usize {
+        ((data[i] as usize) << 10 ^ (data[i + 1] as usize) << 5 ^ data[i + 2] as usize)
+            & (WINDOW - 1)
+    };
+    let mut head = vec![usize::MAX; WINDOW];
+    let mut previous = vec![usize::MAX; WINDOW];
+    let insert = |i: usize, head: &mut [usize], previous: &mut [usize]| {
+        if i + 2 < data.len() {
+            let h = hash(i);
+            previous[i % WINDOW] = head[h];
+            head[h] = i;
+        }
+    };
+
+    let mut i = 0;
+    while i < data.len() {
+        let mut best = (0, 0);
+        if i + 2 < data.len() {
+            let mut candidate = head[hash(i)];
+            let mut tries = 0;
+            while candidate != usize::MAX && i - candidate <= WINDOW && tries < CHAIN {
+                let length = data[candidate..]
+                    .iter()
+                    .zip(&data[i..])
+                    .take(MAX_MATCH)
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                if length > best.0 {
+                    best = (length, i - candidate);
+                }
+                let next = previous[candidate % WINDOW];
+                if next == usize::MAX || next >= candidate {
+                    break;
+                }
+                candidate = next;
+                tries += 1;
+            }
+        }
+
+        if best.0 >= 3 {
+            let (length, distance) = best;
+            let code = LENGTH_BASE
+                .iter()
+                .rposition(|b| *b as usize <= length)
+                .unwrap();
+            bits.literal(257 + code as u16);
+            bits.write(
+                (length - LENGTH_BASE[code] as usize) as u32,
+                LENGTH_EXTRA[code],
+            );
+            let code = DISTANCE_BASE
+                .iter()
+                .rposition(|b| *b as usize <= distance)
+                .unwrap();
+            bits.code(code as u32, 5);
+            bits.write(
+                (distance - DISTANCE_BASE[code] as usize) as u32,
+                DISTANCE_EXTRA[code],
+            );
+            for j in i..i + length {
+                insert(j, &mut head, &mut previous);
+            }
+            i += length;
+        } else {
+            bits.literal(data[i] as u16);
+            insert(i, &mut head, &mut previous);
+            i += 1;
+        }
+    }
+    bits.literal(256);
+    bits.finish()
+}
+
+/// Packs bits into bytes starting from the lowest bit, the way deflate wants
+#[derive(Default)]
+struct Bits {
+    bytes: Vec<u8>,
+    current: u32,
+    count: u8,
+}
+
+impl Bits {
+    /// Write a number lowest bit first
+    fn write(&mut self, value: u32, count: u8) {
+        for n in 0..count {
+            self.current |= ((value >> n) & 1) << self.count;
+            self.count += 1;
+            if self.count == 8 {
+                self.bytes.push(self.current as u8);
+                self.current = 0;
+                self.count = 0;
+            }
+        }
+    }
+
+    /// Write a huffman code, which goes highest bit first
+    fn code(&mut self, code: u32, length: u8) {
+        for n in (0..length).rev() {
+            self.write((code >> n) & 1, 1);
+        }
+    }
+
+    /// A literal, length, or end of block symbol in the fixed code
+    fn literal(&mut self, symbol: u16) {
+        let symbol = symbol as u32;
+        match symbol {
+            0..=143 => self.code(0x30 + symbol, 8),
+            144..=255 => self.code(0x190 + symbol - 144, 9),
+            256..=279 => self.code(symbol - 256, 7),
+            _ => self.code(0xc0 + symbol - 280, 8),
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.bytes.push(self.current as u8);
+        }
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{
+        adler32, crc32, deflate, encode, DISTANCE_BASE, DISTANCE_EXTRA, LENGTH_BASE, LENGTH_EXTRA,
+    };
+
+    /// Just enough inflate to read back what deflate writes
+    fn inflate(data: &[u8]) -> Vec<u8> {
+        let mut position = 0;
+        let mut bit = |count: u8| -> u32 {
+            let mut value = 0;
+            for n in 0..count {
+                let b = (data[position / 8] >> (position % 8)) & 1;
+                value |= (b as u32) << n;
+                position += 1;
+            }
+            value
+        };
+        assert_eq!(bit(1), 1);
+        assert_eq!(bit(2), 1);
+
+        let mut out: Vec<u8> = vec![];
+        loop {
+            // read the fixed code one bit at a time until it is a valid symbol
+            let mut code = 0;
+            let mut length = 0;
+            let symbol = loop {
+                code = (code << 1) | bit(1);
+                length += 1;
+                match length {
+                    7 if code <= 0x17 => break code + 256,
+                    8 if (0x30..=0xbf).contains(&code) => break code - 0x30,
+                    8 if (0xc0..=0xc7).contains(&code) => break code - 0xc0 + 280,
+                    9 if code >= 0x190 => break code - 0x190 + 144,
+                    _ => {}
+                }
+            };
+            match symbol {
+                0..=255 => out.push(symbol as u8),
+                256 => return out,
+                _ => {
+                    let index = (symbol - 257) as usize;
+                    let length = LENGTH_BASE[index] as usize + bit(LENGTH_EXTRA[index]) as usize;
+                    let mut code = 0;
+                    for _ in 0..5 {
+                        code = (code << 1) | bit(1);
+                    }
+                    let code = code as usize;
+                    let distance =
+                        DISTANCE_BASE[code] as usize + bit(DISTANCE_EXTRA[code]) as usize;
+                    for _ in 0..length {
+                        out.push(out[out.len() - distance]);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut data: Vec<u8> = b"hello hello hello, a plotter plots plots of plots".to_vec();
+        data.extend((0..5000).map(|i| (i % 251) as u8));
+        data.extend([7; 1000]);
+        let compressed = deflate(&data);
+        assert!(compressed.len() < data.len() / 2);
+        assert_eq!(inflate(&compressed), data);
+        assert!(inflate(&deflate(&[])).is_empty());
+    }
+
+    #[test]
+    fn checksums() {
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+
+        let png = encode(2, 1, &[255, 0, 0, 255, 0, 0, 255, 128]);
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&png[png.len() - 8..], b"IEND\xae\x42\x60\x82");
+    }
+}
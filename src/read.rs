@@ -5,37 +5,120 @@ use quick_xml::events::{BytesStart, Event};
 use quick_xml::Reader;
 use std::str;
 
+/// Limits on what will be read, so a hostile or broken file gives an error rather than using up all the memory or
+/// the stack. The defaults are well past anything a drawing program writes, use [`ParseOptions::unlimited`] to turn
+/// them all off.
+///
+/// ```
+/// use esvg::error::Error;
+/// use esvg::read::{parse_string_with, ParseOptions};
+///
+/// let options = ParseOptions { max_depth: Some(2), ..Default::default() };
+/// assert!(parse_string_with("<svg><g /></svg>".to_string(), &options).is_ok());
+/// assert!(matches!(
+///     parse_string_with("<svg><g><g /></g></svg>".to_string(), &options),
+///     Err(Error::TooDeep(2))
+/// ));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseOptions {
+    /// Most bytes in the whole file
+    pub max_file_size: Option<usize>,
+    /// Most elements in the whole document
+    pub max_elements: Option<usize>,
+    /// How deep elements can be nested, the root is at depth 1
+    pub max_depth: Option<usize>,
+    /// Most attributes on one element
+    pub max_attributes: Option<usize>,
+    /// Most bytes in one attribute value
+    pub max_attribute_length: Option<usize>,
+    /// Most bytes in one text node
+    pub max_text_length: Option<usize>,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            max_file_size: Some(256 * 1024 * 1024),
+            max_elements: Some(4_000_000),
+            max_depth: Some(512),
+            max_attributes: Some(1024),
+            // embedded images go in attributes so these can be big
+            max_attribute_length: Some(64 * 1024 * 1024),
+            max_text_length: Some(64 * 1024 * 1024),
+        }
+    }
+}
+
+impl ParseOptions {
+    /// No limits at all, only for files that can be trusted
+    pub fn unlimited() -> Self {
+        ParseOptions {
+            max_file_size: None,
+            max_elements: None,
+            max_depth: None,
+            max_attributes: None,
+            max_attribute_length: None,
+            max_text_length: None,
+        }
+    }
+}
+
+/// Check a count against a limit, giving the error made from the limit if it's over
+fn check(count: usize, limit: Option<usize>, error: impl Fn(usize) -> Error) -> Result<(), Error> {
+    match limit {
+        Some(limit) if count > limit => Err(error(limit)),
+        _ => Ok(()),
+    }
+}
+
+/// Parse a document with the default [`ParseOptions`]
 pub fn parse_string(input: String) -> Result<Element, Error> {
+    parse_string_with(input, &ParseOptions::default())
+}
+
+/// Parse a document, stopping with an error as soon as it goes past one of the limits in the options
+pub fn parse_string_with(input: String, options: &ParseOptions) -> Result<Element, Error> {
+    check(input.len(), options.max_file_size, Error::FileTooLarge)?;
     let mut reader = Reader::from_str(input.as_str());
     reader.trim_text(true);
 
     let mut stack: Vec<Element> = vec![];
     let mut result: Option<Element> = None;
+    let mut elements = 0;
     loop {
         match reader.read_event() {
             Ok(Event::Start(ref e)) => {
-                stack.push(to_element(e)?);
+                elements += 1;
+                check(elements, options.max_elements, Error::TooManyElements)?;
+                check(stack.len() + 1, options.max_depth, Error::TooDeep)?;
+                stack.push(to_element(e, options)?);
             }
             Ok(Event::Empty(ref e)) => {
-                let mut parent = stack.pop().unwrap();
-                parent.add(&to_element(e)?);
-                stack.push(parent);
+                elements += 1;
+                check(elements, options.max_elements, Error::TooManyElements)?;
+                check(stack.len() + 1, options.max_depth, Error::TooDeep)?;
+                let element = to_element(e, options)?;
+                match stack.last_mut() {
+                    Some(parent) => parent.add_node(Node::Element(element)),
+                    // a document that is just one empty tag
+                    None => result = Some(element),
+                }
             }
             Ok(Event::End(ref _e)) => {
                 let current = stack.pop().unwrap();
-                if !stack.is_empty() {
-                    let mut parent = stack.pop().unwrap();
-                    parent.add(&current);
-                    stack.push(parent);
-                } else {
-                    result = Some(current);
+                match stack.last_mut() {
+                    Some(parent) => parent.add_node(Node::Element(current)),
+                    None => result = Some(current),
                 }
             }
             Ok(Event::Text(e)) => {
+                check(e.len(), options.max_text_length, Error::TextTooLong)?;
                 let text = e.unescape()?.to_string();
-                let mut parent = stack.pop().unwrap();
-                parent.add_node(Node::Text(text));
-                stack.push(parent);
+                // text outside the root element has nowhere to go
+                if let Some(parent) = stack.last_mut() {
+                    parent.add_node(Node::Text(text));
+                }
             }
             Ok(Event::Eof) => break,
             Ok(_) => {}
@@ -49,15 +132,20 @@ pub fn parse_string(input: String) -> Result<Element, Error> {
     }
 }
 
-fn to_element(e: &BytesStart) -> Result<Element, Error> {
-    let mut element = Element::new(str::from_utf8(e.name().0)?);
-    for attr in e.attributes() {
+fn to_element(e: &BytesStart, options: &ParseOptions) -> Result<Element, Error> {
+    let name = str::from_utf8(e.name().0)?;
+    let mut element = Element::new(name);
+    for (count, attr) in e.attributes().enumerate() {
+        check(count + 1, options.max_attributes, |limit| {
+            Error::TooManyAttributes(name.to_string(), limit)
+        })?;
         match attr {
             Ok(a) => {
-                _ = element.set(
-                    str::from_utf8(a.key.0)?,
-                    str::from_utf8(a.unescape_value()?.as_bytes())?,
-                )
+                let key = str::from_utf8(a.key.0)?;
+                check(a.value.len(), options.max_attribute_length, |limit| {
+                    Error::AttributeTooLong(key.to_string(), limit)
+                })?;
+                _ = element.set(key, str::from_utf8(a.unescape_value()?.as_bytes())?)
             }
             Err(e) => return Err(Error::XMLAttrError(e)),
         }
@@ -69,7 +157,8 @@ fn to_element(e: &BytesStart) -> Result<Element, Error> {
 #[cfg(test)]
 mod tests {
 
-    use super::parse_string;
+    use super::{parse_string, parse_string_with, ParseOptions};
+    use crate::error::Error;
 
     #[test]
     fn round_trip() {
@@ -97,4 +186,57 @@ mod tests {
              "
         );
     }
+
+    #[test]
+    fn limits() {
+        let input = r#"<svg width="10" height="10"><g><rect x="1" /><text>hello</text></g></svg>"#;
+        let parse = |options: ParseOptions| parse_string_with(input.to_string(), &options);
+
+        assert!(parse(ParseOptions::unlimited()).is_ok());
+        assert!(matches!(
+            parse(ParseOptions {
+                max_file_size: Some(20),
+                ..Default::default()
+            }),
+            Err(Error::FileTooLarge(20))
+        ));
+        assert!(matches!(
+            parse(ParseOptions {
+                max_elements: Some(3),
+                ..Default::default()
+            }),
+            Err(Error::TooManyElements(3))
+        ));
+        assert!(parse(ParseOptions {
+            max_elements: Some(4),
+            ..Default::default()
+        })
+        .is_ok());
+        assert!(matches!(
+            parse(ParseOptions {
+                max_depth: Some(2),
+                ..Default::default()
+            }),
+            Err(Error::TooDeep(2))
+        ));
+        assert!(matches!(
+            parse(ParseOptions { max_attributes: Some(1), ..Default::default() }),
+            Err(Error::TooManyAttributes(name, 1)) if name == "svg"
+        ));
+        assert!(matches!(
+            parse(ParseOptions { max_attribute_length: Some(1), ..Default::default() }),
+            Err(Error::AttributeTooLong(name, 1)) if name == "width"
+        ));
+        assert!(matches!(
+            parse(ParseOptions {
+                max_text_length: Some(4),
+                ..Default::default()
+            }),
+            Err(Error::TextTooLong(4))
+        ));
+
+        // too deep for the default limit, and for the stack if it went on much longer
+        let deep = "<g>".repeat(10_000) + &"</g>".repeat(10_000);
+        assert!(matches!(parse_string(deep), Err(Error::TooDeep(512))));
+    }
 }
@@ -0,0 +1,1080 @@
+//! Clean up svgs from people who can't be trusted before showing them in a browser
+//!
+//! ```
+//! use esvg::read::parse_string;
+//! use esvg::sanitize::{sanitize, Policy};
+//!
+//! let mut doc = parse_string(
+//!     r#"<svg onload="steal()"><script>steal()</script><a href="javascript:steal()"><circle r="5" /></a></svg>"#
+//!         .to_string(),
+//! )
+//! .unwrap();
+//! let report = sanitize(&mut doc, &Policy::default());
+//! assert_eq!(doc.to_string(), "<svg>\n<a>\n<circle r=\"5\" />\n</a>\n</svg>");
+//! assert_eq!(report.removed.len(), 3);
+//! ```
+use std::fmt;
+
+use crate::error::Error;
+use crate::read::parse_string;
+use crate::{Element, Node};
+
+/// Elements in every profile, the basic shapes, text, and gradients
+const TINY_ELEMENTS: [&str; 19] = [
+    "svg",
+    "g",
+    "defs",
+    "title",
+    "desc",
+    "path",
+    "rect",
+    "circle",
+    "ellipse",
+    "line",
+    "polyline",
+    "polygon",
+    "text",
+    "tspan",
+    "linearGradient",
+    "radialGradient",
+    "stop",
+    "use",
+    "a",
+];
+
+/// Elements for drawings with everything that doesn't move
+const STATIC_ELEMENTS: [&str; 38] = [
+    "symbol",
+    "clipPath",
+    "mask",
+    "pattern",
+    "marker",
+    "image",
+    "switch",
+    "textPath",
+    "view",
+    "style",
+    "filter",
+    "feBlend",
+    "feColorMatrix",
+    "feComponentTransfer",
+    "feComposite",
+    "feConvolveMatrix",
+    "feDiffuseLighting",
+    "feDisplacementMap",
+    "feDistantLight",
+    "feDropShadow",
+    "feFlood",
+    "feFuncA",
+    "feFuncB",
+    "feFuncG",
+    "feFuncR",
+    "feGaussianBlur",
+    "feImage",
+    "feMerge",
+    "feMergeNode",
+    "feMorphology",
+    "feOffset",
+    "fePointLight",
+    "feSpecularLighting",
+    "feSpotLight",
+    "feTile",
+    "feTurbulence",
+    "font-face",
+    "solidColor",
+];
+
+const ANIMATION_ELEMENTS: [&str; 5] = [
+    "animate",
+    "animateMotion",
+    "animateTransform",
+    "set",
+    "mpath",
+];
+
+/// Attributes in every profile
+const TINY_ATTRIBUTES: [&str; 92] = [
+    "id",
+    "class",
+    "lang",
+    "xml:lang",
+    "xml:space",
+    "xmlns",
+    "xmlns:xlink",
+    "href",
+    "xlink:href",
+    "xlink:title",
+    "target",
+    "version",
+    "baseProfile",
+    "width",
+    "height",
+    "viewBox",
+    "preserveAspectRatio",
+    "x",
+    "y",
+    "x1",
+    "y1",
+    "x2",
+    "y2",
+    "cx",
+    "cy",
+    "r",
+    "rx",
+    "ry",
+    "fx",
+    "fy",
+    "fr",
+    "d",
+    "points",
+    "pathLength",
+    "transform",
+    "dx",
+    "dy",
+    "rotate",
+    "textLength",
+    "lengthAdjust",
+    "offset",
+    "gradientUnits",
+    "gradientTransform",
+    "spreadMethod",
+    "fill",
+    "fill-opacity",
+    "fill-rule",
+    "stroke",
+    "stroke-width",
+    "stroke-opacity",
+    "stroke-linecap",
+    "stroke-linejoin",
+    "stroke-miterlimit",
+    "stroke-dasharray",
+    "stroke-dashoffset",
+    "opacity",
+    "color",
+    "display",
+    "visibility",
+    "stop-color",
+    "stop-opacity",
+    "font-family",
+    "font-size",
+    "font-style",
+    "font-weight",
+    "font-variant",
+    "font-stretch",
+    "text-anchor",
+    "text-decoration",
+    "text-rendering",
+    "dominant-baseline",
+    "alignment-baseline",
+    "baseline-shift",
+    "letter-spacing",
+    "word-spacing",
+    "writing-mode",
+    "direction",
+    "unicode-bidi",
+    "shape-rendering",
+    "image-rendering",
+    "color-interpolation",
+    "color-rendering",
+    "vector-effect",
+    "paint-order",
+    "solid-color",
+    "solid-opacity",
+    "clip-rule",
+    "overflow",
+    "role",
+    "aria-label",
+    "aria-hidden",
+    "focusable",
+];
+
+/// Attributes for drawings with everything that doesn't move
+const STATIC_ATTRIBUTES: [&str; 77] = [
+    "clip-path",
+    "clipPathUnits",
+    "mask",
+    "maskUnits",
+    "maskContentUnits",
+    "filter",
+    "filterUnits",
+    "primitiveUnits",
+    "marker",
+    "marker-start",
+    "marker-mid",
+    "marker-end",
+    "markerUnits",
+    "markerWidth",
+    "markerHeight",
+    "refX",
+    "refY",
+    "orient",
+    "patternUnits",
+    "patternContentUnits",
+    "patternTransform",
+    "startOffset",
+    "method",
+    "spacing",
+    "side",
+    "flood-color",
+    "flood-opacity",
+    "lighting-color",
+    "color-interpolation-filters",
+    "in",
+    "in2",
+    "result",
+    "stdDeviation",
+    "mode",
+    "operator",
+    "k1",
+    "k2",
+    "k3",
+    "k4",
+    "type",
+    "values",
+    "tableValues",
+    "slope",
+    "intercept",
+    "amplitude",
+    "exponent",
+    "scale",
+    "xChannelSelector",
+    "yChannelSelector",
+    "radius",
+    "baseFrequency",
+    "numOctaves",
+    "seed",
+    "stitchTiles",
+    "order",
+    "kernelMatrix",
+    "divisor",
+    "bias",
+    "targetX",
+    "targetY",
+    "edgeMode",
+    "kernelUnitLength",
+    "preserveAlpha",
+    "surfaceScale",
+    "diffuseConstant",
+    "specularConstant",
+    "specularExponent",
+    "limitingConeAngle",
+    "pointsAtX",
+    "pointsAtY",
+    "pointsAtZ",
+    "azimuth",
+    "elevation",
+    "z",
+    "systemLanguage",
+    "requiredFeatures",
+    "requiredExtensions",
+];
+
+const ANIMATION_ATTRIBUTES: [&str; 21] = [
+    "attributeName",
+    "attributeType",
+    "begin",
+    "dur",
+    "end",
+    "min",
+    "max",
+    "restart",
+    "repeatCount",
+    "repeatDur",
+    "calcMode",
+    "keyTimes",
+    "keySplines",
+    "keyPoints",
+    "from",
+    "to",
+    "by",
+    "additive",
+    "accumulate",
+    "path",
+    "values",
+];
+
+/// Image types that can be embedded as data urls, svg images are left out as they could have anything in them
+const DATA_IMAGES: [&str; 5] = [
+    "data:image/png",
+    "data:image/jpeg",
+    "data:image/jpg",
+    "data:image/gif",
+    "data:image/webp",
+];
+
+/// Bits of style sheets that can run code or load things on their own
+const UNSAFE_STYLE: [&str; 6] = [
+    "@import",
+    "expression(",
+    "javascript:",
+    "vbscript:",
+    "-moz-binding",
+    "behavior",
+];
+
+/// Css that takes urls as plain strings as well as in `url()`
+const STRING_URLS: [&str; 3] = ["image-set(", "image(", "src"];
+
+/// Which elements and attributes are allowed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Profile {
+    /// Shapes, paths, text, and gradients, roughly what SVG Tiny has without animation
+    Tiny,
+    /// Everything that doesn't move, adding clipping, masks, patterns, markers, images, style sheets, and filters
+    Static,
+    /// Static plus SMIL animation
+    Animated,
+}
+
+impl Profile {
+    fn allows_element(&self, name: &str) -> bool {
+        TINY_ELEMENTS.contains(&name)
+            || (*self != Profile::Tiny && STATIC_ELEMENTS.contains(&name))
+            || (*self == Profile::Animated && ANIMATION_ELEMENTS.contains(&name))
+    }
+
+    fn allows_attribute(&self, name: &str) -> bool {
+        TINY_ATTRIBUTES.contains(&name)
+            || (*self != Profile::Tiny && STATIC_ATTRIBUTES.contains(&name))
+            || (*self == Profile::Animated && ANIMATION_ATTRIBUTES.contains(&name))
+    }
+}
+
+/// What to let through
+#[derive(Debug, Clone, PartialEq)]
+pub struct Policy {
+    pub profile: Profile,
+    /// Keep links and references to other files and sites, like external images. Script urls are always removed.
+    pub allow_external_references: bool,
+    /// Keep images embedded as png, jpeg, gif, or webp data urls
+    pub allow_data_images: bool,
+    /// Keep style attributes and style sheets, once anything unsafe in them is taken out
+    pub allow_styles: bool,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Policy {
+            profile: Profile::Static,
+            allow_external_references: false,
+            allow_data_images: true,
+            allow_styles: true,
+        }
+    }
+}
+
+/// Why something was removed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Reason {
+    /// The element or attribute isn't in the profile
+    NotAllowed,
+    Script,
+    EventHandler,
+    /// A link or reference to another file or site
+    ExternalReference,
+    /// A `javascript:` url or similar that would run code
+    ScriptUrl,
+    UnsafeStyle,
+    /// An entity declared in the doctype, external ones can read files and internal ones can blow up in size
+    Entity,
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            Reason::NotAllowed => "not allowed",
+            Reason::Script => "script",
+            Reason::EventHandler => "event handler",
+            Reason::ExternalReference => "external reference",
+            Reason::ScriptUrl => "script url",
+            Reason::UnsafeStyle => "unsafe style",
+            Reason::Entity => "entity",
+        };
+        write!(formatter, "{text}")
+    }
+}
+
+/// One thing taken out of a document
+#[derive(Debug, Clone, PartialEq)]
+pub struct Removal {
+    /// Where it was, like `svg/g[2]/a`
+    pub location: String,
+    /// What was removed, `<name>` for an element and the name for an attribute
+    pub item: String,
+    pub reason: Reason,
+}
+
+impl fmt::Display for Removal {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            formatter,
+            "{}: removed {} ({})",
+            self.location, self.item, self.reason
+        )
+    }
+}
+
+/// Everything the sanitiser took out
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Report {
+    pub removed: Vec<Removal>,
+}
+
+impl Report {
+    /// True when nothing needed removing
+    pub fn is_clean(&self) -> bool {
+        self.removed.is_empty()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for removal in &self.removed {
+            writeln!(formatter, "{removal}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Take everything out of a document that isn't allowed by the policy, or that could run code or load something
+/// from somewhere else.
+///
+/// Elements that aren't allowed are removed along with everything in them, and attributes that aren't allowed are
+/// removed from the elements that are. On top of the profile's allowlist scripts, event handlers, `javascript:`
+/// urls, and animations that set links or event handlers are always taken out. References to other files and sites,
+/// in links or in `url()`s, are taken out unless the policy allows them. A root element that isn't allowed is
+/// emptied and turned into an `svg`.
+pub fn sanitize(doc: &mut Element, policy: &Policy) -> Report {
+    let mut report = Report::default();
+    if !policy.profile.allows_element(&doc.name) {
+        report.removed.push(Removal {
+            location: doc.name.clone(),
+            item: format!("<{}>", doc.name),
+            reason: reason_for_element(&doc.name),
+        });
+        *doc = Element::new("svg");
+        return report;
+    }
+    let location = doc.name.clone();
+    clean(doc, &location, policy, &mut report);
+    report
+}
+
+/// Parse and sanitise some svg text. Entities declared in a doctype are removed along with every use of them before
+/// the text is parsed, as they can read files or expand to gigabytes.
+///
+/// ```
+/// use esvg::sanitize::{sanitize_string, Policy, Reason};
+///
+/// let (doc, report) = sanitize_string(
+///     r#"<?xml version="1.0"?>
+/// <!DOCTYPE svg [ <!ENTITY secret SYSTEM "file:///etc/passwd"> ]>
+/// <svg><text>&secret;</text></svg>"#
+///         .to_string(),
+///     &Policy::default(),
+/// )
+/// .unwrap();
+/// assert_eq!(doc.to_string(), "<svg>\n<text />\n</svg>");
+/// assert_eq!(report.removed[0].reason, Reason::Entity);
+/// ```
+pub fn sanitize_string(input: String, policy: &Policy) -> Result<(Element, Report), Error> {
+    let mut report = Report::default();
+    let mut text = input;
+
+    if let Some((start, end)) = doctype(&text) {
+        let declarations = text[start..end].to_string();
+        text.replace_range(start..end, "");
+        for name in entities(&declarations) {
+            text = text.replace(&format!("&{name};"), "");
+            report.removed.push(Removal {
+                location: "doctype".to_string(),
+                item: format!("entity {name}"),
+                reason: Reason::Entity,
+            });
+        }
+    }
+
+    let mut doc = parse_string(text)?;
+    report.removed.extend(sanitize(&mut doc, policy).removed);
+    Ok((doc, report))
+}
+
+fn reason_for_element(name: &str) -> Reason {
+    match name.to_lowercase().as_str() {
+        "script" | "foreignobject" | "iframe" | "embed" | "object" => Reason::Script,
+        _ => Reason::NotAllowed,
+    }
+}
+
+fn clean(element: &mut Element, location: &str, policy: &Policy, report: &mut Report) {
+    let mut remove = |item: String, reason: Reason| {
+        report.removed.push(Removal {
+            location: location.to_string(),
+            item,
+            reason,
+        })
+    };
+
+    let mut names: Vec<String> = element.attributes.keys().cloned().collect();
+    names.sort();
+    for name in names {
+        let value = element.get(&name).unwrap();
+        let lower = name.to_lowercase();
+        let reason = if lower.starts_with("on") {
+            Some(Reason::EventHandler)
+        } else if name == "style" {
+            if policy.allow_styles {
+                let (kept, removed) = clean_style(&value, policy);
+                for (property, reason) in removed {
+                    remove(format!("style {property}"), reason);
+                }
+                match kept {
+                    Some(kept) => _ = element.set("style", kept),
+                    None => _ = element.remove("style"),
+                }
+                continue;
+            }
+            Some(Reason::NotAllowed)
+        } else if !policy.profile.allows_attribute(&name) {
+            Some(Reason::NotAllowed)
+        } else if name == "href" || name == "xlink:href" {
+            check_href(&element.name, &value, policy)
+        } else {
+            check_urls(&value, policy)
+        };
+        if let Some(reason) = reason {
+            element.remove(&name);
+            remove(name, reason);
+        }
+    }
+
+    let mut counts: Vec<(String, usize)> = vec![];
+    let mut kept = vec![];
+    for child in element.children.drain(..) {
+        let Node::Element(mut child) = child else {
+            kept.push(child);
+            continue;
+        };
+        let count = match counts.iter_mut().find(|(n, _)| *n == child.name) {
+            Some((_, count)) => {
+                *count += 1;
+                *count
+            }
+            None => {
+                counts.push((child.name.clone(), 1));
+                1
+            }
+        };
+        let child_location = if count > 1 {
+            format!("{location}/{}[{count}]", child.name)
+        } else {
+            format!("{location}/{}", child.name)
+        };
+
+        let reason = if !policy.profile.allows_element(&child.name)
+            || (child.name == "style" && !policy.allow_styles)
+        {
+            Some(reason_for_element(&child.name))
+        } else if child.name == "style" {
+            let sheet: String = child
+                .children
+                .iter()
+                .filter_map(|c| match c {
+                    Node::Text(t) => Some(t.as_str()),
+                    _ => None,
+                })
+                .collect();
+            unsafe_style(&sheet, policy)
+        } else if ANIMATION_ELEMENTS.contains(&child.name.as_str()) && child.name != "mpath" {
+            animation(&child, policy)
+        } else {
+            None
+        };
+
+        match reason {
+            Some(reason) => report.removed.push(Removal {
+                location: location.to_string(),
+                item: format!("<{}>", child.name),
+                reason,
+            }),
+            None => {
+                clean(&mut child, &child_location, policy, report);
+                kept.push(Node::Element(child));
+            }
+        }
+    }
+    element.children = kept;
+}
+
+/// A url with spaces and control characters taken out and in lower case, the way browsers see the scheme
+fn normalise_url(url: &str) -> String {
+    url.chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect::<String>()
+        .to_lowercase()
+}
+
+/// Why a url can't stay, if it can't. Only urls within the document are always allowed.
+fn check_url(url: &str, policy: &Policy, data_image: bool) -> Option<Reason> {
+    let url = normalise_url(url);
+    if url.starts_with('#') {
+        return None;
+    }
+    if url.starts_with("javascript:") || url.starts_with("vbscript:") {
+        return Some(Reason::ScriptUrl);
+    }
+    if url.starts_with("data:") {
+        return match data_image
+            && policy.allow_data_images
+            && DATA_IMAGES.iter().any(|d| url.starts_with(d))
+        {
+            true => None,
+            false => Some(Reason::ExternalReference),
+        };
+    }
+    // anything with a scheme other than the web ones could do anything
+    let scheme = url
+        .split_once(':')
+        .map(|(s, _)| s)
+        .filter(|s| !s.contains('/'));
+    let web = matches!(scheme, None | Some("http") | Some("https"));
+    match policy.allow_external_references && web {
+        true => None,
+        false => Some(Reason::ExternalReference),
+    }
+}
+
+fn check_href(element: &str, href: &str, policy: &Policy) -> Option<Reason> {
+    check_url(href, policy, element == "image" || element == "feImage")
+}
+
+/// The targets of any `url()`s in a value
+fn urls(value: &str) -> Vec<String> {
+    let lower = value.to_lowercase();
+    let mut result = vec![];
+    let mut from = 0;
+    while let Some(start) = lower[from..].find("url(").map(|s| s + from) {
+        let end = lower[start..].find(')').map_or(lower.len(), |e| e + start);
+        result.push(
+            value[start + 4..end]
+                .trim()
+                .trim_matches(|c| c == '\'' || c == '"')
+                .to_string(),
+        );
+        from = end;
+    }
+    result
+}
+
+fn check_urls(value: &str, policy: &Policy) -> Option<Reason> {
+    if normalise_url(value).contains("javascript:") {
+        return Some(Reason::ScriptUrl);
+    }
+    urls(value)
+        .iter()
+        .find_map(|url| check_url(url, policy, false))
+}
+
+/// Css with comments taken out and escapes turned back into the characters they stand for, so `u\72l(` is seen as
+/// the `url(` a browser would see
+fn decode_css(css: &str) -> String {
+    let mut result = String::with_capacity(css.len());
+    let mut chars = css.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut last = ' ';
+                for c in chars.by_ref() {
+                    if last == '*' && c == '/' {
+                        break;
+                    }
+                    last = c;
+                }
+            }
+            '\\' => {
+                let mut hex = String::new();
+                while hex.len() < 6 && chars.peek().is_some_and(|c| c.is_ascii_hexdigit()) {
+                    hex.push(chars.next().unwrap());
+                }
+                if hex.is_empty() {
+                    // an escaped newline is a line continuation, anything else stands for itself
+                    match chars.next() {
+                        Some('\n') | None => {}
+                        Some(c) => result.push(c),
+                    }
+                    continue;
+                }
+                // one space after a hex escape ends it
+                if chars.peek().is_some_and(|c| c.is_whitespace()) {
+                    chars.next();
+                }
+                let code = u32::from_str_radix(&hex, 16).unwrap_or(0xfffd);
+                result.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+            }
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+/// The quoted strings in some css
+fn strings(css: &str) -> Vec<String> {
+    let mut result = vec![];
+    let mut rest = css;
+    while let Some(start) = rest.find(['"', '\'']) {
+        let quote = rest[start..].chars().next().unwrap();
+        let after = &rest[start + 1..];
+        let end = after.find(quote).unwrap_or(after.len());
+        result.push(after[..end].to_string());
+        rest = &after[(end + 1).min(after.len())..];
+    }
+    result
+}
+
+/// Anything in some css that isn't safe. Functions and rules that take urls as plain strings, like `image-set` and
+/// `src`, have every string in them checked as a url.
+fn unsafe_style(css: &str, policy: &Policy) -> Option<Reason> {
+    let css = decode_css(css);
+    let normal = normalise_url(&css);
+    if UNSAFE_STYLE.iter().any(|u| normal.contains(u)) {
+        return Some(Reason::UnsafeStyle);
+    }
+    if let Some(reason) = check_urls(&css, policy) {
+        return Some(reason);
+    }
+    if STRING_URLS.iter().any(|u| normal.contains(u)) {
+        return strings(&css)
+            .iter()
+            .find_map(|url| check_url(url, policy, false));
+    }
+    None
+}
+
+/// The declarations of a style attribute that are safe, if there are any, and those that were taken out
+fn clean_style(style: &str, policy: &Policy) -> (Option<String>, Vec<(String, Reason)>) {
+    let mut kept = vec![];
+    let mut removed = vec![];
+    for declaration in style.split(';').filter(|d| !d.trim().is_empty()) {
+        let property = declaration
+            .split_once(':')
+            .map_or(declaration, |(p, _)| p)
+            .trim();
+        let reason = if property.to_lowercase().starts_with("on") {
+            Some(Reason::EventHandler)
+        } else {
+            unsafe_style(declaration, policy)
+        };
+        match reason {
+            Some(reason) => removed.push((property.to_string(), reason)),
+            None => kept.push(declaration),
+        }
+    }
+    ((!kept.is_empty()).then(|| kept.join(";")), removed)
+}
+
+/// Animations can change any attribute, so ones that change links, in any namespace, or event handlers are as bad as
+/// scripts. The values they animate through are checked like any other url.
+fn animation(element: &Element, policy: &Policy) -> Option<Reason> {
+    let target = element
+        .get("attributeName")
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase();
+    if target == "href" || target.ends_with(":href") || target.starts_with("on") {
+        return Some(Reason::Script);
+    }
+    ["values", "from", "to", "by"]
+        .iter()
+        .filter_map(|name| element.get(*name))
+        .flat_map(|value| value.split(';').map(|v| v.to_string()).collect::<Vec<_>>())
+        .find_map(|value| match has_scheme(&value) {
+            true => check_url(&value, policy, false),
+            false => check_urls(&value, policy),
+        })
+}
+
+/// true if a value starts with a url scheme, like `https:` or `javascript:`
+fn has_scheme(value: &str) -> bool {
+    let value = normalise_url(value);
+    value.split_once(':').is_some_and(|(scheme, _)| {
+        scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    })
+}
+
+/// Where the doctype is in some xml, as a byte range
+fn doctype(text: &str) -> Option<(usize, usize)> {
+    let start = text.to_uppercase().find("<!DOCTYPE")?;
+    let mut depth = 0;
+    for (i, c) in text[start..].char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            '>' if depth <= 0 => return Some((start, start + i + 1)),
+            _ => {}
+        }
+    }
+    Some((start, text.len()))
+}
+
+/// The names of the entities declared in a doctype
+fn entities(doctype: &str) -> Vec<String> {
+    doctype
+        .split("<!ENTITY")
+        .skip(1)
+        .filter_map(|declaration| {
+            declaration
+                .split_whitespace()
+                .find(|word| *word != "%")
+                .map(|name| name.to_string())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{sanitize, sanitize_string, Policy, Profile, Reason};
+    use crate::read::parse_string;
+    use crate::Element;
+
+    fn clean(text: &str, policy: &Policy) -> (Element, Vec<String>) {
+        let mut doc = parse_string(text.to_string()).unwrap();
+        let report = sanitize(&mut doc, policy);
+        (doc, report.removed.iter().map(|r| r.to_string()).collect())
+    }
+
+    #[test]
+    fn scripts_and_handlers() {
+        let (doc, removed) = clean(
+            r#"<svg xmlns="http://www.w3.org/2000/svg">
+                <g onclick="x()" ONMOUSEOVER="x()" fill="red">
+                    <script>x()</script>
+                    <foreignObject><body /></foreignObject>
+                    <a href=" java&#x09;script:x()"><rect /></a>
+                    <animate attributeName="href" to="javascript:x()" />
+                </g>
+                <rect fill="url(javascript:x())" style="fill:red;background:url(http://evil/x.png);stroke:blue" />
+            </svg>"#,
+            &Policy::default(),
+        );
+        assert_eq!(
+            doc.to_string(),
+            "<svg xmlns=\"http://www.w3.org/2000/svg\">\n<g fill=\"red\">\n<a>\n<rect />\n</a>\n</g>\n\
+             <rect style=\"fill:red;stroke:blue\" />\n</svg>"
+        );
+        assert_eq!(
+            removed,
+            [
+                "svg/g: removed ONMOUSEOVER (event handler)",
+                "svg/g: removed onclick (event handler)",
+                "svg/g: removed <script> (script)",
+                "svg/g: removed <foreignObject> (script)",
+                "svg/g/a: removed href (script url)",
+                "svg/g: removed <animate> (not allowed)",
+                "svg/rect: removed fill (script url)",
+                "svg/rect: removed style background (external reference)",
+            ]
+        );
+    }
+
+    #[test]
+    fn references() {
+        let text = r##"<svg xmlns:xlink="http://www.w3.org/1999/xlink">
+            <use xlink:href="#shape" />
+            <use href="other.svg#shape" />
+            <image href="data:image/png;base64,AAAA" />
+            <image href="data:image/svg+xml;base64,AAAA" />
+            <image href="https://example.com/cat.jpg" />
+            <rect fill="url(#gradient)" filter="url(https://example.com/f.svg#blur)" />
+        </svg>"##;
+        let (doc, removed) = clean(text, &Policy::default());
+        assert_eq!(removed.len(), 4);
+        let hrefs: Vec<Option<String>> = doc
+            .elements()
+            .map(|e| e.get("href").or_else(|| e.get("xlink:href")))
+            .collect();
+        assert_eq!(
+            hrefs,
+            [
+                Some("#shape".to_string()),
+                None,
+                Some("data:image/png;base64,AAAA".to_string()),
+                None,
+                None,
+                None
+            ]
+        );
+
+        let (_, removed) = clean(
+            text,
+            &Policy {
+                allow_external_references: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(removed, ["svg/image[2]: removed href (external reference)"]);
+    }
+
+    #[test]
+    fn profiles() {
+        let text = r#"<svg><style>rect { fill: red }</style><clipPath /><animate attributeName="x" /><rect clip-path="none" /></svg>"#;
+        let names = |doc: &Element| doc.elements().map(|e| e.name.clone()).collect::<Vec<_>>();
+
+        let (doc, _) = clean(
+            text,
+            &Policy {
+                profile: Profile::Animated,
+                ..Default::default()
+            },
+        );
+        assert_eq!(names(&doc), ["style", "clipPath", "animate", "rect"]);
+
+        let (doc, removed) = clean(
+            text,
+            &Policy {
+                profile: Profile::Tiny,
+                ..Default::default()
+            },
+        );
+        assert_eq!(names(&doc), ["rect"]);
+        assert_eq!(removed.len(), 4);
+
+        let (doc, _) = clean(
+            r#"<svg><style>@import url(http://evil/x.css);</style></svg>"#,
+            &Policy::default(),
+        );
+        assert!(doc.children.is_empty());
+
+        let (doc, removed) = clean("<html><svg /></html>", &Policy::default());
+        assert_eq!(doc.to_string(), "<svg />");
+        assert_eq!(removed, ["html: removed <html> (not allowed)"]);
+    }
+
+    #[test]
+    fn animations() {
+        let text = r##"<svg>
+            <animate attributeName="x:href" to="#other" />
+            <set attributeName="fill" to="url(https://evil/f.svg#g)" />
+            <animate attributeName="fill" values="red; javascript:x(); blue" />
+            <set attributeName="class" to="https://evil/" />
+            <animate attributeName="x" from="0" to="10" />
+            <set attributeName="fill" to="url(#gradient)" />
+        </svg>"##;
+        let animated = Policy {
+            profile: Profile::Animated,
+            ..Default::default()
+        };
+        let (doc, removed) = clean(text, &animated);
+        assert_eq!(
+            doc.elements()
+                .map(|e| e.get("attributeName").unwrap())
+                .collect::<Vec<_>>(),
+            ["x", "fill"]
+        );
+        assert_eq!(
+            removed,
+            [
+                "svg: removed <animate> (script)",
+                "svg: removed <set> (external reference)",
+                "svg: removed <animate> (script url)",
+                "svg: removed <set> (external reference)",
+            ]
+        );
+
+        let (_, removed) = clean(
+            text,
+            &Policy {
+                allow_external_references: true,
+                ..animated
+            },
+        );
+        assert_eq!(removed.len(), 2);
+    }
+
+    #[test]
+    fn entities() {
+        let (doc, report) = sanitize_string(
+            r#"<!DOCTYPE svg [
+  <!ENTITY lol "lol">
+  <!ENTITY % external SYSTEM "http://evil/x.dtd">
+]>
+<svg><text>&lol;&lol; &amp; more</text></svg>"#
+                .to_string(),
+            &Policy::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            doc.to_string(),
+            "<svg>\n<text>\n&amp; more\n</text>\n</svg>"
+        );
+        assert_eq!(report.removed.len(), 2);
+        assert!(report.removed.iter().all(|r| r.reason == Reason::Entity));
+        assert_eq!(report.removed[1].item, "entity external");
+        assert!(!report.is_clean());
+    }
+
+    /// Sanitise, write out, read back, and sanitise again, which should find nothing more to take out
+    fn round_trip(text: &str) -> (String, Vec<String>) {
+        let (doc, report) = sanitize_string(text.to_string(), &Policy::default()).unwrap();
+        let written = doc.to_string();
+        let (again, second) = sanitize_string(written.clone(), &Policy::default()).unwrap();
+        assert!(second.is_clean(), "{second}");
+        assert_eq!(again.to_string(), written);
+        (
+            written,
+            report.removed.iter().map(|r| r.to_string()).collect(),
+        )
+    }
+
+    #[test]
+    fn escaping() {
+        let (written, removed) =
+            round_trip("<svg><text>&lt;script&gt;alert(1)&lt;/script&gt;</text></svg>");
+        assert_eq!(
+            written,
+            "<svg>\n<text>\n&lt;script&gt;alert(1)&lt;/script&gt;\n</text>\n</svg>"
+        );
+        assert!(removed.is_empty());
+
+        let (written, _) = round_trip(r#"<svg><rect fill="&quot;' onload='alert(1)" /></svg>"#);
+        assert_eq!(
+            written,
+            "<svg>\n<rect fill=\"&quot;' onload='alert(1)\" />\n</svg>"
+        );
+        let doc = parse_string(written).unwrap();
+        let rect = doc.elements().next().unwrap();
+        assert_eq!(rect.get("onload"), None);
+        assert_eq!(rect.get("fill").unwrap(), "\"' onload='alert(1)");
+    }
+
+    #[test]
+    fn css_escapes() {
+        let (written, removed) = round_trip(
+            r#"<svg>
+                <style>rect{background:u\72l(https://evil/x.png)}</style>
+                <style>@\69mport "https://evil/x.css";</style>
+                <style>rect{fill:u/**/rl(https://evil/y.png)}</style>
+                <rect style="fill:red;background-image:image-set('https://evil/t.png' 1x)" />
+                <text style="font-family:'Open Sans';fill:u\rl(#paint)">hi</text>
+            </svg>"#,
+        );
+        assert_eq!(
+            written,
+            "<svg>\n<rect style=\"fill:red\" />\n\
+             <text style=\"font-family:'Open Sans';fill:u\\rl(#paint)\">\nhi\n</text>\n</svg>"
+        );
+        assert_eq!(
+            removed,
+            [
+                "svg: removed <style> (external reference)",
+                "svg: removed <style> (unsafe style)",
+                "svg: removed <style> (external reference)",
+                "svg/rect: removed style background-image (external reference)",
+            ]
+        );
+    }
+}
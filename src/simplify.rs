@@ -0,0 +1,547 @@
+//! Take out points and lines a plotter doesn't need, so it isn't sent millions of tiny moves or asked to draw the
+//! same line twice
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+use polygonical::point::Point;
+
+use crate::error::Error;
+use crate::length::Length;
+use crate::path::{self, distance};
+use crate::plot::{self, Stroke};
+use crate::Element;
+
+/// Which way to decide what points to take out
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Method {
+    /// Keep the points that are furthest from the line, no point ends up further than the tolerance from the result.
+    /// Good at keeping sharp corners.
+    RamerDouglasPeucker,
+    /// Repeatedly drop the point that makes the smallest triangle with its neighbours, until every triangle is bigger
+    /// than half a square with sides of the tolerance. Gives smoother looking results on noisy lines.
+    Visvalingam,
+}
+
+/// How many points there were before and after simplifying
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Report {
+    pub points_before: usize,
+    pub points_after: usize,
+}
+
+/// Simplify a line with a tolerance in real units, converted to pixels with the dpi. Relative tolerances are an error.
+///
+/// ```
+/// use esvg::length::Length;
+/// use esvg::simplify::{points, Method};
+/// use polygonical::point::Point;
+///
+/// let wobbly: Vec<Point> = (0..=100).map(|i| Point::new(i as f64, (i % 2) as f64 * 0.1)).collect();
+/// let straight = points(&wobbly, Method::RamerDouglasPeucker, Length::mm(0.5), 96).unwrap();
+/// assert_eq!(straight, [Point::new(0.0, 0.0), Point::new(100.0, 0.0)]);
+/// ```
+pub fn points(
+    points: &[Point],
+    method: Method,
+    tolerance: Length,
+    dpi: i32,
+) -> Result<Vec<Point>, Error> {
+    Ok(simplify(points, method, tolerance.to_pixels(dpi)?))
+}
+
+/// Simplify a line with a tolerance in pixels
+fn simplify(points: &[Point], method: Method, tolerance: f64) -> Vec<Point> {
+    match method {
+        Method::RamerDouglasPeucker => ramer_douglas_peucker(points, tolerance),
+        Method::Visvalingam => visvalingam(points, tolerance),
+    }
+}
+
+/// Simplify path data. Arcs are turned into lines first, within the same tolerance.
+pub fn data(
+    data: &path::Data,
+    method: Method,
+    tolerance: Length,
+    dpi: i32,
+) -> Result<path::Data, Error> {
+    let px = tolerance.to_pixels(dpi)?;
+    let mut result = path::Data::new();
+    for line in path::flatten(&data.segments()?, px) {
+        let closed = line.len() > 2 && line.first() == line.last();
+        let mut simple = simplify(&line, method, px);
+        if closed {
+            simple.pop();
+        }
+        result.move_to(simple[0]);
+        for p in &simple[1..] {
+            result.line_to(*p);
+        }
+        if closed {
+            result.close();
+        }
+    }
+    Ok(result)
+}
+
+/// Simplify every shape in a document, rewriting them as paths. Groups and layers are kept, see [`crate::plot::optimise`] for
+/// how shapes are rewritten.
+pub fn document(
+    doc: &mut Element,
+    method: Method,
+    tolerance: Length,
+    dpi: i32,
+) -> Result<Report, Error> {
+    let mut report = Report::default();
+    let tolerance = tolerance.to_pixels(dpi)?;
+    plot::rewrite(doc, tolerance, &mut |strokes| {
+        strokes
+            .into_iter()
+            .map(|s| {
+                report.points_before += s.points.len();
+                let simple = simplify(&s.points, method, tolerance);
+                report.points_after += simple.len();
+                Stroke {
+                    points: simple,
+                    style: s.style,
+                }
+            })
+            .collect()
+    })?;
+    Ok(report)
+}
+
+/// Keep the points furthest from the line between the ends, splitting until everything is within tolerance
+///
+/// ```
+/// use esvg::simplify::ramer_douglas_peucker;
+/// use polygonical::point::Point;
+///
+/// let corner = [Point::new(0.0, 0.0), Point::new(5.0, 0.01), Point::new(10.0, 0.0), Point::new(10.0, 10.0)];
+/// assert_eq!(ramer_douglas_peucker(&corner, 0.1).len(), 3);
+/// ```
+pub fn ramer_douglas_peucker(points: &[Point], tolerance: f64) -> Vec<Point> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+
+    // a stack rather than recursion as generated lines can be very long
+    let mut stack = vec![(0, points.len() - 1)];
+    while let Some((start, end)) = stack.pop() {
+        let mut furthest = (0, 0.0);
+        for (i, p) in points.iter().enumerate().take(end).skip(start + 1) {
+            let d = segment_distance(*p, points[start], points[end]);
+            if d > furthest.1 {
+                furthest = (i, d);
+            }
+        }
+        if furthest.1 > tolerance {
+            keep[furthest.0] = true;
+            stack.push((start, furthest.0));
+            stack.push((furthest.0, end));
+        }
+    }
+
+    points
+        .iter()
+        .zip(keep)
+        .filter_map(|(p, k)| k.then_some(*p))
+        .collect()
+}
+
+/// Drop the point with the smallest triangle until they are all bigger than `tolerance * tolerance / 2`
+///
+/// ```
+/// use esvg::simplify::visvalingam;
+/// use polygonical::point::Point;
+///
+/// let bump = [Point::new(0.0, 0.0), Point::new(5.0, 0.01), Point::new(10.0, 0.0), Point::new(10.0, 10.0)];
+/// assert_eq!(visvalingam(&bump, 0.5).len(), 3);
+/// ```
+pub fn visvalingam(points: &[Point], tolerance: f64) -> Vec<Point> {
+    let n = points.len();
+    if n < 3 {
+        return points.to_vec();
+    }
+    let threshold = tolerance * tolerance / 2.0;
+
+    let mut previous: Vec<usize> = (0..n).map(|i| i.saturating_sub(1)).collect();
+    let mut next: Vec<usize> = (0..n).map(|i| (i + 1).min(n - 1)).collect();
+    let mut areas = vec![f64::INFINITY; n];
+    let mut removed = vec![false; n];
+
+    // areas are never negative so their bits sort the same way they do
+    let mut heap = BinaryHeap::new();
+    for i in 1..n - 1 {
+        areas[i] = triangle_area(points[i - 1], points[i], points[i + 1]);
+        heap.push(Reverse((areas[i].to_bits(), i)));
+    }
+
+    while let Some(Reverse((bits, i))) = heap.pop() {
+        let area = f64::from_bits(bits);
+        if removed[i] || area != areas[i] {
+            // out of date entry
+            continue;
+        }
+        if area >= threshold {
+            break;
+        }
+
+        removed[i] = true;
+        let (p, q) = (previous[i], next[i]);
+        next[p] = q;
+        previous[q] = p;
+
+        // a neighbour can't end up smaller than the point just taken out, or it would be dropped out of order
+        for j in [p, q] {
+            if j != 0 && j != n - 1 {
+                areas[j] = triangle_area(points[previous[j]], points[j], points[next[j]]).max(area);
+                heap.push(Reverse((areas[j].to_bits(), j)));
+            }
+        }
+    }
+
+    points
+        .iter()
+        .zip(removed)
+        .filter_map(|(p, r)| (!r).then_some(*p))
+        .collect()
+}
+
+/// Remove lines that are drawn more than once in a document, keeping the first time each is drawn.
+///
+/// Segments that lie within tolerance of an earlier segment with the same stroke in the same group are cut back to
+/// the parts that aren't covered, anything shorter than the tolerance left over is dropped. The shapes are rewritten
+/// as paths, see [`crate::plot::optimise`]. Returns the length of line removed in user units.
+///
+/// ```
+/// use esvg::length::Length;
+/// use esvg::{shapes, simplify};
+/// use polygonical::point::Point;
+///
+/// // two outlined squares side by side share an edge
+/// let mut doc = esvg::Element::new("svg");
+/// doc.set("stroke", "black").set("fill", "none");
+/// doc.add(&shapes::rectangle(Point::new(10.0, 10.0), 20.0, 20.0));
+/// doc.add(&shapes::rectangle(Point::new(30.0, 10.0), 20.0, 20.0));
+///
+/// let removed = simplify::dedupe(&mut doc, Length::px(0.1), 96).unwrap();
+/// assert_eq!(removed, 20.0);
+/// ```
+pub fn dedupe(doc: &mut Element, tolerance: Length, dpi: i32) -> Result<f64, Error> {
+    let tolerance = tolerance.to_pixels(dpi)?;
+    let mut removed = 0.0;
+    plot::rewrite(doc, tolerance, &mut |strokes| {
+        let (strokes, length) = remove_overlaps(strokes, tolerance);
+        removed += length;
+        strokes
+    })?;
+    Ok(removed)
+}
+
+/// A segment that has been drawn, and which style it was drawn with
+struct Drawn {
+    from: Point,
+    to: Point,
+    style: usize,
+}
+
+fn remove_overlaps(strokes: Vec<Stroke>, tolerance: f64) -> (Vec<Stroke>, f64) {
+    let mut styles: Vec<Vec<(String, String)>> = vec![];
+    let (mut count, mut total) = (0, 0.0);
+    for s in &strokes {
+        for pair in s.points.windows(2) {
+            count += 1;
+            total += distance(pair[0], pair[1]);
+        }
+    }
+    // cells about the size of a segment keep the number of cells each one touches small
+    let cell = (total / count.max(1) as f64).max(tolerance * 4.0).max(1e-6);
+    let mut grid: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+    let mut drawn: Vec<Drawn> = vec![];
+    let mut seen: Vec<usize> = vec![];
+    let mut removed = 0.0;
+
+    let mut result = vec![];
+    for s in strokes {
+        let style = match styles.iter().position(|st| *st == s.style) {
+            Some(i) => i,
+            None => {
+                styles.push(s.style.clone());
+                styles.len() - 1
+            }
+        };
+
+        let mut current: Vec<Point> = vec![];
+        for pair in s.points.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            let length = distance(a, b);
+            if length == 0.0 {
+                continue;
+            }
+
+            let cells = cells_along(a, b, cell);
+            let mut covered = vec![];
+            for c in &cells {
+                for dx in -1..=1 {
+                    for dy in -1..=1 {
+                        for &i in grid.get(&(c.0 + dx, c.1 + dy)).into_iter().flatten() {
+                            if seen.get(i) == Some(&drawn.len()) || drawn[i].style != style {
+                                continue;
+                            }
+                            seen.resize(seen.len().max(i + 1), usize::MAX);
+                            seen[i] = drawn.len();
+                            if let Some(span) = overlap(a, b, &drawn[i], tolerance) {
+                                covered.push(span);
+                            }
+                        }
+                    }
+                }
+            }
+
+            let pieces = uncovered(covered);
+            let mut kept_length = 0.0;
+            for (t0, t1) in pieces {
+                if (t1 - t0) * length <= tolerance {
+                    continue;
+                }
+                kept_length += (t1 - t0) * length;
+                let start = lerp(a, b, t0);
+                let end = lerp(a, b, t1);
+                let continues = t0 == 0.0 && current.last().is_some_and(|p| *p == a);
+                if !continues {
+                    finish(&mut current, &s.style, &mut result);
+                    current.push(start);
+                }
+                current.push(end);
+            }
+            removed += length - kept_length;
+            if kept_length < length {
+                // anything cut short can't be continued from
+                if current.last().is_some_and(|p| *p != b) {
+                    finish(&mut current, &s.style, &mut result);
+                }
+            }
+
+            for c in cells {
+                grid.entry(c).or_default().push(drawn.len());
+            }
+            drawn.push(Drawn {
+                from: a,
+                to: b,
+                style,
+            });
+        }
+        finish(&mut current, &s.style, &mut result);
+    }
+
+    (result, removed)
+}
+
+fn finish(current: &mut Vec<Point>, style: &[(String, String)], result: &mut Vec<Stroke>) {
+    if current.len() > 1 {
+        result.push(Stroke {
+            points: std::mem::take(current),
+            style: style.to_vec(),
+        });
+    } else {
+        current.clear();
+    }
+}
+
+/// The grid cells a segment passes through, found by stepping along it half a cell at a time
+fn cells_along(a: Point, b: Point, cell: f64) -> Vec<(i64, i64)> {
+    let steps = (distance(a, b) / (cell / 2.0)).ceil().max(1.0) as usize;
+    let mut result: Vec<(i64, i64)> = vec![];
+    for i in 0..=steps {
+        let p = lerp(a, b, i as f64 / steps as f64);
+        let c = ((p.x / cell).floor() as i64, (p.y / cell).floor() as i64);
+        if !result.contains(&c) {
+            result.push(c);
+        }
+    }
+    result
+}
+
+/// The part of a to b, as fractions along it, that an earlier segment lies on top of
+fn overlap(a: Point, b: Point, other: &Drawn, tolerance: f64) -> Option<(f64, f64)> {
+    let dx = b.x - a.x;
+    let dy = b.y - a.y;
+    let length = dx.hypot(dy);
+    let across = |p: Point| ((p.x - a.x) * dy - (p.y - a.y) * dx).abs() / length;
+    if across(other.from) > tolerance || across(other.to) > tolerance {
+        return None;
+    }
+
+    let along = |p: Point| ((p.x - a.x) * dx + (p.y - a.y) * dy) / (length * length);
+    let (t0, t1) = (along(other.from), along(other.to));
+    let (lo, hi) = (t0.min(t1).max(0.0), t0.max(t1).min(1.0));
+    (hi > lo).then_some((lo, hi))
+}
+
+/// The parts of zero to one not in any of the spans
+fn uncovered(mut covered: Vec<(f64, f64)>) -> Vec<(f64, f64)> {
+    covered.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut result = vec![];
+    let mut at = 0.0;
+    for (lo, hi) in covered {
+        if lo > at {
+            result.push((at, lo));
+        }
+        at = f64::max(at, hi);
+    }
+    if at < 1.0 {
+        result.push((at, 1.0));
+    }
+    result
+}
+
+fn lerp(a: Point, b: Point, t: f64) -> Point {
+    if t == 0.0 {
+        a
+    } else if t == 1.0 {
+        b
+    } else {
+        Point::new(a.x + (b.x - a.x) * t, a.y + (b.y - a.y) * t)
+    }
+}
+
+/// Distance from p to the closest point on the segment a to b
+fn segment_distance(p: Point, a: Point, b: Point) -> f64 {
+    let dx = b.x - a.x;
+    let dy = b.y - a.y;
+    let length_squared = dx * dx + dy * dy;
+    if length_squared == 0.0 {
+        return distance(p, a);
+    }
+    let t = (((p.x - a.x) * dx + (p.y - a.y) * dy) / length_squared).clamp(0.0, 1.0);
+    distance(p, Point::new(a.x + dx * t, a.y + dy * t))
+}
+
+fn triangle_area(a: Point, b: Point, c: Point) -> f64 {
+    ((b.x - a.x) * (c.y - a.y) - (c.x - a.x) * (b.y - a.y)).abs() / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{data, dedupe, document, ramer_douglas_peucker, visvalingam, Method};
+    use crate::length::Length;
+    use crate::path;
+    use crate::read::parse_string;
+    use crate::shapes;
+    use polygonical::point::Point;
+
+    fn noisy_circle() -> Vec<Point> {
+        (0..=1000)
+            .map(|i| {
+                let a = i as f64 / 1000.0 * std::f64::consts::TAU;
+                let r = 100.0 + if i % 2 == 0 { 0.05 } else { -0.05 };
+                Point::new(a.cos() * r, a.sin() * r)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn simplify_lines() {
+        let circle = noisy_circle();
+        for simple in [
+            ramer_douglas_peucker(&circle, 0.5),
+            visvalingam(&circle, 2.0),
+        ] {
+            assert!(simple.len() < 100, "{}", simple.len());
+            assert!(simple.len() > 10);
+            assert_eq!(simple.first(), circle.first());
+            assert_eq!(simple.last(), circle.last());
+            for p in simple {
+                assert!((p.x.hypot(p.y) - 100.0).abs() < 0.1);
+            }
+        }
+
+        let short = [Point::zero(), Point::new(1.0, 1.0)];
+        assert_eq!(ramer_douglas_peucker(&short, 10.0).len(), 2);
+        assert_eq!(visvalingam(&short, 10.0).len(), 2);
+    }
+
+    #[test]
+    fn simplify_data() {
+        let mut d = path::Data::from_points(&noisy_circle());
+        d.close();
+        let simple = data(&d, Method::RamerDouglasPeucker, Length::mm(0.2), 96).unwrap();
+        let built = simple.build();
+        assert!(built.starts_with("M100.050 0.000 L"));
+        assert!(built.ends_with(" z"));
+        assert!(built.len() < d.build().len() / 10);
+
+        // detail smaller than the three decimals data is built with is kept if the tolerance is small enough
+        let fine = [
+            Point::zero(),
+            Point::new(0.0005, 0.0004),
+            Point::new(0.001, 0.0),
+        ];
+        let tolerance = Length::px(0.0001);
+        let simple = data(
+            &path::Data::from_points(&fine),
+            Method::RamerDouglasPeucker,
+            tolerance,
+            96,
+        )
+        .unwrap();
+        let lines = path::flatten(&simple.segments().unwrap(), 0.0001);
+        assert_eq!(lines[0], fine);
+    }
+
+    #[test]
+    fn simplify_document() {
+        let dense = path::Data::from_points(&noisy_circle()).build();
+        let mut doc = parse_string(format!(
+            r#"<svg><path d="{dense}" stroke="black" fill="none" /><text>hello</text></svg>"#
+        ))
+        .unwrap();
+        let report = document(&mut doc, Method::Visvalingam, Length::mm(1.0), 96).unwrap();
+        assert!(report.points_after < report.points_before);
+        let names: Vec<&str> = doc.elements().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["path", "text"]);
+    }
+
+    #[test]
+    fn remove_duplicates() {
+        // the same circle twice in a grid is drawn once
+        let mut doc = crate::Element::new("svg");
+        let grid = shapes::many_circles(
+            vec![
+                Point::new(0.0, 0.0),
+                Point::new(20.0, 0.0),
+                Point::new(0.0, 0.0),
+            ],
+            10,
+        );
+        doc.set("stroke", "black");
+        doc.add(&grid);
+        let removed = dedupe(&mut doc, Length::px(0.01), 96).unwrap();
+        assert!((removed - 20.0 * std::f64::consts::PI).abs() < 0.5);
+        // the group is kept with the circles rewritten inside it
+        let group = doc.elements().next().unwrap();
+        assert_eq!(group.name, "g");
+        assert_eq!(group.elements().count(), 2);
+
+        // partly overlapping lines keep the part that sticks out, different strokes are left alone
+        let mut doc = parse_string(
+            r#"<svg stroke="black" fill="none">
+  <path d="M0 0 L10 0" />
+  <path d="M5 0.002 L15 0" />
+  <path d="M0 0 L10 0" stroke="red" />
+</svg>"#
+                .to_string(),
+        )
+        .unwrap();
+        let removed = dedupe(&mut doc, Length::px(0.01), 96).unwrap();
+        assert!((removed - 5.0).abs() < 1e-6);
+        let d: Vec<String> = doc.elements().map(|e| e.get("d").unwrap()).collect();
+        assert_eq!(d[1], "M10.000 0.001 L15.000 0.000");
+        assert_eq!(d[2], "M0.000 0.000 L10.000 0.000");
+    }
+}
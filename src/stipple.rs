@@ -0,0 +1,322 @@
+//! Stippling, shading an area with dots whose density follows a function
+use std::collections::HashMap;
+
+use polygonical::point::Point;
+
+use crate::fill::Region;
+use crate::path;
+use crate::shapes;
+use crate::Element;
+
+/// Settings for placing dots
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    /// The same seed always gives the same dots
+    pub seed: u64,
+    /// Distance between dots where the density is 1, lighter areas get further apart so the number of dots in an
+    /// area follows the density
+    pub spacing: f64,
+    /// How many rounds of weighted Voronoi relaxation to do after placing the dots, more evens them out but takes
+    /// longer
+    pub relax: usize,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            seed: 1,
+            spacing: 4.0,
+            relax: 0,
+        }
+    }
+}
+
+/// Dots spread over a region so there are more where the density is higher.
+///
+/// The density function should give 1 for the darkest areas and 0 where there should be no dots, values in between
+/// space the dots out so the number in an area is in proportion. Dots are placed by Poisson disc sampling, so none
+/// are closer than the spacing for where they are, then optionally moved towards the density weighted centre of
+/// their Voronoi cells to even them out.
+///
+/// ```
+/// use esvg::fill::Region;
+/// use esvg::stipple::{dots, Options};
+/// use polygonical::point::Point;
+/// use polygonical::polygon::Polygon;
+///
+/// let square = Polygon::new(vec![
+///     Point::new(0.0, 0.0),
+///     Point::new(100.0, 0.0),
+///     Point::new(100.0, 100.0),
+///     Point::new(0.0, 100.0),
+/// ]);
+/// // darker to the right
+/// let points = dots(&Region::from(&square), |p| p.x / 100.0, &Options::default());
+/// let left = points.iter().filter(|p| p.x < 50.0).count();
+/// assert!(points.len() - left > left * 2);
+/// ```
+pub fn dots<F>(region: &Region, density: F, options: &Options) -> Vec<Point>
+where
+    F: Fn(Point) -> f64,
+{
+    let Some((min, max)) = region.bounds() else {
+        return vec![];
+    };
+    if options.spacing <= 0.0 {
+        return vec![];
+    }
+
+    // the furthest apart two dots need to be checked, anything lighter than this gets no dots
+    let furthest = options.spacing * 16.0;
+    let spacing = |p: Point| {
+        let d = density(p).min(1.0);
+        if d <= 0.0 {
+            None
+        } else {
+            Some(options.spacing / d.sqrt()).filter(|s| *s <= furthest)
+        }
+    };
+
+    let mut random = Random::new(options.seed);
+    let mut grid = Grid::new(options.spacing);
+    let mut points: Vec<Point> = vec![];
+
+    // throw darts, enough that the darkest areas get close to full
+    let area = (max.x - min.x) * (max.y - min.y);
+    let attempts = (area / (options.spacing * options.spacing) * 20.0) as usize;
+    for _ in 0..attempts {
+        let p = Point::new(
+            min.x + random.next() * (max.x - min.x),
+            min.y + random.next() * (max.y - min.y),
+        );
+        let Some(r) = spacing(p) else {
+            continue;
+        };
+        if !region.contains(p) || grid.near(p, r).any(|i| path::distance(points[i], p) < r) {
+            continue;
+        }
+        grid.insert(p, points.len());
+        points.push(p);
+    }
+
+    for _ in 0..options.relax {
+        relax(region, &density, &mut points, options.spacing);
+    }
+    points
+}
+
+/// Move every dot to the density weighted centre of the area closer to it than any other dot
+fn relax<F>(region: &Region, density: &F, points: &mut [Point], spacing: f64)
+where
+    F: Fn(Point) -> f64,
+{
+    let Some((min, max)) = region.bounds() else {
+        return;
+    };
+    let mut grid = Grid::new(spacing);
+    for (i, p) in points.iter().enumerate() {
+        grid.insert(*p, i);
+    }
+
+    let step = spacing / 4.0;
+    let mut totals = vec![(0.0, 0.0, 0.0); points.len()];
+    let mut y = min.y + step / 2.0;
+    while y < max.y {
+        let mut x = min.x + step / 2.0;
+        while x < max.x {
+            let p = Point::new(x, y);
+            let weight = density(p).clamp(0.0, 1.0);
+            if weight > 0.0 && region.contains(p) {
+                if let Some(i) = grid.nearest(p, points) {
+                    totals[i].0 += p.x * weight;
+                    totals[i].1 += p.y * weight;
+                    totals[i].2 += weight;
+                }
+            }
+            x += step;
+        }
+        y += step;
+    }
+
+    for (p, (x, y, weight)) in points.iter_mut().zip(totals) {
+        if weight > 0.0 {
+            let centre = Point::new(x / weight, y / weight);
+            if region.contains(centre) {
+                *p = centre;
+            }
+        }
+    }
+}
+
+/// A circle for each dot, grouped together
+pub fn circles(points: &[Point], radius: i32) -> Element {
+    shapes::many_circles(points.to_vec(), radius)
+}
+
+/// A tiny line for each dot, all in one path, which plotters draw much faster than circles. Most pens leave a dot
+/// for a line shorter than their width.
+///
+/// ```
+/// use esvg::stipple::marks;
+/// use polygonical::point::Point;
+///
+/// let path = marks(&[Point::new(1.0, 1.0), Point::new(5.0, 5.0)], 0.5);
+/// assert_eq!(
+///     path.get("d").unwrap(),
+///     "M1.000 1.000 L1.500 1.000 M5.000 5.000 L5.500 5.000"
+/// );
+/// ```
+pub fn marks(points: &[Point], length: f64) -> Element {
+    let mut data = path::Data::new();
+    for p in points {
+        data.move_to(*p).line_to(Point::new(p.x + length, p.y));
+    }
+    let mut el = data.to_path();
+    el.set("fill", "none");
+    el
+}
+
+/// A small fast random number generator, xorshift64*, so dots come out the same on every platform
+struct Random(u64);
+
+impl Random {
+    fn new(seed: u64) -> Self {
+        // splitmix the seed so small seeds still start well mixed, and zero isn't allowed
+        let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        Random(z.max(1))
+    }
+
+    /// A number from 0 up to but not including 1
+    fn next(&mut self) -> f64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        let value = self.0.wrapping_mul(0x2545_f491_4f6c_dd1d);
+        (value >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Points bucketed by a square grid, for finding the ones nearby quickly
+struct Grid {
+    size: f64,
+    cells: HashMap<(i64, i64), Vec<usize>>,
+}
+
+impl Grid {
+    fn new(size: f64) -> Self {
+        Grid {
+            size,
+            cells: HashMap::new(),
+        }
+    }
+
+    fn cell(&self, p: Point) -> (i64, i64) {
+        (
+            (p.x / self.size).floor() as i64,
+            (p.y / self.size).floor() as i64,
+        )
+    }
+
+    fn insert(&mut self, p: Point, index: usize) {
+        self.cells.entry(self.cell(p)).or_default().push(index);
+    }
+
+    /// Indexes of points in cells within distance of p, which includes some further away
+    fn near(&self, p: Point, distance: f64) -> impl Iterator<Item = usize> + '_ {
+        let (cx, cy) = self.cell(p);
+        let reach = (distance / self.size).ceil() as i64;
+        (cy - reach..=cy + reach)
+            .flat_map(move |y| (cx - reach..=cx + reach).map(move |x| (x, y)))
+            .filter_map(|c| self.cells.get(&c))
+            .flatten()
+            .copied()
+    }
+
+    /// The index of the closest point to p, searching further out until one is found
+    fn nearest(&self, p: Point, points: &[Point]) -> Option<usize> {
+        if points.is_empty() {
+            return None;
+        }
+        let mut reach = 1.0;
+        loop {
+            let best = self
+                .near(p, reach * self.size)
+                .map(|i| (i, path::distance(points[i], p)))
+                .min_by(|a, b| a.1.total_cmp(&b.1));
+            // only certain once the closest is nearer than anything in cells we haven't looked at
+            if let Some((i, d)) = best {
+                if d <= reach * self.size {
+                    return Some(i);
+                }
+            }
+            reach *= 2.0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{dots, Options};
+    use crate::fill::Region;
+    use crate::page::Page;
+    use crate::path::distance;
+    use polygonical::point::Point;
+
+    #[test]
+    fn deterministic() {
+        let page = Page::A6(96);
+        let region = Region::from(&page);
+        let options = Options {
+            spacing: 20.0,
+            ..Default::default()
+        };
+        let first = dots(&region, |_| 1.0, &options);
+        assert_eq!(first, dots(&region, |_| 1.0, &options));
+        assert!(first.iter().all(|p| region.contains(*p)));
+
+        let other = Options { seed: 2, ..options };
+        assert_ne!(first, dots(&region, |_| 1.0, &other));
+    }
+
+    #[test]
+    fn spacing() {
+        let page = Page::A6(96);
+        let region = Region::from(&page);
+        let options = Options {
+            spacing: 10.0,
+            relax: 0,
+            ..Default::default()
+        };
+        let points = dots(&region, |p| if p.y < 200.0 { 1.0 } else { 0.0 }, &options);
+        assert!(points.iter().all(|p| p.y < 200.0));
+        for (i, a) in points.iter().enumerate() {
+            for b in &points[i + 1..] {
+                assert!(distance(*a, *b) >= 10.0);
+            }
+        }
+    }
+
+    #[test]
+    fn relaxing() {
+        let page = Page::A6(96);
+        let region = Region::from(&page);
+        let mut options = Options {
+            spacing: 15.0,
+            ..Default::default()
+        };
+        let placed = dots(&region, |_| 1.0, &options);
+        options.relax = 3;
+        let relaxed = dots(&region, |_| 1.0, &options);
+        assert_eq!(placed.len(), relaxed.len());
+        assert_ne!(placed, relaxed);
+        assert!(relaxed.iter().all(|p| region.contains(*p)));
+
+        // nothing to spread over
+        let nowhere = dots(&region, |_| 0.0, &options);
+        assert_eq!(nowhere, Vec::<Point>::new());
+    }
+}
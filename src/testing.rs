@@ -0,0 +1,621 @@
+//! Helpers for comparing svg trees in tests, without tripping over attribute order, spacing, or rounding
+//!
+//! ```
+//! use esvg::assert_svg_eq;
+//!
+//! let mut doc = esvg::Element::new("svg");
+//! doc.add(esvg::Element::new("path").set("d", "M0,0 L10,10.0000001").set("fill", "none"));
+//! assert_svg_eq!(doc, r#"<svg><path fill="none" d="M 0 0 L 10 10" /></svg>"#);
+//! ```
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::error::Error;
+use crate::read::parse_string;
+use crate::value::{number_length, LISTS};
+use crate::{Element, Node};
+
+/// How close two numbers have to be to count as the same when nothing else is asked for
+pub const EPSILON: f64 = 1e-6;
+
+/// Set this environment variable to rewrite snapshot files with the current output instead of checking them
+pub const UPDATE_SNAPSHOTS: &str = "ESVG_UPDATE_SNAPSHOTS";
+
+/// Something that can be compared as an svg tree, either a tree already or some svg text to parse
+pub trait Document {
+    fn document(&self) -> Result<Element, Error>;
+}
+
+impl Document for Element {
+    fn document(&self) -> Result<Element, Error> {
+        Ok(self.clone())
+    }
+}
+
+impl Document for str {
+    fn document(&self) -> Result<Element, Error> {
+        parse_string(self.to_string())
+    }
+}
+
+impl Document for String {
+    fn document(&self) -> Result<Element, Error> {
+        parse_string(self.clone())
+    }
+}
+
+impl<T: Document + ?Sized> Document for &T {
+    fn document(&self) -> Result<Element, Error> {
+        (**self).document()
+    }
+}
+
+/// One way two trees don't match
+#[derive(Debug, Clone, PartialEq)]
+pub struct Difference {
+    /// Where in the tree, like `svg/g[2]/path#outline`
+    pub location: String,
+    /// What doesn't match
+    pub what: String,
+    pub expected: Option<String>,
+    pub actual: Option<String>,
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "{}: {}", self.location, self.what)?;
+        if let Some(expected) = &self.expected {
+            write!(formatter, "\n    expected: {expected}")?;
+        }
+        if let Some(actual) = &self.actual {
+            write!(formatter, "\n      actual: {actual}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Compare two trees and list everything that is different.
+///
+/// Attribute order doesn't matter, nor does the order of declarations in a style. Numbers match if they are within
+/// epsilon of each other, including each number in path data, points, and transforms. Text is compared after
+/// trimming and comments are ignored as they don't survive reading a file.
+///
+/// ```
+/// use esvg::read::parse_string;
+/// use esvg::testing::compare;
+///
+/// let expected = parse_string(r#"<svg><rect x="10" y="5" /></svg>"#.to_string()).unwrap();
+/// let actual = parse_string(r#"<svg><rect y="5.0" x="10.5" /></svg>"#.to_string()).unwrap();
+/// let differences = compare(&expected, &actual, 0.001);
+/// assert_eq!(differences.len(), 1);
+/// assert_eq!(differences[0].location, "svg/rect");
+/// assert_eq!(differences[0].what, "attribute \"x\" is different");
+/// ```
+pub fn compare(expected: &Element, actual: &Element, epsilon: f64) -> Vec<Difference> {
+    let mut differences = vec![];
+    compare_elements(
+        expected,
+        actual,
+        &name(expected, None),
+        epsilon,
+        &mut differences,
+    );
+    differences
+}
+
+/// Check two documents match, returning a readable description of what is different if they don't. This is what
+/// [`assert_svg_eq!`](crate::assert_svg_eq) uses.
+pub fn check<A, E>(actual: &A, expected: &E, epsilon: f64) -> Result<(), String>
+where
+    A: Document + ?Sized,
+    E: Document + ?Sized,
+{
+    let actual = actual
+        .document()
+        .map_err(|e| format!("could not read the actual svg: {e}"))?;
+    let expected = expected
+        .document()
+        .map_err(|e| format!("could not read the expected svg: {e}"))?;
+
+    let differences = compare(&expected, &actual, epsilon);
+    if differences.is_empty() {
+        return Ok(());
+    }
+    let mut message = format!("svg trees are different in {} places:\n", differences.len());
+    for difference in differences {
+        message = format!("{message}\n{difference}\n");
+    }
+    Err(format!(
+        "{message}\nactual svg:\n{}",
+        actual.to_pretty_string()
+    ))
+}
+
+/// Check a document matches the snapshot saved at path. The first time round there is nothing to compare with so
+/// the document is saved as the snapshot and the check passes, review it and commit it with the test.
+///
+/// Setting the `ESVG_UPDATE_SNAPSHOTS` environment variable saves over existing snapshots instead, for when the
+/// output is meant to change.
+pub fn check_snapshot<A, P>(actual: &A, path: P, epsilon: f64) -> Result<(), String>
+where
+    A: Document + ?Sized,
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    if !path.exists() || env::var_os(UPDATE_SNAPSHOTS).is_some() {
+        let actual = actual
+            .document()
+            .map_err(|e| format!("could not read the actual svg: {e}"))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("could not create {}: {e}", parent.display()))?;
+        }
+        return fs::write(path, actual.to_pretty_string())
+            .map_err(|e| format!("could not write snapshot {}: {e}", path.display()));
+    }
+
+    let expected = fs::read_to_string(path)
+        .map_err(|e| format!("could not read snapshot {}: {e}", path.display()))?;
+    check(actual, &expected, epsilon).map_err(|message| {
+        format!(
+            "does not match snapshot {}, set {UPDATE_SNAPSHOTS} to update it\n\n{message}",
+            path.display()
+        )
+    })
+}
+
+/// Assert two svg trees are the same, ignoring attribute order and formatting and allowing numbers to be a little
+/// out. Either side can be an [`Element`](crate::Element) or svg text. An optional third argument sets how close
+/// numbers have to be, it defaults to [`EPSILON`](crate::testing::EPSILON).
+///
+/// ```should_panic
+/// use esvg::assert_svg_eq;
+///
+/// assert_svg_eq!("<svg><circle r=\"5\" /></svg>", "<svg><circle r=\"5.1\" /></svg>", 0.01);
+/// ```
+#[macro_export]
+macro_rules! assert_svg_eq {
+    ($actual:expr, $expected:expr $(,)?) => {
+        $crate::assert_svg_eq!($actual, $expected, $crate::testing::EPSILON)
+    };
+    ($actual:expr, $expected:expr, $epsilon:expr $(,)?) => {
+        if let Err(message) = $crate::testing::check(&$actual, &$expected, $epsilon) {
+            panic!("{}", message);
+        }
+    };
+}
+
+/// Assert an svg tree matches a snapshot file, saving the snapshot if it doesn't exist yet. See
+/// [`check_snapshot`](crate::testing::check_snapshot).
+#[macro_export]
+macro_rules! assert_svg_snapshot {
+    ($actual:expr, $path:expr $(,)?) => {
+        $crate::assert_svg_snapshot!($actual, $path, $crate::testing::EPSILON)
+    };
+    ($actual:expr, $path:expr, $epsilon:expr $(,)?) => {
+        if let Err(message) = $crate::testing::check_snapshot(&$actual, $path, $epsilon) {
+            panic!("{}", message);
+        }
+    };
+}
+
+fn compare_elements(
+    expected: &Element,
+    actual: &Element,
+    location: &str,
+    epsilon: f64,
+    differences: &mut Vec<Difference>,
+) {
+    let mut difference = |what: String, expected: Option<String>, actual: Option<String>| {
+        differences.push(Difference {
+            location: location.to_string(),
+            what,
+            expected,
+            actual,
+        })
+    };
+
+    if expected.name != actual.name {
+        difference(
+            "element is different".to_string(),
+            Some(format!("<{}>", expected.name)),
+            Some(format!("<{}>", actual.name)),
+        );
+        return;
+    }
+
+    let mut names: Vec<&String> = expected.attributes.keys().collect();
+    names.extend(
+        actual
+            .attributes
+            .keys()
+            .filter(|k| !expected.attributes.contains_key(*k)),
+    );
+    names.sort();
+    for key in names {
+        match (expected.get(key), actual.get(key)) {
+            (Some(e), None) => difference(format!("missing attribute {key:?}"), Some(e), None),
+            (None, Some(a)) => difference(format!("unexpected attribute {key:?}"), None, Some(a)),
+            (Some(e), Some(a)) => {
+                let same = if key == "style" {
+                    same_style(&e, &a, epsilon)
+                } else {
+                    same_value(key, &e, &a, epsilon)
+                };
+                if !same {
+                    difference(format!("attribute {key:?} is different"), Some(e), Some(a));
+                }
+            }
+            (None, None) => {}
+        }
+    }
+
+    let expected_children = children(expected);
+    let actual_children = children(actual);
+    let mut counts: Vec<(&str, usize)> = vec![];
+    for (i, (e, a)) in expected_children.iter().zip(&actual_children).enumerate() {
+        match (e, a) {
+            (Node::Element(e), Node::Element(a)) => {
+                let child = format!("{location}/{}", name(e, Some(index(&mut counts, &e.name))));
+                compare_elements(e, a, &child, epsilon, differences);
+            }
+            (Node::Text(e), Node::Text(a)) => {
+                if e.trim() != a.trim() {
+                    differences.push(Difference {
+                        location: location.to_string(),
+                        what: format!("text {} is different", i + 1),
+                        expected: Some(format!("{:?}", e.trim())),
+                        actual: Some(format!("{:?}", a.trim())),
+                    });
+                }
+            }
+            (e, a) => differences.push(Difference {
+                location: location.to_string(),
+                what: format!("child {} is different", i + 1),
+                expected: Some(describe(e)),
+                actual: Some(describe(a)),
+            }),
+        }
+    }
+    for (i, e) in expected_children
+        .iter()
+        .enumerate()
+        .skip(actual_children.len())
+    {
+        differences.push(Difference {
+            location: location.to_string(),
+            what: format!("missing child {}", i + 1),
+            expected: Some(describe(e)),
+            actual: None,
+        });
+    }
+    for (i, a) in actual_children
+        .iter()
+        .enumerate()
+        .skip(expected_children.len())
+    {
+        differences.push(Difference {
+            location: location.to_string(),
+            what: format!("unexpected child {}", i + 1),
+            expected: None,
+            actual: Some(describe(a)),
+        });
+    }
+}
+
+/// The children worth comparing, comments and blank text are left out
+fn children(element: &Element) -> Vec<&Node> {
+    element
+        .children
+        .iter()
+        .filter(|n| match n {
+            Node::Element(_) => true,
+            Node::Text(t) => !t.trim().is_empty(),
+            Node::Comment(_) => false,
+        })
+        .collect()
+}
+
+/// Count elements of each name as they go past, so locations can say which one
+fn index<'a>(counts: &mut Vec<(&'a str, usize)>, name: &'a str) -> usize {
+    match counts.iter_mut().find(|(n, _)| *n == name) {
+        Some((_, count)) => {
+            *count += 1;
+            *count
+        }
+        None => {
+            counts.push((name, 1));
+            1
+        }
+    }
+}
+
+/// How an element is shown in a location, by id if it has one otherwise by position among elements of the same name
+fn name(element: &Element, index: Option<usize>) -> String {
+    match (element.get("id"), index) {
+        (Some(id), _) => format!("{}#{id}", element.name),
+        (None, Some(i)) if i > 1 => format!("{}[{i}]", element.name),
+        _ => element.name.clone(),
+    }
+}
+
+fn describe(node: &Node) -> String {
+    match node {
+        Node::Element(e) => e.shallow_clone().to_string(),
+        Node::Text(t) => format!("text {:?}", t.trim()),
+        Node::Comment(c) => format!("<!-- {c} -->"),
+    }
+}
+
+fn same_style(expected: &str, actual: &str, epsilon: f64) -> bool {
+    let declarations = |style: &str| {
+        let mut result: Vec<(String, String)> = style
+            .split(';')
+            .filter(|d| !d.trim().is_empty())
+            .map(|d| match d.split_once(':') {
+                Some((k, v)) => (k.trim().to_string(), v.trim().to_string()),
+                None => (d.trim().to_string(), String::new()),
+            })
+            .collect();
+        result.sort();
+        result
+    };
+    let expected = declarations(expected);
+    let actual = declarations(actual);
+    expected.len() == actual.len()
+        && expected
+            .iter()
+            .zip(&actual)
+            .all(|((ek, ev), (ak, av))| ek == ak && same_value(ek, ev, av, epsilon))
+}
+
+/// Path data and other lists of numbers are compared number by number so spacing and commas don't matter
+fn same_value(key: &str, expected: &str, actual: &str, epsilon: f64) -> bool {
+    let path_data = key == "d";
+    if path_data || LISTS.contains(&key) {
+        let expected = tokens(expected, path_data);
+        let actual = tokens(actual, path_data);
+        return expected.len() == actual.len()
+            && expected.iter().zip(&actual).all(|(e, a)| match (e, a) {
+                (Token::Number(e), Token::Number(a)) => (e - a).abs() <= epsilon,
+                // close path means the same either way
+                (Token::Word(e), Token::Word(a)) if e.eq_ignore_ascii_case("z") => {
+                    a.eq_ignore_ascii_case("z")
+                }
+                _ => e == a,
+            });
+    }
+
+    match (number(expected), number(actual)) {
+        (Some((e, eu)), Some((a, au))) => eu == au && (e - a).abs() <= epsilon,
+        _ => words(expected) == words(actual),
+    }
+}
+
+/// Split a value on spaces and put it back together with single spaces
+fn words(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// A number with an optional unit after it, like `10`, `-2.5e3`, or `12px`
+fn number(value: &str) -> Option<(f64, &str)> {
+    let value = value.trim();
+    let end = number_length(value.as_bytes(), 0);
+    let unit = &value[end..];
+    if end == 0 || !unit.chars().all(|c| c.is_ascii_alphabetic() || c == '%') {
+        return None;
+    }
+    value[..end].parse().ok().map(|n| (n, unit))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Word(String),
+    Symbol(char),
+}
+
+/// Break a list like path data or a transform into numbers, words, and anything else, dropping spaces and commas.
+/// In path data every letter is a command of its own and arc flags are single digits, as they can be written
+/// without anything between them.
+fn tokens(value: &str, path_data: bool) -> Vec<Token> {
+    let bytes = value.as_bytes();
+    let mut result = vec![];
+    let mut i = 0;
+    // which argument of the current path command is next, to spot arc flags
+    let mut command = b' ';
+    let mut argument = 0;
+    while i < bytes.len() {
+        let c = bytes[i] as char;
+        if c.is_ascii_whitespace() || c == ',' {
+            i += 1;
+        } else if c.is_ascii_alphabetic() {
+            let start = i;
+            i += 1;
+            if path_data {
+                command = bytes[start].to_ascii_lowercase();
+                argument = 0;
+            } else {
+                while i < bytes.len() && (bytes[i] as char).is_ascii_alphabetic() {
+                    i += 1;
+                }
+            }
+            result.push(Token::Word(value[start..i].to_string()));
+        } else {
+            let flag = path_data && command == b'a' && (argument % 7 == 3 || argument % 7 == 4);
+            let end = if flag && (c == '0' || c == '1') {
+                i + 1
+            } else {
+                number_length(bytes, i)
+            };
+            match value[i..end].parse() {
+                Ok(n) if end > i => {
+                    result.push(Token::Number(n));
+                    argument += 1;
+                    i = end;
+                }
+                _ => {
+                    let symbol = value[i..].chars().next().unwrap();
+                    result.push(Token::Symbol(symbol));
+                    i += symbol.len_utf8();
+                }
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{check, check_snapshot, compare, tokens, Token};
+    use crate::read::parse_string;
+    use crate::{Element, Node};
+
+    #[test]
+    fn token_lists() {
+        assert_eq!(
+            tokens("M10-5.5.5L1e2,3z", true),
+            vec![
+                Token::Word("M".to_string()),
+                Token::Number(10.0),
+                Token::Number(-5.5),
+                Token::Number(0.5),
+                Token::Word("L".to_string()),
+                Token::Number(100.0),
+                Token::Number(3.0),
+                Token::Word("z".to_string()),
+            ]
+        );
+        assert_eq!(
+            tokens("translate(1 2)", false),
+            vec![
+                Token::Word("translate".to_string()),
+                Token::Symbol('('),
+                Token::Number(1.0),
+                Token::Number(2.0),
+                Token::Symbol(')'),
+            ]
+        );
+
+        // commands written together and arc flags without spaces
+        let word = |w: &str| Token::Word(w.to_string());
+        assert_eq!(
+            tokens("M0 0zM5 5a2 2 0 0110 10", true),
+            vec![
+                word("M"),
+                Token::Number(0.0),
+                Token::Number(0.0),
+                word("z"),
+                word("M"),
+                Token::Number(5.0),
+                Token::Number(5.0),
+                word("a"),
+                Token::Number(2.0),
+                Token::Number(2.0),
+                Token::Number(0.0),
+                Token::Number(0.0),
+                Token::Number(1.0),
+                Token::Number(10.0),
+                Token::Number(10.0),
+            ]
+        );
+        assert!(check(
+            r#"<svg><path d="M0 0zM5 5a2 2 0 0110 10" /></svg>"#,
+            r#"<svg><path d="M 0 0 Z M 5 5 a 2 2 0 0 1 10 10" /></svg>"#,
+            0.1
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn semantic_equality() {
+        let mut built = Element::new("svg");
+        built
+            .set("width", "210mm")
+            .set("viewBox", "0 0 793.7 1122.5");
+        built.add(
+            Element::new("path")
+                .set("d", "M 0,0 L 10,10.0000004 Z")
+                .set("style", "stroke:red;fill:none;")
+                .set("transform", "translate(10, 20) rotate(45)"),
+        );
+        let mut text = Element::new("text");
+        text.add_node(Node::Text("hi".to_string()));
+        built.add(&text);
+
+        crate::assert_svg_eq!(
+            built,
+            r#"<svg viewBox="0,0,793.7,1122.5" width="210.0mm">
+                <path transform="translate(10 20)rotate(45)" style="fill: none; stroke: red" d="M0 0L10 10z" />
+                <text> hi </text>
+            </svg>"#
+        );
+        // path commands are case sensitive and units have to match
+        assert!(check(
+            r#"<svg><path d="m0 0" /></svg>"#,
+            r#"<svg><path d="M0 0" /></svg>"#,
+            0.1
+        )
+        .is_err());
+        assert!(check(r#"<svg width="10mm" />"#, r#"<svg width="10" />"#, 0.1).is_err());
+    }
+
+    #[test]
+    fn structural_differences() {
+        let expected = parse_string(
+            r#"<svg><g id="a"><rect /><rect x="1" /></g><g><circle r="2" /></g><title>one</title></svg>"#
+                .to_string(),
+        )
+        .unwrap();
+        let actual = parse_string(
+            r#"<svg><g id="a"><rect /><rect x="2" fill="red" /><line /></g><g><ellipse /></g><title>two</title></svg>"#
+                .to_string(),
+        )
+        .unwrap();
+
+        let found: Vec<(String, String)> = compare(&expected, &actual, 0.1)
+            .into_iter()
+            .map(|d| (d.location, d.what))
+            .collect();
+        let expected_differences = [
+            ("svg/g#a/rect[2]", "unexpected attribute \"fill\""),
+            ("svg/g#a/rect[2]", "attribute \"x\" is different"),
+            ("svg/g#a", "unexpected child 3"),
+            ("svg/g[2]/circle", "element is different"),
+            ("svg/title", "text 1 is different"),
+        ];
+        assert_eq!(
+            found,
+            expected_differences
+                .map(|(l, w)| (l.to_string(), w.to_string()))
+                .to_vec()
+        );
+
+        let message = check(&actual, &expected, 0.1).unwrap_err();
+        assert!(message.contains("svg trees are different in 5 places"));
+        assert!(message.contains(
+            "svg/g#a/rect[2]: attribute \"x\" is different\n    expected: 1\n      actual: 2"
+        ));
+    }
+
+    #[test]
+    fn snapshots() {
+        let dir = std::env::temp_dir().join(format!("esvg-snapshot-{}", std::process::id()));
+        let path = dir.join("circle.svg");
+        let mut doc = Element::new("svg");
+        doc.add(Element::new("circle").set("r", 5));
+
+        // first run saves it
+        crate::assert_svg_snapshot!(doc, &path);
+        assert!(path.exists());
+        crate::assert_svg_snapshot!(doc, &path);
+
+        doc.add(&Element::new("rect"));
+        let message = check_snapshot(&doc, &path, 0.1).unwrap_err();
+        assert!(message.contains("unexpected child 2"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,408 @@
+//! Split a drawing that is too big for the printer across several pages that can be taped together
+use polygonical::point::Point;
+
+use crate::error::Error;
+use crate::length::Length;
+use crate::page::Page;
+use crate::shapes;
+use crate::text;
+use crate::{create_document, path, Element};
+
+/// Cut a drawing made for the source page into a grid of target sized pages.
+///
+/// Each page shows the part of the drawing that fits inside the target page's borders, so the printer's margins are
+/// left alone. Neighbouring pages share a strip of the drawing overlap wide, the edge of which is marked with a
+/// dashed line, and alignment crosses are drawn in the middle of each shared strip so that they land on top of each
+/// other when the pages are lined up. Every page is labelled with its row letter and column number in the top
+/// border, with the labels of its neighbours along the matching edges.
+///
+/// Pages are returned row by row from the top left. Both pages keep their physical size, so the dpi of the source and
+/// target can differ. An overlap that is negative, relative, or doesn't leave room on the target page is an
+/// [`Error::InvalidOverlap`].
+///
+/// ```
+/// use esvg::length::Length;
+/// use esvg::page::Page;
+/// use esvg::{create_document, shapes, tile};
+///
+/// let source = Page::A2(96);
+/// let mut doc = create_document(&source);
+/// doc.add(&shapes::circle(source.center(), 300));
+///
+/// let pages = tile::tile(&doc, &source, &Page::A4(96), Length::mm(10.0)).unwrap();
+/// assert_eq!(pages.len(), 9);
+/// ```
+pub fn tile(
+    doc: &Element,
+    source: &Page,
+    target: &Page,
+    overlap: Length,
+) -> Result<Vec<Element>, Error> {
+    let grid = Grid::new(source, target, overlap)?;
+    let mut result = vec![];
+
+    for row in 0..grid.rows {
+        for column in 0..grid.columns {
+            result.push(grid.page(doc, row, column));
+        }
+    }
+
+    Ok(result)
+}
+
+/// The label used for a tile, rows are lettered and columns are numbered from one, so the top left tile is "A1"
+///
+/// ```
+/// assert_eq!(esvg::tile::label(0, 0), "A1");
+/// assert_eq!(esvg::tile::label(27, 4), "AB5");
+/// ```
+pub fn label(row: usize, column: usize) -> String {
+    let mut letters = vec![];
+    let mut r = row + 1;
+    while r > 0 {
+        r -= 1;
+        letters.push((b'A' + (r % 26) as u8) as char);
+        r /= 26;
+    }
+    letters.reverse();
+
+    format!("{}{}", letters.into_iter().collect::<String>(), column + 1)
+}
+
+/// How the source drawing is cut up. All sizes are in source pixels unless they say otherwise.
+struct Grid<'a> {
+    source: &'a Page,
+    target: &'a Page,
+    /// source pixels per target pixel
+    scale: f64,
+    tile_width: f64,
+    tile_height: f64,
+    overlap: f64,
+    rows: usize,
+    columns: usize,
+}
+
+impl<'a> Grid<'a> {
+    fn new(source: &'a Page, target: &'a Page, overlap: Length) -> Result<Grid<'a>, Error> {
+        let scale = source.dpi as f64 / target.dpi as f64;
+        let tile_width = target.display_width_px() * scale;
+        let tile_height = target.display_height_px() * scale;
+        let overlap_px = overlap
+            .to_pixels(source.dpi)
+            .map_err(|_| Error::InvalidOverlap(overlap.to_string()))?;
+
+        if !overlap_px.is_finite()
+            || overlap_px < 0.0
+            || overlap_px >= tile_width
+            || overlap_px >= tile_height
+        {
+            return Err(Error::InvalidOverlap(overlap.to_string()));
+        }
+
+        let count = |size: f64, tile: f64| {
+            if size <= tile {
+                1
+            } else {
+                ((size - overlap_px) / (tile - overlap_px)).ceil() as usize
+            }
+        };
+
+        Ok(Grid {
+            source,
+            target,
+            scale,
+            tile_width,
+            tile_height,
+            overlap: overlap_px,
+            rows: count(source.height, tile_height),
+            columns: count(source.width, tile_width),
+        })
+    }
+
+    /// top left corner of a tile in the source drawing
+    fn origin(&self, row: usize, column: usize) -> Point {
+        Point::new(
+            column as f64 * (self.tile_width - self.overlap),
+            row as f64 * (self.tile_height - self.overlap),
+        )
+    }
+
+    fn page(&self, doc: &Element, row: usize, column: usize) -> Element {
+        let target = self.target;
+        let origin = self.origin(row, column);
+        let clip_id = format!("tile-{}", label(row, column));
+
+        let mut result = create_document(target);
+
+        let mut clip = Element::new("clipPath");
+        clip.set("id", clip_id.as_str());
+        clip.add(&shapes::rectangle(
+            target.center(),
+            target.display_width_px(),
+            target.display_height_px(),
+        ));
+        let mut defs = Element::new("defs");
+        defs.add(&clip);
+        result.add(&defs);
+
+        // Everything in here is in source coordinates
+        let mut content = Element::group();
+        content.set(
+            "transform",
+            format!(
+                "translate({}, {}) scale({}) translate({}, {})",
+                target.borders.left,
+                target.borders.top,
+                1.0 / self.scale,
+                -origin.x,
+                -origin.y
+            ),
+        );
+        for child in &doc.children {
+            content.add_node(child.clone());
+        }
+        content.add(&self.overlap_marks(row, column));
+
+        let mut clipped = Element::group();
+        clipped.set("clip-path", format!("url(#{clip_id})"));
+        clipped.add(&content);
+        result.add(&clipped);
+
+        result.add(&self.labels(row, column));
+
+        result
+    }
+
+    /// Dashed lines along the inner edges of the shared strips and crosses in the middle of them.
+    fn overlap_marks(&self, row: usize, column: usize) -> Element {
+        let origin = self.origin(row, column);
+        let (w, h, o) = (self.tile_width, self.tile_height, self.overlap);
+        let cross = self.source.dpi as f64 / 4.0;
+        let line_width = self.scale;
+
+        let mut group = Element::group();
+        group.set("class", "tile-marks");
+
+        let mut edge = |from: Point, to: Point| {
+            let mut line = path::create(&[from.translate(&origin), to.translate(&origin)]);
+            line.set("stroke", "black");
+            line.set("stroke-width", line_width);
+            line.set(
+                "stroke-dasharray",
+                format!("{} {}", cross / 2.0, cross / 4.0),
+            );
+            group.add(&line);
+        };
+
+        let has_left = column > 0;
+        let has_right = column + 1 < self.columns;
+        let has_top = row > 0;
+        let has_bottom = row + 1 < self.rows;
+
+        if has_left {
+            edge(Point::new(o, 0.0), Point::new(o, h));
+        }
+        if has_right {
+            edge(Point::new(w - o, 0.0), Point::new(w - o, h));
+        }
+        if has_top {
+            edge(Point::new(0.0, o), Point::new(w, o));
+        }
+        if has_bottom {
+            edge(Point::new(0.0, h - o), Point::new(w, h - o));
+        }
+
+        let mut centres = vec![];
+        for fraction in [0.25, 0.75] {
+            if has_left {
+                centres.push(Point::new(o / 2.0, h * fraction));
+            }
+            if has_right {
+                centres.push(Point::new(w - o / 2.0, h * fraction));
+            }
+            if has_top {
+                centres.push(Point::new(w * fraction, o / 2.0));
+            }
+            if has_bottom {
+                centres.push(Point::new(w * fraction, h - o / 2.0));
+            }
+        }
+
+        let size = cross.min(o) / 2.0;
+        for c in centres {
+            let c = c.translate(&origin);
+            let mut data = path::Data::new();
+            data.move_to(Point::new(c.x - size, c.y))
+                .line_to(Point::new(c.x + size, c.y))
+                .move_to(Point::new(c.x, c.y - size))
+                .line_to(Point::new(c.x, c.y + size));
+            let mut mark = data.to_path();
+            mark.set("stroke", "black");
+            mark.set("stroke-width", line_width);
+            group.add(&mark);
+        }
+
+        group
+    }
+
+    /// The name of this tile in the top border and the names of its neighbours along each shared edge.
+    fn labels(&self, row: usize, column: usize) -> Element {
+        let target = self.target;
+        let font_size = target.dpi as f64 / 8.0;
+        let style =
+            format!("font-family:sans-serif;font-size:{font_size}px;fill:black;text-anchor:middle");
+        // keep the labels in the border but never off the page
+        let inset = |border: f64| (border / 2.0).max(font_size);
+
+        let mut group = Element::group();
+        group.set("class", "tile-labels");
+
+        let title = format!(
+            "{} (row {} of {}, column {} of {})",
+            label(row, column),
+            row + 1,
+            self.rows,
+            column + 1,
+            self.columns
+        );
+        group.add(&text::create_text(
+            title,
+            Point::new(target.width / 2.0, inset(target.borders.top)),
+            &style,
+        ));
+
+        let mut neighbour = |r: usize, c: usize, at: Point, rotate: f64| {
+            let mut el = text::create_text(label(r, c), at, &style);
+            if rotate != 0.0 {
+                el.set("transform", format!("rotate({rotate}, {}, {})", at.x, at.y));
+            }
+            group.add(&el);
+        };
+
+        let middle_y = target.height / 2.0;
+        if column > 0 {
+            neighbour(
+                row,
+                column - 1,
+                Point::new(inset(target.borders.left), middle_y),
+                -90.0,
+            );
+        }
+        if column + 1 < self.columns {
+            neighbour(
+                row,
+                column + 1,
+                Point::new(target.width - inset(target.borders.right), middle_y),
+                90.0,
+            );
+        }
+        if row + 1 < self.rows {
+            neighbour(
+                row + 1,
+                column,
+                Point::new(
+                    target.width / 2.0,
+                    target.height - inset(target.borders.bottom),
+                ),
+                0.0,
+            );
+        }
+        if row > 0 {
+            neighbour(
+                row - 1,
+                column,
+                Point::new(target.width / 4.0, inset(target.borders.top)),
+                0.0,
+            );
+        }
+
+        group
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{label, tile};
+    use crate::create_document;
+    use crate::error::Error;
+    use crate::length::Length;
+    use crate::page::{Borders, Page};
+
+    #[test]
+    fn labels() {
+        assert_eq!(label(0, 0), "A1");
+        assert_eq!(label(1, 9), "B10");
+        assert_eq!(label(25, 0), "Z1");
+        assert_eq!(label(26, 0), "AA1");
+    }
+
+    #[test]
+    fn grid_size() {
+        let target = Page::new(
+            Length::inches(5.0),
+            Length::inches(5.0),
+            96,
+            Borders::even(0.5, 96),
+        )
+        .unwrap();
+
+        // 4in printable with 1in overlap moves 3in each tile, so 10in wide needs three columns
+        let source = Page::new(
+            Length::inches(10.0),
+            Length::inches(4.0),
+            96,
+            Borders::even(0.0, 96),
+        )
+        .unwrap();
+        let doc = create_document(&source);
+        let pages = tile(&doc, &source, &target, Length::inches(1.0)).unwrap();
+        assert_eq!(pages.len(), 3);
+
+        let last = pages[2].to_pretty_string();
+        assert!(last.contains("translate(48, 48) scale(1) translate(-576, -0)"));
+        assert!(last.contains("A3 (row 1 of 1, column 3 of 3)"));
+        assert!(last.contains("clip-path=\"url(#tile-A3)\""));
+    }
+
+    #[test]
+    fn different_dpi() {
+        let source = Page::new(
+            Length::inches(8.0),
+            Length::inches(8.0),
+            300,
+            Borders::even(0.0, 300),
+        )
+        .unwrap();
+        let target = Page::new(
+            Length::inches(5.0),
+            Length::inches(5.0),
+            100,
+            Borders::even(0.5, 100),
+        )
+        .unwrap();
+        let doc = create_document(&source);
+        let pages = tile(&doc, &source, &target, Length::inches(0.0)).unwrap();
+        assert_eq!(pages.len(), 4);
+        assert!(pages[3]
+            .to_pretty_string()
+            .contains("translate(50, 50) scale(0.3333333333333333) translate(-1200, -1200)"));
+    }
+
+    #[test]
+    fn bad_overlap() {
+        let page = Page::A4(96);
+        let doc = create_document(&page);
+        assert!(tile(&doc, &page, &page, Length::inches(20.0)).is_err());
+        assert!(tile(&doc, &page, &page, Length::inches(-1.0)).is_err());
+        assert!(tile(&doc, &page, &page, Length::mm(f64::NAN)).is_err());
+
+        // a percentage has nothing to be a percentage of
+        let poster = Page::A2(96);
+        let poster_doc = create_document(&poster);
+        assert!(matches!(
+            tile(&poster_doc, &poster, &page, Length::percent(10.0)),
+            Err(Error::InvalidOverlap(o)) if o == "10%"
+        ));
+    }
+}
@@ -0,0 +1,192 @@
+//! The transform attribute as a matrix
+use std::fmt;
+use std::str::FromStr;
+
+use polygonical::point::Point;
+
+use crate::error::Error;
+
+/// An affine transform, the `matrix(a b c d e f)` form of the transform attribute.
+///
+/// A point is mapped to `(a * x + c * y + e, b * x + d * y + f)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+    pub d: f64,
+    pub e: f64,
+    pub f: f64,
+}
+
+impl Transform {
+    /// A transform that doesn't move anything
+    pub fn identity() -> Self {
+        Transform::new(1.0, 0.0, 0.0, 1.0, 0.0, 0.0)
+    }
+
+    pub fn new(a: f64, b: f64, c: f64, d: f64, e: f64, f: f64) -> Self {
+        Transform { a, b, c, d, e, f }
+    }
+
+    pub fn translate(x: f64, y: f64) -> Self {
+        Transform::new(1.0, 0.0, 0.0, 1.0, x, y)
+    }
+
+    pub fn scale(x: f64, y: f64) -> Self {
+        Transform::new(x, 0.0, 0.0, y, 0.0, 0.0)
+    }
+
+    /// A rotation around the origin, the angle is in degrees like the transform attribute
+    pub fn rotate(angle: f64) -> Self {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        Transform::new(cos, sin, -sin, cos, 0.0, 0.0)
+    }
+
+    /// The transform that applies this one and then the other
+    pub fn then(&self, other: &Transform) -> Transform {
+        Transform {
+            a: other.a * self.a + other.c * self.b,
+            b: other.b * self.a + other.d * self.b,
+            c: other.a * self.c + other.c * self.d,
+            d: other.b * self.c + other.d * self.d,
+            e: other.a * self.e + other.c * self.f + other.e,
+            f: other.b * self.e + other.d * self.f + other.f,
+        }
+    }
+
+    /// Move a point
+    pub fn apply(&self, p: Point) -> Point {
+        Point::new(
+            self.a * p.x + self.c * p.y + self.e,
+            self.b * p.x + self.d * p.y + self.f,
+        )
+    }
+
+    /// How much lengths grow on average, useful for turning a tolerance in page units into local ones
+    pub fn scale_factor(&self) -> f64 {
+        (self.a * self.d - self.b * self.c).abs().sqrt()
+    }
+
+    /// The transform that undoes this one, None if it squashes everything flat
+    pub fn inverse(&self) -> Option<Transform> {
+        let determinant = self.a * self.d - self.b * self.c;
+        if determinant.abs() < 1e-12 {
+            return None;
+        }
+        let (a, b) = (self.d / determinant, -self.b / determinant);
+        let (c, d) = (-self.c / determinant, self.a / determinant);
+        Some(Transform {
+            a,
+            b,
+            c,
+            d,
+            e: -(a * self.e + c * self.f),
+            f: -(b * self.e + d * self.f),
+        })
+    }
+
+    pub fn is_identity(&self) -> bool {
+        *self == Transform::identity()
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::identity()
+    }
+}
+
+/// Parse a transform attribute, the transforms in the list are applied right to left like svg does
+///
+/// ```
+/// use esvg::transform::Transform;
+/// use polygonical::point::Point;
+///
+/// let t: Transform = "translate(10, 0) scale(2)".parse().unwrap();
+/// assert_eq!(t.apply(Point::new(1.0, 1.0)), Point::new(12.0, 2.0));
+/// ```
+impl FromStr for Transform {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidAttribute("transform".to_string(), value.to_string());
+        let mut result = Transform::identity();
+        let mut rest = value.trim();
+
+        while !rest.is_empty() {
+            let (name, after) = rest.split_once('(').ok_or_else(invalid)?;
+            let (args, after) = after.split_once(')').ok_or_else(invalid)?;
+            let args: Vec<f64> = args
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|s| !s.is_empty())
+                .map(f64::from_str)
+                .collect::<Result<_, _>>()
+                .map_err(|_| invalid())?;
+
+            let t = match (name.trim(), &args[..]) {
+                ("matrix", &[a, b, c, d, e, f]) => Transform::new(a, b, c, d, e, f),
+                ("translate", &[x]) => Transform::translate(x, 0.0),
+                ("translate", &[x, y]) => Transform::translate(x, y),
+                ("scale", &[s]) => Transform::scale(s, s),
+                ("scale", &[x, y]) => Transform::scale(x, y),
+                ("rotate", &[a]) => Transform::rotate(a),
+                ("rotate", &[a, x, y]) => Transform::translate(-x, -y)
+                    .then(&Transform::rotate(a))
+                    .then(&Transform::translate(x, y)),
+                ("skewX", &[a]) => Transform::new(1.0, 0.0, a.to_radians().tan(), 1.0, 0.0, 0.0),
+                ("skewY", &[a]) => Transform::new(1.0, a.to_radians().tan(), 0.0, 1.0, 0.0, 0.0),
+                _ => return Err(invalid()),
+            };
+            // later transforms in the list are applied first
+            result = t.then(&result);
+
+            rest = after.trim_start_matches(|c: char| c == ',' || c.is_whitespace());
+        }
+
+        Ok(result)
+    }
+}
+
+impl fmt::Display for Transform {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            formatter,
+            "matrix({} {} {} {} {} {})",
+            self.a, self.b, self.c, self.d, self.e, self.f
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::Transform;
+    use polygonical::point::Point;
+
+    #[test]
+    fn parse() {
+        let t: Transform = "rotate(90, 10, 10)".parse().unwrap();
+        assert_eq!(t.apply(Point::new(20.0, 10.0)), Point::new(10.0, 20.0));
+
+        let t: Transform = "scale(2,3)translate(1 1)".parse().unwrap();
+        assert_eq!(t.apply(Point::zero()), Point::new(2.0, 3.0));
+
+        let t: Transform = "matrix(1 0 0 1 5 6)".parse().unwrap();
+        assert_eq!(t, Transform::translate(5.0, 6.0));
+        assert_eq!(t.to_string(), "matrix(1 0 0 1 5 6)");
+
+        assert!("".parse::<Transform>().unwrap().is_identity());
+        assert!("scale(1, 2, 3)".parse::<Transform>().is_err());
+        assert!("wobble(1)".parse::<Transform>().is_err());
+        assert!("translate(1".parse::<Transform>().is_err());
+    }
+
+    #[test]
+    fn inverse() {
+        let t: Transform = "translate(10, 5) rotate(30) scale(2, 3)".parse().unwrap();
+        let p = Point::new(3.0, -4.0);
+        assert_eq!(t.inverse().unwrap().apply(t.apply(p)), p);
+        assert!(Transform::scale(0.0, 1.0).inverse().is_none());
+    }
+}
@@ -1,32 +1,127 @@
 //! Attribute values and handling different types that can be converted to a value
 use std::fmt;
 
+use crate::error::Error;
+
+/// Attributes that are a single number, or a number and a unit
+pub(crate) const NUMBERS: [&str; 27] = [
+    "x",
+    "y",
+    "width",
+    "height",
+    "rx",
+    "ry",
+    "cx",
+    "cy",
+    "r",
+    "x1",
+    "y1",
+    "x2",
+    "y2",
+    "fx",
+    "fy",
+    "fr",
+    "dx",
+    "dy",
+    "offset",
+    "opacity",
+    "fill-opacity",
+    "stroke-opacity",
+    "stop-opacity",
+    "stroke-width",
+    "stroke-dashoffset",
+    "stroke-miterlimit",
+    "font-size",
+];
+
+/// Attributes that are lists of numbers mixed in with other things
+pub(crate) const LISTS: [&str; 6] = [
+    "points",
+    "transform",
+    "gradientTransform",
+    "patternTransform",
+    "viewBox",
+    "stroke-dasharray",
+];
+
 /// Wrapper type for attribute values to allow setting attributes to floats or ints easily
 #[derive(Debug, Clone)]
 pub struct Value {
     value: String,
+    /// Set for values made from floats, so they can be written again with a different number format
+    number: Option<f64>,
 }
 
 impl Value {
     pub fn to_string_bare(&self) -> String {
         self.value.clone()
     }
+
+    /// This value written with a number format. Numbers in path data, transforms, and other attributes that hold
+    /// numbers are rewritten too, however they were set.
+    pub(crate) fn format_with(&self, name: &str, format: &NumberFormat) -> Result<Value, Error> {
+        let value = match self.number {
+            Some(number) => format.format(number)?,
+            None if name == "d" => format_numbers(&self.value, format, true)?,
+            None if NUMBERS.contains(&name) || LISTS.contains(&name) => {
+                format_numbers(&self.value, format, false)?
+            }
+            None => return Ok(self.clone()),
+        };
+        Ok(Value {
+            value,
+            number: self.number,
+        })
+    }
+}
+
+impl Value {
+    /// Whether this value can go in a document as it is. Numbers that aren't finite can't, whether they were set as
+    /// floats or written into the text of an attribute that holds numbers.
+    pub(crate) fn check(&self, name: &str) -> Result<(), Error> {
+        if let Some(number) = self.number.filter(|n| !n.is_finite()) {
+            return Err(Error::NotFinite(number));
+        }
+        if name == "d" || NUMBERS.contains(&name) || LISTS.contains(&name) {
+            if self.value.contains("NaN") {
+                return Err(Error::NotFinite(f64::NAN));
+            }
+            if self.value.contains("inf") {
+                return Err(Error::NotFinite(f64::INFINITY));
+            }
+        }
+        Ok(())
+    }
 }
 
 impl fmt::Display for Value {
     fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
-        // TODO: handle character escaping
-        if self.value.contains('\"') {
-            write!(formatter, "'{}'", self.value)
-        } else {
-            write!(formatter, "\"{}\"", self.value)
+        write!(formatter, "\"{}\"", escape(&self.value, true))
+    }
+}
+
+/// Escape text so it reads back as the same text. Text nodes need `& < >` escaping, and attribute values, which are
+/// always written in double quotes, need `& < "`.
+pub(crate) fn escape(text: &str, attribute: bool) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' if !attribute => result.push_str("&gt;"),
+            '"' if attribute => result.push_str("&quot;"),
+            _ => result.push(c),
         }
     }
+    result
 }
 
 impl From<String> for Value {
     fn from(other: String) -> Value {
-        Value { value: other }
+        Value {
+            value: other,
+            number: None,
+        }
     }
 }
 
@@ -36,7 +131,8 @@ macro_rules! implement_from_to_string {
             #[inline]
             fn from(inner: $primitive) -> Self {
                 Value {
-                    value: inner.to_string()
+                    value: inner.to_string(),
+                    number: None,
                 }
             }
         })*
@@ -47,6 +143,272 @@ implement_from_to_string! {
     &str,
     i8, i16, i32, i64, isize,
     u8, u16, u32, u64, usize,
-    f32, f64,
     bool,
 }
+
+impl From<f64> for Value {
+    fn from(inner: f64) -> Self {
+        Value {
+            value: inner.to_string(),
+            number: Some(inner),
+        }
+    }
+}
+
+impl From<f32> for Value {
+    fn from(inner: f32) -> Self {
+        // going through the text keeps 0.1 as 0.1 rather than the f64 closest to the f32 closest to 0.1
+        let value = inner.to_string();
+        Value {
+            number: value.parse().ok(),
+            value,
+        }
+    }
+}
+
+/// How numbers are written out, see [`Element::to_pretty_string_with`](crate::Element::to_pretty_string_with).
+///
+/// The default writes the shortest text that reads back as exactly the same number, using an exponent when that is
+/// shorter.
+///
+/// ```
+/// use esvg::value::NumberFormat;
+///
+/// assert_eq!(NumberFormat::default().format(0.1 + 0.2).unwrap(), "0.30000000000000004");
+/// assert_eq!(NumberFormat::decimals(3).format(0.1 + 0.2).unwrap(), "0.3");
+///
+/// let compact = NumberFormat { drop_leading_zero: true, ..NumberFormat::decimals(2) };
+/// assert_eq!(compact.format(-0.5).unwrap(), "-.5");
+/// assert!(compact.format(f64::NAN).is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NumberFormat {
+    /// Round to this many decimal places, or none for the shortest text that reads back as the same number
+    pub decimals: Option<usize>,
+    /// Take zeros off the end of the decimals, and the point if there is nothing left after it
+    pub strip_trailing_zeros: bool,
+    /// Write `0.5` as `.5`
+    pub drop_leading_zero: bool,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        NumberFormat {
+            decimals: None,
+            strip_trailing_zeros: true,
+            drop_leading_zero: false,
+        }
+    }
+}
+
+impl NumberFormat {
+    /// Round to a number of decimal places, without trailing zeros
+    pub fn decimals(places: usize) -> Self {
+        NumberFormat {
+            decimals: Some(places),
+            ..Default::default()
+        }
+    }
+
+    /// Write a number, numbers that aren't finite can't go in an svg so are an error
+    pub fn format(&self, value: f64) -> Result<String, Error> {
+        if !value.is_finite() {
+            return Err(Error::NotFinite(value));
+        }
+        Ok(self.format_unchecked(value))
+    }
+
+    /// Write a number without checking it is finite, for the places that have always written NaN
+    pub(crate) fn format_unchecked(&self, value: f64) -> String {
+        let mut text = match self.decimals {
+            Some(places) => format!("{value:.places$}"),
+            None => value.to_string(),
+        };
+        if self.strip_trailing_zeros && text.contains('.') {
+            text = text.trim_end_matches('0').trim_end_matches('.').to_string();
+        }
+        // rounding can leave a minus sign on nothing
+        if text.starts_with('-') && text[1..].chars().all(|c| c == '0' || c == '.') {
+            text.remove(0);
+        }
+        if self.drop_leading_zero {
+            if let Some(rest) = text.strip_prefix("0.") {
+                text = format!(".{rest}");
+            } else if let Some(rest) = text.strip_prefix("-0.") {
+                text = format!("-.{rest}");
+            }
+        }
+        // very big and very small numbers are shorter with an exponent
+        if self.decimals.is_none() {
+            let exponent = format!("{value:e}");
+            if exponent.len() < text.len() {
+                return exponent;
+            }
+        }
+        text
+    }
+}
+
+/// Write every number in a value with a format, leaving everything else where it is. Path data needs to know about
+/// arc flags, which can be written without anything between them.
+pub(crate) fn format_numbers(
+    value: &str,
+    format: &NumberFormat,
+    path_data: bool,
+) -> Result<String, Error> {
+    let bytes = value.as_bytes();
+    let mut result = String::with_capacity(value.len());
+    let mut i = 0;
+    // which argument of the current path command is next, to spot arc flags
+    let mut command = b' ';
+    let mut argument = 0;
+    // whether the last number written has a point in it, so a number starting with a point can go straight after
+    let mut pointed = false;
+    while i < bytes.len() {
+        let c = bytes[i];
+        if c.is_ascii_alphabetic() {
+            if path_data {
+                command = c.to_ascii_lowercase();
+                argument = 0;
+            }
+            result.push(c as char);
+            i += 1;
+            continue;
+        }
+        let flag = path_data && command == b'a' && (argument % 7 == 3 || argument % 7 == 4);
+        let end = if flag && (c == b'0' || c == b'1') {
+            i + 1
+        } else {
+            number_length(bytes, i)
+        };
+        if end == i {
+            let other = value[i..].chars().next().unwrap();
+            result.push(other);
+            i += other.len_utf8();
+            continue;
+        }
+
+        let number = if flag {
+            value[i..end].to_string()
+        } else {
+            let number: f64 = value[i..end]
+                .parse()
+                .map_err(|_| Error::InvalidAttribute("number".to_string(), value.to_string()))?;
+            format.format(number)?
+        };
+        // numbers that used to be told apart by their points may need something between them now
+        let after_digit = result.ends_with(|c: char| c.is_ascii_digit());
+        if (number.starts_with(|c: char| c.is_ascii_digit()) && after_digit)
+            || (number.starts_with('.') && after_digit && !pointed)
+        {
+            result.push(' ');
+        }
+        pointed = number.contains('.');
+        result.push_str(&number);
+        argument += 1;
+        i = end;
+    }
+    Ok(result)
+}
+
+/// Where the number starting at start ends, which is start if there isn't one. Only one decimal point is taken so
+/// `.5.5` is two numbers the way path data means it.
+pub(crate) fn number_length(bytes: &[u8], start: usize) -> usize {
+    let digits = |mut i: usize| {
+        while i < bytes.len() && bytes[i].is_ascii_digit() {
+            i += 1;
+        }
+        i
+    };
+    let mut i = start;
+    if i < bytes.len() && (bytes[i] == b'-' || bytes[i] == b'+') {
+        i += 1;
+    }
+    let whole = digits(i);
+    let mut end = whole;
+    if end < bytes.len() && bytes[end] == b'.' {
+        end = digits(end + 1);
+    }
+    if end == i || (end == i + 1 && whole == i) {
+        // no digits at all, just a sign or a point
+        return start;
+    }
+    if end < bytes.len() && (bytes[end] == b'e' || bytes[end] == b'E') {
+        let mut e = end + 1;
+        if e < bytes.len() && (bytes[e] == b'-' || bytes[e] == b'+') {
+            e += 1;
+        }
+        let exponent = digits(e);
+        if exponent > e {
+            end = exponent;
+        }
+    }
+    end
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{format_numbers, NumberFormat, Value};
+
+    #[test]
+    fn number_formats() {
+        let fixed = NumberFormat {
+            strip_trailing_zeros: false,
+            ..NumberFormat::decimals(3)
+        };
+        assert_eq!(fixed.format(1.5).unwrap(), "1.500");
+        assert_eq!(fixed.format(-0.0001).unwrap(), "0.000");
+        assert_eq!(NumberFormat::decimals(0).format(2.5).unwrap(), "2");
+        assert_eq!(NumberFormat::default().format(1e21).unwrap(), "1e21");
+        assert_eq!(
+            NumberFormat::default().format(-1.5e-300).unwrap(),
+            "-1.5e-300"
+        );
+        assert_eq!(NumberFormat::default().format(1e5).unwrap(), "1e5");
+        assert_eq!(NumberFormat::default().format(100.0).unwrap(), "100");
+        assert_eq!(NumberFormat::default().format(0.0001).unwrap(), "1e-4");
+        assert_eq!(NumberFormat::default().format(0.25).unwrap(), "0.25");
+        assert_eq!(NumberFormat::default().format(1e-5).unwrap(), "1e-5");
+        assert!(NumberFormat::default().format(f64::INFINITY).is_err());
+    }
+
+    #[test]
+    fn numbers_in_values() {
+        let compact = NumberFormat {
+            drop_leading_zero: true,
+            ..NumberFormat::decimals(2)
+        };
+        assert_eq!(
+            format_numbers("M1.23456.5L-0.0001,2e-1 3.10000", &compact, true).unwrap(),
+            "M1.23.5L0,.2 3.1"
+        );
+        assert_eq!(
+            format_numbers("M1 0.5 L 10.001-0.5", &compact, true).unwrap(),
+            "M1 .5 L 10-.5"
+        );
+        // arc flags can be written without spaces
+        assert_eq!(
+            format_numbers("a5.555 5 0 0110.004 10", &NumberFormat::decimals(1), true).unwrap(),
+            "a5.6 5 0 0 1 10 10"
+        );
+        assert!(format_numbers("1e999", &compact, false).is_err());
+
+        let value = Value::from(0.1 + 0.2);
+        assert_eq!(value.to_string_bare(), "0.30000000000000004");
+        let formatted = value.format_with("opacity", &compact).unwrap();
+        assert_eq!(formatted.to_string_bare(), ".3");
+        assert_eq!(
+            Value::from(0.1f32)
+                .format_with("x", &NumberFormat::default())
+                .unwrap()
+                .to_string_bare(),
+            "0.1"
+        );
+        let id = Value::from("a1.50");
+        assert_eq!(
+            id.format_with("id", &compact).unwrap().to_string_bare(),
+            "a1.50"
+        );
+    }
+}